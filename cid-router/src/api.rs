@@ -1,29 +1,88 @@
+pub mod routing;
 pub mod v1;
 
-use std::{net::SocketAddr, sync::Arc};
+use std::{net::SocketAddr, sync::Arc, time::Duration};
 
 use anyhow::Result;
-use axum::{response::Redirect, routing::get, Router};
-use log::info;
+use api_utils::{shutdown, tls::TlsConfig};
+use axum::{
+    middleware,
+    response::Redirect,
+    routing::{delete, get, post},
+    Router,
+};
 use routes;
+use tower_http::compression::CompressionLayer;
+use tracing::info;
 use utoipa::OpenApi;
 use utoipa_swagger_ui::SwaggerUi;
 
-use crate::context::Context;
+use crate::{
+    auth::{self, ApiKeyScope},
+    context::Context,
+    rate_limit, telemetry,
+};
 
 #[derive(OpenApi)]
 #[openapi(
     paths(
+        routing::v1::providers::get_providers,
+        v1::admin::api_keys::delete_api_key,
+        v1::admin::api_keys::get_api_keys,
+        v1::admin::api_keys::post_api_key,
+        v1::admin::config::get_config_history,
+        v1::admin::config::post_rollback,
+        v1::admin::reload::post_reload,
+        v1::diagnostics::get_egress,
+        v1::diagnostics::get_reachability,
+        v1::health::get_healthz,
+        v1::health::get_readyz,
+        v1::providers::delete_provider,
         v1::providers::get_providers,
+        v1::providers::post_provider,
+        v1::resolve::post_resolve,
         v1::routes::get_routes,
         v1::status::get_status,
+        v1::tickets::get_tickets,
     ),
     components(
         schemas(
+            api_utils::ApiErrorBody,
+            api_utils::ErrorCode,
+            crate::auth::ApiKeyScope,
+            crate::config_history::ConfigDiff,
+            crate::config_history::ConfigRevision,
+            crate::config_history::RevisionSource,
+            crate::crp::Transport,
+            crate::egress::EgressAllowList,
+            crate::egress::EgressHost,
+            routing::v1::providers::PeerRecord,
+            routing::v1::providers::ProviderRecord,
+            routing::v1::providers::ProvidersResponse,
+            routing::v1::providers::RouteRecord,
+            v1::admin::api_keys::ApiKeyInfo,
+            v1::admin::api_keys::ApiKeysResponse,
+            v1::admin::api_keys::CreateApiKeyRequest,
+            v1::admin::api_keys::CreateApiKeyResponse,
+            v1::admin::config::ConfigHistoryResponse,
+            v1::admin::config::RollbackResponse,
+            v1::admin::reload::ReloadResponse,
+            v1::diagnostics::AddressReachability,
+            v1::diagnostics::ProviderReachability,
+            v1::diagnostics::ReachabilityResponse,
+            v1::health::HealthResponse,
+            v1::health::HealthStatus,
+            v1::health::ProviderHealth,
+            v1::providers::ProviderResponse,
             v1::providers::ProvidersResponse,
+            v1::resolve::ResolveRequest,
+            v1::resolve::ResolveResponse,
             v1::routes::RoutesResponse,
             v1::routes::Route,
+            v1::routes::ResolutionHints,
             v1::status::StatusResponse,
+            v1::tickets::TicketsResponse,
+            routes::Route,
             routes::AzureBlobStorageRouteMethod,
             routes::UrlRouteMethod,
             routes::IpfsRouteMethod,
@@ -37,13 +96,39 @@ use crate::context::Context;
 )]
 struct ApiDoc;
 
-pub async fn start(ctx: Arc<Context>) -> Result<()> {
-    let addr = SocketAddr::from(([0, 0, 0, 0], ctx.port));
+/// Serve the API, over HTTPS if TLS is configured, until SIGINT or SIGTERM, then give in-flight
+/// requests up to `shutdown_timeout` to finish
+pub async fn start(
+    ctx: Arc<Context>,
+    tls: Option<TlsConfig>,
+    shutdown_timeout: Duration,
+) -> Result<()> {
+    let addr = SocketAddr::new(ctx.bind_addr, ctx.port);
 
     info!("🚀 Starting CID Router");
-    info!("🚀 HTTP API = {addr}");
+    match &tls {
+        Some(_) => info!("🚀 HTTPS API = {addr}"),
+        None => info!("🚀 HTTP API = {addr}"),
+    }
 
-    let router = Router::new()
+    let router = router(ctx);
+
+    shutdown::serve_with_tls(
+        router,
+        addr,
+        tls.as_ref(),
+        shutdown::signal(),
+        shutdown_timeout,
+    )
+    .await
+}
+
+pub fn router(ctx: Arc<Context>) -> Router {
+    let require_scope =
+        |scope| middleware::from_fn_with_state((ctx.clone(), scope), auth::require_scope);
+    let rate_limit = || middleware::from_fn_with_state(ctx.clone(), rate_limit::limit);
+
+    Router::new()
         .merge(
             SwaggerUi::new("/swagger")
                 .config(utoipa_swagger_ui::Config::default().try_it_out_enabled(true))
@@ -53,16 +138,70 @@ pub async fn start(ctx: Arc<Context>) -> Result<()> {
             "/",
             get(move || async move { Redirect::temporary("/swagger") }),
         )
-        .route("/v1/providers", get(v1::providers::get_providers))
-        .route("/v1/routes/:cid", get(v1::routes::get_routes))
+        .route("/healthz", get(v1::health::get_healthz))
+        .route("/readyz", get(v1::health::get_readyz))
+        .route(
+            "/v1/admin/api-keys",
+            get(v1::admin::api_keys::get_api_keys)
+                .post(v1::admin::api_keys::post_api_key)
+                .route_layer(require_scope(ApiKeyScope::ApiKeys)),
+        )
+        .route(
+            "/v1/admin/api-keys/:name",
+            delete(v1::admin::api_keys::delete_api_key)
+                .route_layer(require_scope(ApiKeyScope::ApiKeys)),
+        )
+        .route(
+            "/v1/admin/config/history",
+            get(v1::admin::config::get_config_history)
+                .route_layer(require_scope(ApiKeyScope::Config)),
+        )
+        .route(
+            "/v1/admin/config/rollback/:revision",
+            post(v1::admin::config::post_rollback).route_layer(require_scope(ApiKeyScope::Config)),
+        )
+        .route(
+            "/v1/admin/reload",
+            post(v1::admin::reload::post_reload).route_layer(require_scope(ApiKeyScope::Config)),
+        )
+        .route("/v1/diagnostics/egress", get(v1::diagnostics::get_egress))
+        .route(
+            "/v1/diagnostics/reachability",
+            get(v1::diagnostics::get_reachability),
+        )
+        .route(
+            "/v1/providers",
+            get(v1::providers::get_providers).merge(
+                post(v1::providers::post_provider)
+                    .route_layer(require_scope(ApiKeyScope::Providers)),
+            ),
+        )
+        .route(
+            "/v1/providers/:id",
+            delete(v1::providers::delete_provider)
+                .route_layer(require_scope(ApiKeyScope::Providers)),
+        )
+        .route(
+            "/routing/v1/providers/:cid",
+            get(routing::v1::providers::get_providers).route_layer(rate_limit()),
+        )
+        .route(
+            "/v1/resolve",
+            post(v1::resolve::post_resolve).route_layer(rate_limit()),
+        )
+        .route(
+            "/v1/routes/:cid",
+            get(v1::routes::get_routes).route_layer(rate_limit()),
+        )
         .route("/v1/status", get(v1::status::get_status))
-        .with_state(ctx);
-
-    axum::Server::bind(&addr)
-        .serve(router.into_make_service())
-        .await?;
-
-    Ok(())
+        .route(
+            "/v1/tickets/:cid",
+            get(v1::tickets::get_tickets).route_layer(rate_limit()),
+        )
+        // gzip or zstd as the request accepts, images and tiny bodies aren't compressed
+        .layer(CompressionLayer::new())
+        .layer(middleware::from_fn(telemetry::trace_request))
+        .with_state(ctx)
 }
 
 pub fn openapi() -> utoipa::openapi::OpenApi {
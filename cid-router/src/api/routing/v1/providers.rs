@@ -0,0 +1,201 @@
+use std::{net::SocketAddr, str::FromStr, sync::Arc};
+
+use anyhow::Result;
+use api_utils::{ApiError, ApiResult, ErrorCode};
+use axum::{
+    extract::{Path, State},
+    http::{
+        header::{ACCEPT, CONTENT_TYPE},
+        HeaderMap,
+    },
+    response::{IntoResponse, Response},
+    Json,
+};
+use cid::{
+    multibase::{self, Base},
+    multihash::Multihash,
+    Cid,
+};
+use iroh_base::ticket::BlobTicket;
+use routes::{IntoRoute, IrohRouteMethod};
+use serde::Serialize;
+use serde_json::Value;
+use utoipa::ToSchema;
+
+use crate::{
+    api::v1::routes::{lookup_routes, Route},
+    auth::bearer_token,
+    context::Context,
+};
+
+const NDJSON: &str = "application/x-ndjson";
+
+/// Protocol of records for iroh nodes, which serve blobs over iroh rather than libp2p
+const IROH_PROTOCOL: &str = "transport-iroh-bytes";
+
+/// Schema of records for routes that aren't to a peer. Delegated routing clients skip records of
+/// schemas they don't know, so these are only of use to clients that know cid-router routes.
+const ROUTE_SCHEMA: &str = "cid-router-route";
+
+#[derive(Serialize, ToSchema)]
+pub struct ProvidersResponse {
+    #[serde(rename = "Providers")]
+    providers: Vec<ProviderRecord>,
+}
+
+/// A provider record of the delegated routing HTTP API
+#[derive(Serialize, ToSchema)]
+#[serde(untagged)]
+pub enum ProviderRecord {
+    Peer(PeerRecord),
+    Route(RouteRecord),
+}
+
+/// A peer the content can be fetched from, with the `peer` schema
+#[derive(Serialize, ToSchema)]
+pub struct PeerRecord {
+    /// Always "peer"
+    #[serde(rename = "Schema")]
+    schema: String,
+    /// libp2p peer ID, for iroh nodes the peer ID of the node's ed25519 key
+    #[serde(rename = "ID")]
+    id: String,
+    /// Multiaddrs the peer is reachable at
+    #[serde(rename = "Addrs")]
+    addrs: Vec<String>,
+    #[serde(rename = "Protocols")]
+    protocols: Vec<String>,
+}
+
+/// A cid-router route that isn't to a peer, with the `cid-router-route` schema
+#[derive(Serialize, ToSchema)]
+pub struct RouteRecord {
+    /// Always "cid-router-route"
+    #[serde(rename = "Schema")]
+    schema: String,
+    #[serde(rename = "Fingerprint")]
+    fingerprint: String,
+    #[serde(rename = "Type")]
+    type_: String,
+    #[serde(rename = "Method")]
+    method: Value,
+}
+
+/// Get delegated routing providers for a CID
+///
+/// The router's routes for a CID as provider records of the IPFS delegated routing HTTP API, so
+/// IPFS nodes can use the router for content routing. Iroh routes become `peer` records and other
+/// routes `cid-router-route` records, which clients that don't know them skip. Responds with
+/// newline delimited JSON when the `Accept` header asks for `application/x-ndjson`.
+#[utoipa::path(
+    get,
+    path = "/routing/v1/providers/{cid}",
+    tag = "/routing/v1/providers/{cid}",
+    responses(
+        (status = 200, description = "Get provider records for a CID", body = ProvidersResponse),
+        (status = 400, description = "Invalid CID", body = ApiErrorBody),
+        (status = 404, description = "No provider records for the CID", body = ApiErrorBody),
+        (status = 502, description = "All providers eligible for the CID failed", body = ApiErrorBody)
+    )
+)]
+pub async fn get_providers(
+    Path(cid): Path<String>,
+    headers: HeaderMap,
+    State(ctx): State<Arc<Context>>,
+) -> ApiResult<Response> {
+    let api_key = ctx.api_keys.authenticate(bearer_token(&headers))?;
+
+    let region = ctx.settings().region;
+
+    let cid = Cid::from_str(&cid)
+        .map_err(|e| ApiError::new(ErrorCode::CidInvalid, format!("invalid cid={cid}: {e}")))?;
+
+    let routes = lookup_routes(&ctx, &cid, api_key.as_ref(), region.as_ref(), false).await?;
+
+    let providers = routes
+        .into_iter()
+        .filter_map(|route| match ProviderRecord::from_route(route) {
+            Ok(record) => Some(record),
+            Err(e) => {
+                tracing::warn!("dropping route for cid={cid} without a provider record: {e}");
+                None
+            }
+        })
+        .collect::<Vec<_>>();
+
+    if providers.is_empty() {
+        return Err(ApiError::new(
+            ErrorCode::NotFound,
+            format!("no providers for cid={cid}"),
+        ));
+    }
+
+    let accepts_ndjson = headers
+        .get(ACCEPT)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.contains(NDJSON));
+
+    if !accepts_ndjson {
+        return Ok(Json(ProvidersResponse { providers }).into_response());
+    }
+
+    let mut body = String::new();
+    for provider in providers {
+        body.push_str(&serde_json::to_string(&provider)?);
+        body.push('\n');
+    }
+
+    Ok(([(CONTENT_TYPE, NDJSON)], body).into_response())
+}
+
+impl ProviderRecord {
+    fn from_route(route: Route) -> Result<Self> {
+        let Route {
+            fingerprint,
+            route: routes::Route { type_, method, .. },
+            ..
+        } = route;
+
+        if type_ != IrohRouteMethod::type_str() {
+            return Ok(Self::Route(RouteRecord {
+                schema: ROUTE_SCHEMA.to_owned(),
+                fingerprint,
+                type_,
+                method,
+            }));
+        }
+
+        let IrohRouteMethod { ticket } = serde_json::from_value(method)?;
+        let node_addr = BlobTicket::from_str(&ticket)?.node_addr().clone();
+
+        Ok(Self::Peer(PeerRecord {
+            schema: "peer".to_owned(),
+            id: ed25519_peer_id(node_addr.node_id.as_bytes())?,
+            addrs: node_addr.direct_addresses().map(quic_multiaddr).collect(),
+            protocols: vec![IROH_PROTOCOL.to_owned()],
+        }))
+    }
+}
+
+/// libp2p peer ID of an ed25519 public key, the identity multihash of the key's protobuf encoding
+/// in base58btc
+fn ed25519_peer_id(public_key: &[u8; 32]) -> Result<String> {
+    // protobuf PublicKey { Type: Ed25519, Data: public_key }
+    let mut encoded_key = vec![0x08, 0x01, 0x12, 0x20];
+    encoded_key.extend_from_slice(public_key);
+
+    let multihash = Multihash::<64>::wrap(0x00, &encoded_key)?;
+
+    // multibase prefixes base58btc with a "z", which peer IDs don't have
+    Ok(multibase::encode(Base::Base58Btc, multihash.to_bytes())[1..].to_owned())
+}
+
+/// Multiaddr of a QUIC socket address
+fn quic_multiaddr(addr: &SocketAddr) -> String {
+    let protocol = match addr {
+        SocketAddr::V4(_) => "ip4",
+        SocketAddr::V6(_) => "ip6",
+    };
+
+    format!("/{protocol}/{}/udp/{}/quic-v1", addr.ip(), addr.port())
+}
@@ -1,3 +1,8 @@
+pub mod admin;
+pub mod diagnostics;
+pub mod health;
 pub mod providers;
+pub mod resolve;
 pub mod routes;
 pub mod status;
+pub mod tickets;
@@ -0,0 +1,140 @@
+use std::sync::Arc;
+
+use api_utils::{ApiError, ApiResult, ErrorCode};
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::{
+    auth::{generate_key, hash_key, ApiKeyScope},
+    config::ApiKeyConfig,
+    context::Context,
+};
+
+#[derive(Serialize, ToSchema)]
+pub struct ApiKeysResponse {
+    keys: Vec<ApiKeyInfo>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ApiKeyInfo {
+    name: String,
+    scopes: Vec<ApiKeyScope>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct CreateApiKeyRequest {
+    name: String,
+    scopes: Vec<ApiKeyScope>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct CreateApiKeyResponse {
+    name: String,
+    /// The key, which isn't kept and can't be retrieved again
+    key: String,
+    scopes: Vec<ApiKeyScope>,
+}
+
+impl From<ApiKeyConfig> for ApiKeyInfo {
+    fn from(key: ApiKeyConfig) -> Self {
+        let ApiKeyConfig { name, scopes, .. } = key;
+
+        Self { name, scopes }
+    }
+}
+
+/// Get API Keys
+#[utoipa::path(
+    get,
+    path = "/v1/admin/api-keys",
+    tag = "/v1/admin/api-keys",
+    responses(
+        (status = 200, description = "Get API key names and scopes", body = ApiKeysResponse),
+        (status = 401, description = "Missing or invalid API key", body = ApiErrorBody),
+        (status = 403, description = "API key doesn't have the api_keys scope", body = ApiErrorBody)
+    )
+)]
+pub async fn get_api_keys(State(ctx): State<Arc<Context>>) -> ApiResult<Json<ApiKeysResponse>> {
+    let keys = ctx
+        .api_keys
+        .list()
+        .into_iter()
+        .map(ApiKeyInfo::from)
+        .collect();
+
+    Ok(Json(ApiKeysResponse { keys }))
+}
+
+/// Create API Key
+///
+/// Only the key's hash is kept. Keys created here are kept across reloads and rollbacks, and
+/// across restarts if `api_keys_file` is set. They aren't part of the config or its history.
+#[utoipa::path(
+    post,
+    path = "/v1/admin/api-keys",
+    tag = "/v1/admin/api-keys",
+    request_body = CreateApiKeyRequest,
+    responses(
+        (status = 200, description = "Create an API key", body = CreateApiKeyResponse),
+        (status = 400, description = "A key with the name already exists", body = ApiErrorBody),
+        (status = 401, description = "Missing or invalid API key", body = ApiErrorBody),
+        (status = 403, description = "API key doesn't have the api_keys scope", body = ApiErrorBody)
+    )
+)]
+pub async fn post_api_key(
+    State(ctx): State<Arc<Context>>,
+    Json(request): Json<CreateApiKeyRequest>,
+) -> ApiResult<Json<CreateApiKeyResponse>> {
+    let CreateApiKeyRequest { name, scopes } = request;
+
+    let key = generate_key()?;
+
+    let key_config = ApiKeyConfig {
+        name: name.clone(),
+        key_sha256: hash_key(&key),
+        scopes: scopes.clone(),
+    };
+
+    if !ctx.api_keys.insert(key_config)? {
+        return Err(ApiError::new(
+            ErrorCode::BadRequest,
+            format!("api key={name} already exists"),
+        ));
+    }
+
+    tracing::info!("Created api key={name}");
+
+    Ok(Json(CreateApiKeyResponse { name, key, scopes }))
+}
+
+/// Revoke API Key
+///
+/// A key from the config comes back when the config is reloaded unless it's removed from the file.
+#[utoipa::path(
+    delete,
+    path = "/v1/admin/api-keys/{name}",
+    tag = "/v1/admin/api-keys/{name}",
+    responses(
+        (status = 200, description = "Revoke an API key", body = ApiKeyInfo),
+        (status = 401, description = "Missing or invalid API key", body = ApiErrorBody),
+        (status = 403, description = "API key doesn't have the api_keys scope", body = ApiErrorBody),
+        (status = 404, description = "API key not found", body = ApiErrorBody)
+    )
+)]
+pub async fn delete_api_key(
+    Path(name): Path<String>,
+    State(ctx): State<Arc<Context>>,
+) -> ApiResult<Json<ApiKeyInfo>> {
+    let key = ctx
+        .api_keys
+        .remove(&name)?
+        .ok_or_else(|| ApiError::new(ErrorCode::NotFound, format!("api key={name} not found")))?;
+
+    tracing::info!("Revoked api key={name}");
+
+    Ok(Json(ApiKeyInfo::from(key)))
+}
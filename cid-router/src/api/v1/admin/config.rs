@@ -0,0 +1,88 @@
+use std::sync::Arc;
+
+use api_utils::{ApiError, ApiResult, ErrorCode};
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::{config_history::ConfigRevision, context::Context};
+
+#[derive(Serialize, ToSchema)]
+pub struct ConfigHistoryResponse {
+    /// Applied configs, oldest first
+    revisions: Vec<ConfigRevision>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct RollbackResponse {
+    /// IDs of providers added by the rollback
+    added: Vec<String>,
+    /// IDs of providers removed by the rollback
+    removed: Vec<String>,
+}
+
+/// Get Config History
+#[utoipa::path(
+    get,
+    path = "/v1/admin/config/history",
+    tag = "/v1/admin/config/history",
+    responses(
+        (status = 200, description = "Get the configs applied to the router with what changed in each", body = ConfigHistoryResponse),
+        (status = 401, description = "Missing or invalid API key", body = ApiErrorBody),
+        (status = 403, description = "API key doesn't have the config scope", body = ApiErrorBody)
+    )
+)]
+pub async fn get_config_history(
+    State(ctx): State<Arc<Context>>,
+) -> ApiResult<Json<ConfigHistoryResponse>> {
+    let revisions = ctx.config_history.revisions();
+
+    Ok(Json(ConfigHistoryResponse { revisions }))
+}
+
+/// Roll Back Config
+///
+/// Apply the config of an earlier revision, recording it as a new revision. The config file isn't
+/// changed, so the next reload undoes the rollback unless the file is changed to match.
+#[utoipa::path(
+    post,
+    path = "/v1/admin/config/rollback/{revision}",
+    tag = "/v1/admin/config/rollback/{revision}",
+    responses(
+        (status = 200, description = "Roll back to a config revision", body = RollbackResponse),
+        (status = 401, description = "Missing or invalid API key", body = ApiErrorBody),
+        (status = 403, description = "API key doesn't have the config scope", body = ApiErrorBody),
+        (status = 404, description = "Revision not found", body = ApiErrorBody),
+        (status = 502, description = "A provider in the revision failed to initialize", body = ApiErrorBody)
+    )
+)]
+pub async fn post_rollback(
+    Path(revision): Path<u64>,
+    State(ctx): State<Arc<Context>>,
+) -> ApiResult<Json<RollbackResponse>> {
+    let revision = ctx.config_history.get(revision).ok_or_else(|| {
+        ApiError::new(
+            ErrorCode::NotFound,
+            format!("config revision={revision} not found"),
+        )
+    })?;
+
+    let rolled_back_to = revision.revision;
+
+    let summary = ctx.rollback(revision).await.map_err(|e| {
+        ApiError::new(
+            ErrorCode::ProviderUnavailable,
+            format!("failed to roll back to config revision={rolled_back_to}: {e:#}"),
+        )
+    })?;
+
+    tracing::info!("Rolled back to config revision={rolled_back_to}");
+
+    Ok(Json(RollbackResponse {
+        added: summary.added,
+        removed: summary.removed,
+    }))
+}
@@ -0,0 +1,52 @@
+use std::sync::Arc;
+
+use api_utils::{ApiError, ApiResult, ErrorCode};
+use axum::{extract::State, Json};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::context::Context;
+
+#[derive(Serialize, ToSchema)]
+pub struct ReloadResponse {
+    /// IDs of providers added by the reload
+    added: Vec<String>,
+    /// IDs of providers removed by the reload
+    removed: Vec<String>,
+}
+
+/// Reload Config
+///
+/// Re-read the config file and apply it, as on SIGHUP. Providers whose config is unchanged are
+/// kept, and if any new provider fails to initialize nothing is applied. Providers added through
+/// `POST /v1/providers` are removed unless they're in the config.
+#[utoipa::path(
+    post,
+    path = "/v1/admin/reload",
+    tag = "/v1/admin/reload",
+    responses(
+        (status = 200, description = "Reload the config", body = ReloadResponse),
+        (status = 401, description = "Missing or invalid API key", body = ApiErrorBody),
+        (status = 403, description = "API key doesn't have the config scope", body = ApiErrorBody),
+        (status = 400, description = "The config couldn't be read or applied", body = ApiErrorBody)
+    )
+)]
+pub async fn post_reload(State(ctx): State<Arc<Context>>) -> ApiResult<Json<ReloadResponse>> {
+    let summary = ctx.reload().await.map_err(|e| {
+        ApiError::new(
+            ErrorCode::BadRequest,
+            format!("failed to reload config: {e:#}"),
+        )
+    })?;
+
+    tracing::info!(
+        "Reloaded config, added providers={:?} removed providers={:?}",
+        summary.added,
+        summary.removed
+    );
+
+    Ok(Json(ReloadResponse {
+        added: summary.added,
+        removed: summary.removed,
+    }))
+}
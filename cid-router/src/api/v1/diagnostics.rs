@@ -0,0 +1,193 @@
+use std::{
+    collections::HashMap,
+    net::{Ipv4Addr, Ipv6Addr, SocketAddr},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use api_utils::ApiResult;
+use axum::{extract::State, http::HeaderMap, Json};
+use serde::Serialize;
+use tokio::net::{TcpStream, UdpSocket};
+use utoipa::ToSchema;
+
+use crate::{
+    auth::bearer_token, context::Context, crp::Transport, egress::EgressAllowList,
+    provider::Provider,
+};
+
+#[derive(Serialize, ToSchema)]
+pub struct ReachabilityResponse {
+    providers: HashMap<String, ProviderReachability>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ProviderReachability {
+    /// Whether any of the provider's IPv4 addresses is reachable, unset if it has none
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ipv4: Option<bool>,
+    /// Whether any of the provider's IPv6 addresses is reachable, unset if it has none
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ipv6: Option<bool>,
+    addresses: Vec<AddressReachability>,
+    /// Error getting the provider's addresses
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct AddressReachability {
+    address: String,
+    transport: Transport,
+    reachable: bool,
+    /// Time taken by the check, in milliseconds
+    latency_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Get egress allow-list
+///
+/// Hosts the router's providers send requests to, along with the proxies they're configured with,
+/// for deriving egress firewall rules. Iroh nodes known only by their node ID are found through
+/// discovery, so their hosts aren't included. Only providers whose routes are visible to the
+/// request's API key, if any, are included.
+#[utoipa::path(
+    get,
+    path = "/v1/diagnostics/egress",
+    tag = "/v1/diagnostics/egress",
+    responses(
+        (status = 200, description = "Get the hosts providers send requests to", body = EgressAllowList),
+        (status = 401, description = "Invalid API key", body = ApiErrorBody)
+    )
+)]
+pub async fn get_egress(
+    headers: HeaderMap,
+    State(ctx): State<Arc<Context>>,
+) -> ApiResult<Json<EgressAllowList>> {
+    let api_key = ctx.api_keys.authenticate(bearer_token(&headers))?;
+
+    let providers = ctx
+        .providers
+        .snapshot()
+        .into_iter()
+        .filter(|(_, provider)| provider.settings.visibility.is_visible_to(api_key.as_ref()))
+        .collect();
+
+    Ok(Json(EgressAllowList::from_providers(&providers)))
+}
+
+/// Get provider reachability
+///
+/// Check each provider's addresses over IPv4 and IPv6 separately. TCP addresses are connected to.
+/// UDP is connectionless, so for UDP addresses this only checks the router has a route to them.
+/// Only providers whose routes are visible to the request's API key, if any, are checked.
+#[utoipa::path(
+    get,
+    path = "/v1/diagnostics/reachability",
+    tag = "/v1/diagnostics/reachability",
+    responses(
+        (status = 200, description = "Get provider reachability by address family", body = ReachabilityResponse),
+        (status = 401, description = "Invalid API key", body = ApiErrorBody)
+    )
+)]
+pub async fn get_reachability(
+    headers: HeaderMap,
+    State(ctx): State<Arc<Context>>,
+) -> ApiResult<Json<ReachabilityResponse>> {
+    let api_key = ctx.api_keys.authenticate(bearer_token(&headers))?;
+
+    let providers = ctx.providers.snapshot();
+
+    let providers = futures::future::join_all(
+        providers
+            .iter()
+            .filter(|(_, provider)| provider.settings.visibility.is_visible_to(api_key.as_ref()))
+            .map(|(provider_id, provider)| async move {
+                (
+                    provider_id.clone(),
+                    check_provider_reachability(provider).await,
+                )
+            }),
+    )
+    .await
+    .into_iter()
+    .collect();
+
+    Ok(Json(ReachabilityResponse { providers }))
+}
+
+async fn check_provider_reachability(provider: &Provider) -> ProviderReachability {
+    let timeout = provider.settings.timeout;
+
+    let socket_addrs = match provider.crp.socket_addrs().await {
+        Ok(socket_addrs) => socket_addrs,
+        Err(e) => {
+            return ProviderReachability {
+                ipv4: None,
+                ipv6: None,
+                addresses: vec![],
+                error: Some(e.to_string()),
+            }
+        }
+    };
+
+    let checks = futures::future::join_all(socket_addrs.into_iter().map(
+        |(addr, transport)| async move {
+            let start = Instant::now();
+
+            let error = check_address(addr, transport, timeout)
+                .await
+                .err()
+                .map(|e| e.to_string());
+
+            (
+                addr,
+                AddressReachability {
+                    address: addr.to_string(),
+                    transport,
+                    reachable: error.is_none(),
+                    latency_ms: start.elapsed().as_millis() as u64,
+                    error,
+                },
+            )
+        },
+    ))
+    .await;
+
+    let family_reachable = |is_family: fn(&SocketAddr) -> bool| {
+        checks
+            .iter()
+            .filter(|(addr, _)| is_family(addr))
+            .map(|(_, check)| check.reachable)
+            .reduce(|a, b| a || b)
+    };
+
+    ProviderReachability {
+        ipv4: family_reachable(SocketAddr::is_ipv4),
+        ipv6: family_reachable(SocketAddr::is_ipv6),
+        addresses: checks.into_iter().map(|(_, check)| check).collect(),
+        error: None,
+    }
+}
+
+async fn check_address(addr: SocketAddr, transport: Transport, timeout: Duration) -> Result<()> {
+    match transport {
+        Transport::Tcp => {
+            tokio::time::timeout(timeout, TcpStream::connect(addr)).await??;
+        }
+        Transport::Udp => {
+            let local_addr = match addr {
+                SocketAddr::V4(_) => SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0)),
+                SocketAddr::V6(_) => SocketAddr::from((Ipv6Addr::UNSPECIFIED, 0)),
+            };
+
+            // fails if the router has no address or route for the address family
+            let socket = UdpSocket::bind(local_addr).await?;
+            socket.connect(addr).await?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,125 @@
+use std::{collections::HashMap, sync::Arc, time::Instant};
+
+use api_utils::ApiResult;
+use axum::{extract::State, http::StatusCode, Json};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::{context::Context, provider::Provider};
+
+#[derive(Serialize, ToSchema)]
+pub struct HealthResponse {
+    /// "ok", "degraded" if a non-critical provider is unhealthy, or "unavailable" if a critical
+    /// provider is unhealthy
+    status: HealthStatus,
+    uptime: i64,
+    providers: HashMap<String, ProviderHealth>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    Ok,
+    Degraded,
+    Unavailable,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ProviderHealth {
+    ok: bool,
+    critical: bool,
+    /// Time taken by the health check, in milliseconds
+    latency_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Get router and per-provider health
+#[utoipa::path(
+    get,
+    path = "/healthz",
+    tag = "/healthz",
+    responses(
+        (status = 200, description = "Get health", body = HealthResponse)
+    )
+)]
+pub async fn get_healthz(State(ctx): State<Arc<Context>>) -> ApiResult<Json<HealthResponse>> {
+    Ok(Json(check_health(&ctx).await))
+}
+
+/// Get readiness, failing if any critical provider is unhealthy
+#[utoipa::path(
+    get,
+    path = "/readyz",
+    tag = "/readyz",
+    responses(
+        (status = 200, description = "Ready", body = HealthResponse),
+        (status = 503, description = "A critical provider is unhealthy", body = HealthResponse)
+    )
+)]
+pub async fn get_readyz(
+    State(ctx): State<Arc<Context>>,
+) -> ApiResult<(StatusCode, Json<HealthResponse>)> {
+    let health = check_health(&ctx).await;
+
+    let status_code = match health.status {
+        HealthStatus::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+        HealthStatus::Ok | HealthStatus::Degraded => StatusCode::OK,
+    };
+
+    Ok((status_code, Json(health)))
+}
+
+async fn check_health(ctx: &Context) -> HealthResponse {
+    let Context { start_time, .. } = ctx;
+
+    let providers = ctx.providers.snapshot();
+
+    let uptime = chrono::Utc::now().timestamp() - *start_time;
+
+    let providers =
+        futures::future::join_all(providers.iter().map(|(provider_id, provider)| async move {
+            (provider_id.clone(), check_provider_health(provider).await)
+        }))
+        .await
+        .into_iter()
+        .collect::<HashMap<_, _>>();
+
+    let status =
+        providers
+            .values()
+            .filter(|health| !health.ok)
+            .fold(HealthStatus::Ok, |status, health| {
+                if health.critical {
+                    HealthStatus::Unavailable
+                } else if status == HealthStatus::Ok {
+                    HealthStatus::Degraded
+                } else {
+                    status
+                }
+            });
+
+    HealthResponse {
+        status,
+        uptime,
+        providers,
+    }
+}
+
+async fn check_provider_health(provider: &Provider) -> ProviderHealth {
+    let start = Instant::now();
+
+    let error =
+        match tokio::time::timeout(provider.settings.timeout, provider.crp.check_health()).await {
+            Ok(Ok(())) => None,
+            Ok(Err(e)) => Some(e.to_string()),
+            Err(_) => Some("timed out".to_owned()),
+        };
+
+    ProviderHealth {
+        ok: error.is_none(),
+        critical: provider.settings.critical,
+        latency_ms: start.elapsed().as_millis() as u64,
+        error,
+    }
+}
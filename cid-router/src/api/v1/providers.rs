@@ -1,34 +1,165 @@
 use std::{collections::HashMap, sync::Arc};
 
-use api_utils::ApiResult;
-use axum::{extract::State, Json};
+use api_utils::{ApiError, ApiResult, ErrorCode};
+use axum::{
+    extract::{Path, State},
+    http::HeaderMap,
+    Json,
+};
 use serde::Serialize;
 use serde_json::Value;
 use utoipa::ToSchema;
 
-use crate::context::Context;
+use crate::{auth::bearer_token, config::ProviderEntry, context::Context, provider::Provider};
 
 #[derive(Serialize, ToSchema)]
 pub struct ProvidersResponse {
     providers: HashMap<String, Value>,
 }
 
+#[derive(Serialize, ToSchema)]
+pub struct ProviderResponse {
+    id: String,
+    config: Value,
+}
+
 /// Get providers
+///
+/// Only providers whose routes are visible to the request's API key, if any, are included.
 #[utoipa::path(
     get,
     path = "/v1/providers",
     tag = "/v1/providers",
     responses(
-        (status = 200, description = "Get providers", body = ProvidersResponse)
+        (status = 200, description = "Get providers", body = ProvidersResponse),
+        (status = 401, description = "Invalid API key", body = ApiErrorBody)
     )
 )]
-pub async fn get_providers(State(ctx): State<Arc<Context>>) -> ApiResult<Json<ProvidersResponse>> {
-    let Context { providers, .. } = &*ctx;
+pub async fn get_providers(
+    headers: HeaderMap,
+    State(ctx): State<Arc<Context>>,
+) -> ApiResult<Json<ProvidersResponse>> {
+    let api_key = ctx.api_keys.authenticate(bearer_token(&headers))?;
 
-    let providers = providers
-        .iter()
-        .map(|(id, provider)| (id.to_owned(), provider.provider_config()))
+    let providers = ctx
+        .providers
+        .snapshot()
+        .into_iter()
+        .filter(|(_, provider)| provider.settings.visibility.is_visible_to(api_key.as_ref()))
+        .map(|(id, provider)| (id, provider.crp.provider_config()))
         .collect();
 
     Ok(Json(ProvidersResponse { providers }))
 }
+
+/// Add provider
+///
+/// Takes a provider entry as it's written in the router config. The provider is initialized
+/// before it's added. Added providers aren't written back to the config, so they're dropped on
+/// restart unless they're also added there. The change is recorded in the config history.
+#[utoipa::path(
+    post,
+    path = "/v1/providers",
+    tag = "/v1/providers",
+    request_body(content = Object, description = "Provider entry, as in the config's `providers`"),
+    responses(
+        (status = 200, description = "Add a provider", body = ProviderResponse),
+        (status = 401, description = "Missing or invalid API key", body = ApiErrorBody),
+        (status = 403, description = "API key doesn't have the providers scope", body = ApiErrorBody),
+        (status = 400, description = "Invalid provider entry or the provider already exists", body = ApiErrorBody),
+        (status = 502, description = "Provider failed to initialize", body = ApiErrorBody)
+    )
+)]
+pub async fn post_provider(
+    State(ctx): State<Arc<Context>>,
+    Json(entry): Json<ProviderEntry>,
+) -> ApiResult<Json<ProviderResponse>> {
+    let (mut crp, settings) = ctx
+        .new_crp(entry.clone())
+        .map_err(|e| ApiError::new(ErrorCode::BadRequest, format!("{e:#}")))?;
+
+    let id = crp.provider_id();
+
+    let already_exists = || {
+        ApiError::new(
+            ErrorCode::BadRequest,
+            format!("provider={id} already exists"),
+        )
+    };
+
+    if ctx.providers.contains(&id) {
+        return Err(already_exists());
+    }
+
+    crp.init().await.map_err(|e| {
+        ApiError::new(
+            ErrorCode::ProviderUnavailable,
+            format!("failed to initialize provider={id}: {e:#}"),
+        )
+    })?;
+
+    let config = crp.provider_config();
+
+    // another request can add the same provider while this one is initializing
+    if !ctx
+        .providers
+        .insert(id.clone(), Provider::new(Arc::from(crp), settings))
+    {
+        return Err(already_exists());
+    }
+
+    tracing::info!("Added provider={id}");
+
+    ctx.export_egress();
+
+    if let Err(e) = ctx
+        .config_history
+        .record_api_change(|config| config.providers.push(entry))
+    {
+        tracing::error!("Failed to record config revision: {e:#}");
+    }
+
+    Ok(Json(ProviderResponse { id, config }))
+}
+
+/// Remove provider
+///
+/// Route lookups already in flight against the provider still complete. Removed providers come
+/// back on restart unless they're also removed from the config.
+#[utoipa::path(
+    delete,
+    path = "/v1/providers/{id}",
+    tag = "/v1/providers/{id}",
+    responses(
+        (status = 200, description = "Remove a provider", body = ProviderResponse),
+        (status = 401, description = "Missing or invalid API key", body = ApiErrorBody),
+        (status = 403, description = "API key doesn't have the providers scope", body = ApiErrorBody),
+        (status = 404, description = "Provider not found", body = ApiErrorBody)
+    )
+)]
+pub async fn delete_provider(
+    Path(id): Path<String>,
+    State(ctx): State<Arc<Context>>,
+) -> ApiResult<Json<ProviderResponse>> {
+    let provider = ctx
+        .providers
+        .remove(&id)
+        .ok_or_else(|| ApiError::new(ErrorCode::NotFound, format!("provider={id} not found")))?;
+
+    tracing::info!("Removed provider={id}");
+
+    ctx.export_egress();
+
+    if let Err(e) = ctx.config_history.record_api_change(|config| {
+        config
+            .providers
+            .retain(|entry| entry.provider.provider_id() != id)
+    }) {
+        tracing::error!("Failed to record config revision: {e:#}");
+    }
+
+    Ok(Json(ProviderResponse {
+        id,
+        config: provider.crp.provider_config(),
+    }))
+}
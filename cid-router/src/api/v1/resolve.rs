@@ -0,0 +1,131 @@
+use std::{cmp::Reverse, sync::Arc};
+
+use api_utils::{ApiError, ApiResult, ErrorCode};
+use axum::{extract::State, http::HeaderMap, Json};
+use serde::{Deserialize, Serialize};
+use tracing::Instrument;
+use utoipa::ToSchema;
+
+use crate::{
+    api::v1::routes::{ResolutionHints, Route},
+    auth::bearer_token,
+    context::Context,
+    crp::CrpError,
+};
+
+#[derive(Deserialize, ToSchema)]
+pub struct ResolveRequest {
+    /// URL of content a provider serves, e.g. an Azure blob URL or a GitHub commit URL
+    url: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ResolveResponse {
+    cid: String,
+    route: Route,
+}
+
+/// Resolve a URL to a CID
+///
+/// Asks each provider visible to the request whether it serves the URL, and returns the CID and
+/// route from the first that does, by provider priority then ID. Providers compute the CID if
+/// they haven't already, e.g. the Azure CRP hashes a blob it has listed but not hashed yet. A
+/// lookup that times out carries on in the provider, so retrying later picks up the result. If
+/// every provider fails, the error is the one they share, e.g. 503 if they're all rate limiting
+/// the router.
+#[utoipa::path(
+    post,
+    path = "/v1/resolve",
+    tag = "/v1/resolve",
+    request_body = ResolveRequest,
+    responses(
+        (status = 200, description = "Get the CID and route of the content at a URL", body = ResolveResponse),
+        (status = 401, description = "Invalid API key", body = ApiErrorBody),
+        (status = 404, description = "No provider serves the URL", body = ApiErrorBody),
+        (status = 502, description = "All providers failed", body = ApiErrorBody),
+        (status = 503, description = "All providers are rate limiting the router", body = ApiErrorBody)
+    )
+)]
+pub async fn post_resolve(
+    headers: HeaderMap,
+    State(ctx): State<Arc<Context>>,
+    Json(request): Json<ResolveRequest>,
+) -> ApiResult<Json<ResolveResponse>> {
+    let ResolveRequest { url } = request;
+
+    let api_key = ctx.api_keys.authenticate(bearer_token(&headers))?;
+
+    let providers = ctx.providers.snapshot();
+
+    // results come back in this order, so the first provider to resolve the url is the one picked
+    let mut providers = providers
+        .iter()
+        .filter(|(_, provider)| provider.settings.visibility.is_visible_to(api_key.as_ref()))
+        .collect::<Vec<_>>();
+    providers
+        .sort_by_key(|(provider_id, provider)| (Reverse(provider.settings.priority), *provider_id));
+
+    let resolutions =
+        futures::future::join_all(providers.into_iter().map(|(provider_id, provider)| {
+            let url = &url;
+            let span = tracing::info_span!("provider_resolve_url", provider = %provider_id, url);
+
+            async move {
+                let resolution =
+                    tokio::time::timeout(provider.settings.timeout, provider.crp.resolve_url(url))
+                        .await;
+
+                match resolution {
+                    Ok(Ok(resolution)) => Ok(resolution.map(|resolution| (provider, resolution))),
+                    Ok(Err(e)) => match CrpError::of(&e) {
+                        CrpError::NotFound(_) => Ok(None),
+                        e => {
+                            tracing::error!(
+                                "failed to resolve url={url} with provider={provider_id}: {e}"
+                            );
+                            Err(e)
+                        }
+                    },
+                    Err(_) => {
+                        tracing::error!(
+                            "timed out resolving url={url} with provider={provider_id}"
+                        );
+                        Err(CrpError::Transient(format!(
+                            "timed out after {:?}",
+                            provider.settings.timeout
+                        )))
+                    }
+                }
+            }
+            .instrument(span)
+        }))
+        .await;
+
+    let errors = resolutions
+        .iter()
+        .filter_map(|resolution| resolution.as_ref().err())
+        .collect::<Vec<_>>();
+
+    if !resolutions.is_empty() && errors.len() == resolutions.len() {
+        return Err(ApiError::new(
+            CrpError::combined_error_code(errors),
+            format!("all providers failed to resolve url={url}"),
+        ));
+    }
+
+    let Some((provider, (cid, route))) = resolutions.into_iter().flatten().flatten().next() else {
+        return Err(ApiError::new(
+            ErrorCode::NotFound,
+            format!("no provider resolved url={url}"),
+        ));
+    };
+
+    let cid = cid.to_string();
+    let fingerprint = route.fingerprint(&cid)?;
+    let hints = ResolutionHints::from(provider.as_ref());
+
+    Ok(Json(ResolveResponse {
+        cid,
+        route: Route::new(route, fingerprint, Some(hints)),
+    }))
+}
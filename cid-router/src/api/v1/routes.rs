@@ -1,17 +1,39 @@
-use std::{collections::HashMap, str::FromStr, sync::Arc};
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, HashSet},
+    str::FromStr,
+    sync::Arc,
+    time::Instant,
+};
 
-use api_utils::ApiResult;
+use api_utils::{ApiError, ApiResult, ErrorCode};
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
+    http::{HeaderMap, HeaderValue},
     Json,
 };
 use cid::Cid;
 use futures::stream::StreamExt;
-use serde::Serialize;
-use serde_json::Value;
-use utoipa::ToSchema;
+use serde::{Deserialize, Serialize};
+use tracing::Instrument;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    auth::bearer_token,
+    config::{ApiKeyConfig, RouteSelection},
+    context::Context,
+    crp::{peer_router::PEER_REQUEST_HEADER, CrpError},
+    provider::Provider,
+};
 
-use crate::context::Context;
+/// Header with the fingerprint of the route the router selected for a CID, the first one returned
+pub const SELECTED_ROUTE_HEADER: &str = "x-cid-router-selected-route";
+
+#[derive(Deserialize, IntoParams)]
+pub struct RoutesQuery {
+    /// Region to prefer routes from (defaults to the router's configured region)
+    region: Option<String>,
+}
 
 #[derive(Serialize, ToSchema)]
 pub struct RoutesResponse {
@@ -20,79 +42,252 @@ pub struct RoutesResponse {
 
 #[derive(Serialize, ToSchema)]
 pub struct Route {
+    /// Deterministic identity of the route, for deduplicating routes across routers
+    pub fingerprint: String,
+    #[serde(flatten)]
+    pub route: routes::Route,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hints: Option<ResolutionHints>,
+}
+
+/// Hints about the provider a route came from, so clients can choose between routes
+#[derive(Clone, Serialize, ToSchema)]
+pub struct ResolutionHints {
+    /// Timeout the router uses for route lookups against the provider, in milliseconds
+    pub timeout_ms: u64,
+    /// Region the provider serves content from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
+    /// Median latency of recent route lookups against the provider, in milliseconds
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub crp_id: Option<String>,
-    #[serde(rename = "type")]
-    pub type_: String,
-    pub method: Value,
+    pub median_latency_ms: Option<u64>,
+    /// Unix timestamp of the last failed route lookup against the provider
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub metadata: Option<Value>,
+    pub last_failure: Option<i64>,
 }
 
 /// Get routes for a CID
+///
+/// Routes from providers that aren't public are only returned to requests with an API key, as an
+/// `Authorization: Bearer` header, that the provider's visibility allows. Peer routers are only
+/// asked for CIDs no other provider has routes for, and never for lookups made by a peer router.
+///
+/// Routes from providers with a higher priority come first, then routes are ordered by the
+/// configured route selection. The fingerprint of the first route is in the
+/// `x-cid-router-selected-route` header.
 #[utoipa::path(
     get,
     path = "/v1/routes/{cid}",
     tag = "/v1/routes/{cid}",
+    params(RoutesQuery),
     responses(
-        (status = 200, description = "Get routes for a CID", body = RoutesResponse)
+        (status = 200, description = "Get routes for a CID, the selected route first", body = RoutesResponse),
+        (status = 400, description = "Invalid CID", body = ApiErrorBody),
+        (status = 401, description = "Invalid API key", body = ApiErrorBody),
+        (status = 502, description = "All providers eligible for the CID failed", body = ApiErrorBody),
+        (status = 503, description = "All providers eligible for the CID are rate limiting the router", body = ApiErrorBody)
     )
 )]
 pub async fn get_routes(
     Path(cid): Path<String>,
+    Query(query): Query<RoutesQuery>,
+    headers: HeaderMap,
     State(ctx): State<Arc<Context>>,
-) -> ApiResult<Json<RoutesResponse>> {
-    let Context { providers, .. } = &*ctx;
+) -> ApiResult<(HeaderMap, Json<RoutesResponse>)> {
+    let api_key = ctx.api_keys.authenticate(bearer_token(&headers))?;
+
+    let default_region = ctx.settings().region;
+
+    let region = query.region.as_ref().or(default_region.as_ref());
 
-    let cid = Cid::from_str(&cid)?;
+    let cid = Cid::from_str(&cid)
+        .map_err(|e| ApiError::new(ErrorCode::CidInvalid, format!("invalid cid={cid}: {e}")))?;
 
-    let eligible_providers = providers
+    let is_peer_request = headers.contains_key(PEER_REQUEST_HEADER);
+
+    let routes = lookup_routes(&ctx, &cid, api_key.as_ref(), region, is_peer_request).await?;
+
+    let mut response_headers = HeaderMap::new();
+    if let Some(route) = routes.first() {
+        response_headers.insert(
+            SELECTED_ROUTE_HEADER,
+            HeaderValue::from_str(&route.fingerprint)?,
+        );
+    }
+
+    Ok((response_headers, Json(RoutesResponse { routes })))
+}
+
+/// Routes for a CID from the providers visible to the API key, if any, deduplicated and ordered by
+/// provider priority then the router's route selection, preferring routes from the region
+pub async fn lookup_routes(
+    ctx: &Context,
+    cid: &Cid,
+    api_key: Option<&ApiKeyConfig>,
+    region: Option<&String>,
+    is_peer_request: bool,
+) -> ApiResult<Vec<Route>> {
+    let providers = ctx.providers.snapshot();
+
+    let (fallback_providers, providers) = providers
+        .iter()
+        .filter(|(_, provider)| provider.settings.visibility.is_visible_to(api_key))
+        .filter(|(_, provider)| provider.crp.provider_is_eligible_for_cid(cid))
+        .partition::<BTreeMap<_, _>, _>(|(_, provider)| provider.crp.is_fallback());
+
+    let mut provider_results = get_provider_routes(cid, providers).await;
+
+    let has_routes = provider_results
         .iter()
-        .filter(|(_, provider)| provider.provider_is_eligible_for_cid(&cid))
-        .collect::<HashMap<_, _>>();
+        .any(|(_, routes)| routes.as_ref().is_ok_and(|routes| !routes.is_empty()));
 
-    let provider_requests = eligible_providers
+    // lookups from peer routers only go to local providers, so peers can't forward them in a loop
+    if !has_routes && !is_peer_request {
+        provider_results.extend(get_provider_routes(cid, fallback_providers).await);
+    }
+
+    let errors = provider_results
+        .iter()
+        .filter_map(|(_, routes)| routes.as_ref().err())
+        .collect::<Vec<_>>();
+
+    if !provider_results.is_empty() && errors.len() == provider_results.len() {
+        return Err(ApiError::new(
+            CrpError::combined_error_code(errors),
+            format!("all providers eligible for cid={cid} failed"),
+        ));
+    }
+
+    let cid_str = cid.to_string();
+    let mut fingerprints = HashSet::new();
+    let mut routes = vec![];
+
+    for (provider, provider_routes) in provider_results {
+        let hints = ResolutionHints::from(provider.as_ref());
+
+        for route in provider_routes.into_iter().flatten() {
+            let fingerprint = route.fingerprint(&cid_str)?;
+
+            // fingerprints include the provider ID, so these are routes a provider returned twice
+            // or that more than one peer router got from the same upstream provider
+            if fingerprints.insert(fingerprint.clone()) {
+                routes.push((
+                    provider.settings.priority,
+                    Route::new(route, fingerprint, Some(hints.clone())),
+                ));
+            }
+        }
+    }
+
+    let route_selection = ctx.settings().route_selection;
+
+    // stable, so routes otherwise keep the order providers returned them in, taking providers in
+    // ID order
+    routes.sort_by_key(|(priority, route)| {
+        let hints = route.hints.as_ref();
+
+        let preference = match route_selection {
+            RouteSelection::Region => {
+                let in_region = region.map_or(true, |region| {
+                    hints.and_then(|hints| hints.region.as_ref()) == Some(region)
+                });
+                u64::from(!in_region)
+            }
+            RouteSelection::Latency => hints
+                .and_then(|hints| hints.median_latency_ms)
+                .unwrap_or(u64::MAX),
+        };
+
+        (Reverse(*priority), preference)
+    });
+
+    Ok(routes.into_iter().map(|(_, route)| route).collect())
+}
+
+/// Routes from each provider for the CID, or why the provider failed or timed out, in provider ID
+/// order
+async fn get_provider_routes<'a>(
+    cid: &Cid,
+    providers: BTreeMap<&'a String, &'a Arc<Provider>>,
+) -> Vec<(&'a Arc<Provider>, Result<Vec<routes::Route>, CrpError>)> {
+    let provider_requests = providers
         .into_iter()
-        .map(|(provider_id, provider)| async move {
-            match provider.get_routes_for_cid(&cid).await {
-                Ok(routes) => routes,
-                Err(e) => {
-                    log::error!(
-                        "failed to get routes for cid={cid} from provider={provider_id}: {e}"
-                    );
-                    vec![]
-                }
+        .map(|(provider_id, provider)| {
+            let span = tracing::info_span!("provider_routes", provider = %provider_id, %cid);
+
+            async move {
+                let start = Instant::now();
+
+                let routes = match tokio::time::timeout(
+                    provider.settings.timeout,
+                    provider.crp.get_routes_for_cid(cid),
+                )
+                .await
+                {
+                    Ok(Ok(routes)) => {
+                        provider.record_success(start.elapsed());
+                        Ok(routes)
+                    }
+                    Ok(Err(e)) => match CrpError::of(&e) {
+                        CrpError::NotFound(_) => {
+                            provider.record_success(start.elapsed());
+                            Ok(vec![])
+                        }
+                        e => {
+                            provider.record_failure();
+                            tracing::error!(
+                                "failed to get routes for cid={cid} from provider={provider_id}: {e}"
+                            );
+                            Err(e)
+                        }
+                    },
+                    Err(_) => {
+                        provider.record_failure();
+                        tracing::error!(
+                            "timed out getting routes for cid={cid} from provider={provider_id}"
+                        );
+                        Err(CrpError::Transient(format!(
+                            "timed out after {:?}",
+                            provider.settings.timeout
+                        )))
+                    }
+                };
+
+                (provider, routes)
             }
+            .instrument(span)
         })
         .collect::<Vec<_>>();
 
-    let routes = futures::stream::iter(provider_requests.into_iter())
+    futures::stream::iter(provider_requests)
         .buffered(5)
         .collect::<Vec<_>>()
         .await
-        .into_iter()
-        .flatten()
-        .collect::<Vec<_>>();
-
-    let routes = routes.into_iter().map(Into::into).collect();
-
-    Ok(Json(RoutesResponse { routes }))
 }
 
-impl From<routes::Route> for Route {
-    fn from(route: routes::Route) -> Self {
-        let routes::Route {
-            crp_id,
-            type_,
-            method,
-            metadata,
-        } = route;
+impl Route {
+    pub(crate) fn new(
+        route: routes::Route,
+        fingerprint: String,
+        hints: Option<ResolutionHints>,
+    ) -> Self {
+        Self {
+            fingerprint,
+            route,
+            hints,
+        }
+    }
+}
 
+impl From<&Provider> for ResolutionHints {
+    fn from(provider: &Provider) -> Self {
         Self {
-            crp_id,
-            type_,
-            method,
-            metadata,
+            timeout_ms: provider.settings.timeout.as_millis() as u64,
+            region: provider.settings.region.clone(),
+            median_latency_ms: provider
+                .median_latency()
+                .map(|latency| latency.as_millis() as u64),
+            last_failure: provider.last_failure(),
         }
     }
 }
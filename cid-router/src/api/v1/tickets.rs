@@ -0,0 +1,93 @@
+use std::{str::FromStr, sync::Arc};
+
+use api_utils::{ApiError, ApiResult, ErrorCode};
+use axum::{
+    extract::{Path, State},
+    http::HeaderMap,
+    Json,
+};
+use cid::Cid;
+use routes::{IntoRoute, IrohRouteMethod};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::{
+    api::v1::routes::{lookup_routes, Route},
+    auth::bearer_token,
+    context::Context,
+};
+
+/// Multihash code of blake3, the only hash iroh addresses blobs by
+const BLAKE3: u64 = 0x1e;
+
+#[derive(Serialize, ToSchema)]
+pub struct TicketsResponse {
+    /// Iroh blob tickets of nodes with the blob, the selected node's first
+    tickets: Vec<String>,
+}
+
+/// Get iroh tickets for a blake3 CID
+///
+/// Blob tickets from the router's iroh routes for a CID, so clients on the iroh network can fetch
+/// the blob from a node directly rather than over HTTP. Tickets are ordered and filtered by API
+/// key like routes are.
+#[utoipa::path(
+    get,
+    path = "/v1/tickets/{cid}",
+    tag = "/v1/tickets/{cid}",
+    responses(
+        (status = 200, description = "Get iroh tickets for a CID, the selected node's first", body = TicketsResponse),
+        (status = 400, description = "Invalid or non-blake3 CID", body = ApiErrorBody),
+        (status = 401, description = "Invalid API key", body = ApiErrorBody),
+        (status = 404, description = "No iroh node has the blob", body = ApiErrorBody),
+        (status = 502, description = "All providers eligible for the CID failed", body = ApiErrorBody),
+        (status = 503, description = "All providers eligible for the CID are rate limiting the router", body = ApiErrorBody)
+    )
+)]
+pub async fn get_tickets(
+    Path(cid): Path<String>,
+    headers: HeaderMap,
+    State(ctx): State<Arc<Context>>,
+) -> ApiResult<Json<TicketsResponse>> {
+    let api_key = ctx.api_keys.authenticate(bearer_token(&headers))?;
+
+    let region = ctx.settings().region;
+
+    let cid = Cid::from_str(&cid)
+        .map_err(|e| ApiError::new(ErrorCode::CidInvalid, format!("invalid cid={cid}: {e}")))?;
+
+    if cid.hash().code() != BLAKE3 {
+        return Err(ApiError::new(
+            ErrorCode::BadRequest,
+            format!("iroh tickets are only for blake3 cids, cid={cid} isn't one"),
+        ));
+    }
+
+    let routes = lookup_routes(&ctx, &cid, api_key.as_ref(), region.as_ref(), false).await?;
+
+    let tickets = routes
+        .into_iter()
+        .filter_map(|Route { route, .. }| {
+            if route.type_ != IrohRouteMethod::type_str() {
+                return None;
+            }
+
+            match serde_json::from_value::<IrohRouteMethod>(route.method) {
+                Ok(IrohRouteMethod { ticket }) => Some(ticket),
+                Err(e) => {
+                    tracing::warn!("dropping iroh route for cid={cid} without a ticket: {e}");
+                    None
+                }
+            }
+        })
+        .collect::<Vec<_>>();
+
+    if tickets.is_empty() {
+        return Err(ApiError::new(
+            ErrorCode::NotFound,
+            format!("no iroh tickets for cid={cid}"),
+        ));
+    }
+
+    Ok(Json(TicketsResponse { tickets }))
+}
@@ -0,0 +1,323 @@
+use std::{
+    fs,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, RwLock,
+    },
+};
+
+use anyhow::{anyhow, Result};
+use api_utils::{ApiError, ApiResult, ErrorCode};
+use axum::{
+    extract::State,
+    http::{header::AUTHORIZATION, HeaderMap, Request},
+    middleware::Next,
+    response::Response,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use utoipa::ToSchema;
+
+use crate::{config::ApiKeyConfig, context::Context};
+
+/// What an API key is allowed to do
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiKeyScope {
+    /// Add and remove providers
+    Providers,
+    /// Reload the config, and view and roll back its history
+    Config,
+    /// Create and revoke API keys
+    ApiKeys,
+    /// Get routes from providers with `restricted` visibility
+    RestrictedRoutes,
+}
+
+/// Who a provider's routes are returned to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RouteVisibility {
+    /// Anyone
+    #[default]
+    Public,
+    /// Requests with any valid API key
+    Authenticated,
+    /// Requests with an API key with the `restricted_routes` scope
+    Restricted,
+}
+
+impl RouteVisibility {
+    /// Whether routes are visible to a request made with the key, if any
+    pub fn is_visible_to(&self, key: Option<&ApiKeyConfig>) -> bool {
+        match self {
+            Self::Public => true,
+            Self::Authenticated => key.is_some(),
+            Self::Restricted => {
+                key.is_some_and(|key| key.scopes.contains(&ApiKeyScope::RestrictedRoutes))
+            }
+        }
+    }
+}
+
+impl ApiKeyScope {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Providers => "providers",
+            Self::Config => "config",
+            Self::ApiKeys => "api_keys",
+            Self::RestrictedRoutes => "restricted_routes",
+        }
+    }
+}
+
+/// API keys for admin endpoints, from the config and created through the API. While there are
+/// none the admin endpoints are closed, unless they're configured to be open.
+#[derive(Default)]
+pub struct ApiKeys {
+    keys: RwLock<KeySet>,
+    /// Whether admin endpoints are open while there are no keys
+    open_without_keys: AtomicBool,
+    /// File keys created through the API are saved to, if any
+    file: Option<PathBuf>,
+}
+
+#[derive(Default)]
+struct KeySet {
+    /// Keys from the config, replaced when it's reloaded or rolled back
+    configured: Vec<ApiKeyConfig>,
+    /// Keys created through the API, kept across reloads and rollbacks
+    managed: Vec<ApiKeyConfig>,
+}
+
+impl KeySet {
+    fn iter(&self) -> impl Iterator<Item = &ApiKeyConfig> {
+        // a managed key can also be in the config, e.g. once it's copied there
+        self.configured.iter().chain(
+            self.managed
+                .iter()
+                .filter(|k| !self.configured.iter().any(|c| c.name == k.name)),
+        )
+    }
+}
+
+impl ApiKeys {
+    /// Load keys created through the API from the file, if any. The file is created when a key is
+    /// first created.
+    pub fn load(file: Option<PathBuf>) -> Result<Self> {
+        let managed = match &file {
+            Some(file) if file.exists() => serde_json::from_str(&fs::read_to_string(file)?)
+                .map_err(|e| anyhow!("invalid api keys file={}: {e}", file.display()))?,
+            _ => vec![],
+        };
+
+        Ok(Self {
+            keys: RwLock::new(KeySet {
+                configured: vec![],
+                managed,
+            }),
+            file,
+            open_without_keys: AtomicBool::new(false),
+        })
+    }
+
+    /// Leave admin endpoints open to anyone while there are no keys, or keep them closed
+    pub fn set_open_without_keys(&self, open: bool) {
+        self.open_without_keys.store(open, Ordering::Relaxed);
+    }
+
+    pub fn list(&self) -> Vec<ApiKeyConfig> {
+        self.keys
+            .read()
+            .expect("api keys lock poisoned")
+            .iter()
+            .cloned()
+            .collect()
+    }
+
+    /// Replace the keys from the config, keeping keys created through the API. A created key whose
+    /// name is now taken by a different key in the config is dropped.
+    pub fn set_configured(&self, keys: Vec<ApiKeyConfig>) {
+        let mut key_set = self.keys.write().expect("api keys lock poisoned");
+
+        let (managed, dropped) = key_set.managed.iter().cloned().partition::<Vec<_>, _>(|m| {
+            !keys
+                .iter()
+                .any(|c| c.name == m.name && c.key_sha256 != m.key_sha256)
+        });
+
+        for key in &dropped {
+            tracing::warn!(
+                "Dropping api key={} created through the api, the config has a different key with its name",
+                key.name
+            );
+        }
+
+        if !dropped.is_empty() {
+            // the config is already applied, so the dropped keys stay dropped in memory regardless
+            if let Err(e) = self.save(&managed) {
+                tracing::error!("{e:#}");
+            }
+        }
+
+        *key_set = KeySet {
+            configured: keys,
+            managed,
+        };
+    }
+
+    /// Add a key created through the API, returning false without replacing it if one with the
+    /// name already exists
+    pub fn insert(&self, key: ApiKeyConfig) -> Result<bool> {
+        let mut key_set = self.keys.write().expect("api keys lock poisoned");
+
+        if key_set.iter().any(|k| k.name == key.name) {
+            return Ok(false);
+        }
+
+        let mut managed = key_set.managed.clone();
+        managed.push(key);
+
+        self.save(&managed)?;
+        key_set.managed = managed;
+
+        Ok(true)
+    }
+
+    /// Remove a key. A key from the config is only removed until the config is next reloaded.
+    pub fn remove(&self, name: &str) -> Result<Option<ApiKeyConfig>> {
+        let mut key_set = self.keys.write().expect("api keys lock poisoned");
+
+        if let Some(index) = key_set.configured.iter().position(|k| k.name == name) {
+            return Ok(Some(key_set.configured.remove(index)));
+        }
+
+        let Some(index) = key_set.managed.iter().position(|k| k.name == name) else {
+            return Ok(None);
+        };
+
+        let mut managed = key_set.managed.clone();
+        let key = managed.remove(index);
+
+        self.save(&managed)?;
+        key_set.managed = managed;
+
+        Ok(Some(key))
+    }
+
+    /// Write keys created through the API to the file, if any
+    fn save(&self, managed: &[ApiKeyConfig]) -> Result<()> {
+        let Some(file) = &self.file else {
+            return Ok(());
+        };
+
+        fs::write(file, serde_json::to_string_pretty(managed)?)
+            .map_err(|e| anyhow!("failed to write api keys file={}: {e}", file.display()))
+    }
+
+    /// Key for a request's bearer token, `None` if the request has no token and an error if the
+    /// token isn't a key
+    pub fn authenticate(&self, token: Option<&str>) -> ApiResult<Option<ApiKeyConfig>> {
+        let Some(token) = token else {
+            return Ok(None);
+        };
+
+        let key_sha256 = hash_key(token);
+
+        // every key is compared, so how long this takes doesn't tell which key's hash is closest
+        self.keys
+            .read()
+            .expect("api keys lock poisoned")
+            .iter()
+            .fold(None, |found, k| {
+                let matches = constant_time_eq(
+                    k.key_sha256.to_lowercase().as_bytes(),
+                    key_sha256.as_bytes(),
+                );
+                found.or(matches.then_some(k))
+            })
+            .cloned()
+            .map(Some)
+            .ok_or_else(|| ApiError::new(ErrorCode::Unauthorized, "missing or invalid api key"))
+    }
+
+    /// Check a request's bearer token is a key with the scope
+    pub fn authorize(&self, token: Option<&str>, scope: ApiKeyScope) -> ApiResult<()> {
+        if self
+            .keys
+            .read()
+            .expect("api keys lock poisoned")
+            .iter()
+            .next()
+            .is_none()
+        {
+            if self.open_without_keys.load(Ordering::Relaxed) {
+                return Ok(());
+            }
+
+            return Err(ApiError::new(
+                ErrorCode::Unauthorized,
+                "no api keys are configured, admin endpoints are closed",
+            ));
+        }
+
+        let key = self
+            .authenticate(token)?
+            .ok_or_else(|| ApiError::new(ErrorCode::Unauthorized, "missing or invalid api key"))?;
+
+        if !key.scopes.contains(&scope) {
+            return Err(ApiError::new(
+                ErrorCode::Forbidden,
+                format!("api key={} doesn't have scope={}", key.name, scope.name()),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Token from a request's `Authorization: Bearer` header
+pub fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+}
+
+/// Hex sha256 of an API key, which is what's kept in the config
+pub fn hash_key(key: &str) -> String {
+    hex::encode(Sha256::digest(key.as_bytes()))
+}
+
+/// Whether two byte strings are equal, taking the same time wherever they differ
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+
+    let diff = a.iter().zip(b).fold(0u8, |diff, (a, b)| diff | (a ^ b));
+
+    std::hint::black_box(diff) == 0
+}
+
+/// New random API key
+pub fn generate_key() -> Result<String> {
+    let mut bytes = [0u8; 32];
+    getrandom::getrandom(&mut bytes).map_err(|e| anyhow!("failed to generate an api key: {e}"))?;
+
+    Ok(hex::encode(bytes))
+}
+
+/// Middleware rejecting requests without an API key with the scope, as an `Authorization: Bearer`
+/// header
+pub async fn require_scope<B>(
+    State((ctx, scope)): State<(Arc<Context>, ApiKeyScope)>,
+    request: Request<B>,
+    next: Next<B>,
+) -> ApiResult<Response> {
+    ctx.api_keys
+        .authorize(bearer_token(request.headers()), scope)?;
+
+    Ok(next.run(request).await)
+}
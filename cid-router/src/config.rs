@@ -1,14 +1,118 @@
-use std::{fs, path::PathBuf};
+use std::{fs, net::IpAddr, path::PathBuf};
 
 use anyhow::Result;
+use api_utils::tls::TlsConfig;
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
-use crate::crp::{external::ExternalCrpConfig, ipfs::IpfsCrpConfig, iroh::IrohCrpConfig};
+use crate::{
+    auth::{ApiKeyScope, RouteVisibility},
+    crp::{
+        external::ExternalCrpConfig, github::GithubCrpConfig, ipfs::IpfsCrpConfig,
+        iroh::IrohCrpConfig, peer_router::PeerRouterCrpConfig, provider_id_from_config,
+    },
+    rate_limit::RateLimitConfig,
+};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
+    /// Address to listen on, "::" listens on IPv6 and, unless the OS restricts IPv6 sockets to
+    /// IPv6 only, IPv4 too (defaults to 0.0.0.0)
+    pub bind_addr: Option<IpAddr>,
     pub port: u16,
-    pub providers: Vec<ProviderConfig>,
+    /// Serve HTTPS, optionally requiring client certificates (defaults to plain HTTP). Only read
+    /// on startup.
+    pub tls: Option<TlsConfig>,
+    /// Default timeout for route lookups against a provider, in milliseconds
+    pub provider_timeout_ms: Option<u64>,
+    /// Include internal callstacks in API error responses (defaults to true in debug builds only)
+    pub expose_callstacks: Option<bool>,
+    /// Region routes are preferred from when a request doesn't give one
+    pub region: Option<String>,
+    /// Order routes for a CID are returned in, after provider priority (defaults to region)
+    pub route_selection: Option<RouteSelection>,
+    /// Proxy for providers' outbound HTTP requests (defaults to the `HTTP_PROXY`, `HTTPS_PROXY` and
+    /// `NO_PROXY` environment variables)
+    pub proxy: Option<ProxyConfig>,
+    /// How often to check the config file for changes to reload, in milliseconds, 0 disables it
+    /// (defaults to 5000). The config is also reloaded on SIGHUP.
+    pub config_poll_interval_ms: Option<u64>,
+    /// File to keep the history of applied configs in, so it survives restarts (defaults to
+    /// keeping it in memory only)
+    pub config_history_file: Option<PathBuf>,
+    /// File to write the egress allow-list to as JSON whenever the providers change
+    pub egress_file: Option<PathBuf>,
+    /// How long in-flight requests are given to finish on SIGINT or SIGTERM, in milliseconds
+    /// (defaults to 30000)
+    pub shutdown_timeout_ms: Option<u64>,
+    /// OTLP gRPC endpoint to export traces of requests and provider lookups to, e.g.
+    /// "http://localhost:4317" (defaults to not exporting them). Only read on startup.
+    pub otlp_endpoint: Option<String>,
+    /// API keys for the admin endpoints, which are closed while there are none unless
+    /// `open_admin_endpoints` is set
+    pub api_keys: Option<Vec<ApiKeyConfig>>,
+    /// Leave the admin endpoints open to anyone while there are no API keys, e.g. for local
+    /// development (defaults to false)
+    pub open_admin_endpoints: Option<bool>,
+    /// File to keep API keys created through the API in, so they survive restarts (defaults to
+    /// keeping them in memory only). Only read on startup.
+    pub api_keys_file: Option<PathBuf>,
+    /// Rate limits on the route lookup endpoints (defaults to no limits)
+    pub rate_limit: Option<RateLimitConfig>,
+    pub providers: Vec<ProviderEntry>,
+}
+
+/// A provider's config along with router-side settings for it.
+///
+/// Router-side settings aren't part of the [`ProviderConfig`] so changing them doesn't change the
+/// provider ID.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderEntry {
+    #[serde(flatten)]
+    pub provider: ProviderConfig,
+    /// Timeout for route lookups against this provider, in milliseconds
+    pub timeout_ms: Option<u64>,
+    /// Report the router as not ready while this provider is unhealthy (defaults to false)
+    pub critical: Option<bool>,
+    /// Region the provider serves content from, e.g. "eu"
+    pub region: Option<String>,
+    /// Overrides the top-level `proxy` for this provider
+    pub proxy: Option<ProxyConfig>,
+    /// Who the provider's routes are returned to (defaults to public)
+    pub visibility: Option<RouteVisibility>,
+    /// Routes from providers with a higher priority are returned before others, whatever the
+    /// route selection (defaults to 0)
+    pub priority: Option<i32>,
+}
+
+/// How routes for a CID are ordered, so the first route is the one a client should pick
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RouteSelection {
+    /// Routes from the preferred region first
+    #[default]
+    Region,
+    /// Routes from providers with the lowest median lookup latency first, providers without
+    /// recent lookups last
+    Latency,
+}
+
+/// API key for the admin endpoints
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ApiKeyConfig {
+    pub name: String,
+    /// Hex sha256 of the key, e.g. from `printf %s "$KEY" | sha256sum`
+    pub key_sha256: String,
+    pub scopes: Vec<ApiKeyScope>,
+}
+
+/// Outbound HTTP(S) proxy
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProxyConfig {
+    /// Proxy URL, e.g. "http://proxy.internal:3128"
+    pub url: String,
+    /// Comma separated hosts, domains and IP ranges to connect to directly, as in `NO_PROXY`
+    pub no_proxy: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -16,13 +120,28 @@ pub struct Config {
 #[serde(tag = "type")]
 pub enum ProviderConfig {
     External(ExternalCrpConfig),
+    Github(GithubCrpConfig),
     Ipfs(IpfsCrpConfig),
     Iroh(IrohCrpConfig),
+    PeerRouter(PeerRouterCrpConfig),
+}
+
+impl ProviderConfig {
+    /// ID of a provider created from this config
+    pub fn provider_id(&self) -> String {
+        provider_id_from_config(
+            &serde_json::to_value(self).expect("unexpectedly failed to serialize a config type"),
+        )
+    }
 }
 
 impl Config {
     pub fn from_file(path: PathBuf) -> Result<Self> {
-        let config = toml::from_str(&fs::read_to_string(path)?)?;
+        let config: Self = toml::from_str(&fs::read_to_string(path)?)?;
+
+        if let Some(rate_limit) = &config.rate_limit {
+            rate_limit.validate()?;
+        }
 
         Ok(config)
     }
@@ -0,0 +1,220 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::{self, OpenOptions},
+    io::Write,
+    path::PathBuf,
+    sync::Mutex,
+};
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use utoipa::ToSchema;
+
+use crate::config::Config;
+
+/// What applied a config revision
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RevisionSource {
+    /// The config file was loaded on startup
+    Startup,
+    /// The config file was reloaded
+    Reload,
+    /// A provider was added or removed through the API
+    Api,
+    /// An earlier revision was rolled back to
+    Rollback,
+}
+
+/// A config applied to the router, along with what changed from the revision before it
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ConfigRevision {
+    pub revision: u64,
+    /// Unix timestamp the config was applied at
+    pub timestamp: i64,
+    pub source: RevisionSource,
+    /// Revision rolled back to, for rollbacks
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rolled_back_to: Option<u64>,
+    pub diff: ConfigDiff,
+    #[schema(value_type = Object)]
+    pub config: Config,
+}
+
+/// Changes from the previous revision
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct ConfigDiff {
+    /// Top-level settings that changed
+    pub settings: Vec<String>,
+    /// IDs of providers added
+    pub added_providers: Vec<String>,
+    /// IDs of providers removed
+    pub removed_providers: Vec<String>,
+    /// IDs of providers whose router-side settings changed
+    pub changed_providers: Vec<String>,
+}
+
+/// History of the configs applied to the router, appended to a file as JSON lines if one is
+/// configured
+#[derive(Default)]
+pub struct ConfigHistory {
+    file: Option<PathBuf>,
+    revisions: Mutex<Vec<ConfigRevision>>,
+}
+
+impl ConfigHistory {
+    /// Load the history from the file, if any. The file is created when a revision is first recorded.
+    pub fn load(file: Option<PathBuf>) -> Result<Self> {
+        let revisions = match &file {
+            Some(file) if file.exists() => fs::read_to_string(file)?
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(serde_json::from_str)
+                .collect::<Result<Vec<ConfigRevision>, _>>()
+                .map_err(|e| anyhow!("invalid config history file={}: {e}", file.display()))?,
+            _ => vec![],
+        };
+
+        Ok(Self {
+            file,
+            revisions: Mutex::new(revisions),
+        })
+    }
+
+    pub fn revisions(&self) -> Vec<ConfigRevision> {
+        self.revisions
+            .lock()
+            .expect("config history lock poisoned")
+            .clone()
+    }
+
+    pub fn get(&self, revision: u64) -> Option<ConfigRevision> {
+        self.revisions
+            .lock()
+            .expect("config history lock poisoned")
+            .iter()
+            .find(|r| r.revision == revision)
+            .cloned()
+    }
+
+    /// Record a newly applied config
+    pub fn record(
+        &self,
+        config: Config,
+        source: RevisionSource,
+        rolled_back_to: Option<u64>,
+    ) -> Result<ConfigRevision> {
+        let mut revisions = self.revisions.lock().expect("config history lock poisoned");
+
+        self.push(&mut revisions, config, source, rolled_back_to)
+    }
+
+    /// Record a change made to the current config through the API, doing nothing if there's no
+    /// current config
+    pub fn record_api_change(&self, change: impl FnOnce(&mut Config)) -> Result<()> {
+        let mut revisions = self.revisions.lock().expect("config history lock poisoned");
+
+        let Some(mut config) = revisions.last().map(|r| r.config.clone()) else {
+            return Ok(());
+        };
+
+        change(&mut config);
+
+        self.push(&mut revisions, config, RevisionSource::Api, None)?;
+
+        Ok(())
+    }
+
+    fn push(
+        &self,
+        revisions: &mut Vec<ConfigRevision>,
+        config: Config,
+        source: RevisionSource,
+        rolled_back_to: Option<u64>,
+    ) -> Result<ConfigRevision> {
+        let previous = revisions.last();
+
+        let revision = ConfigRevision {
+            revision: previous.map_or(1, |r| r.revision + 1),
+            timestamp: chrono::Utc::now().timestamp(),
+            source,
+            rolled_back_to,
+            diff: previous
+                .map(|r| diff(&r.config, &config))
+                .unwrap_or_default(),
+            config,
+        };
+
+        if let Some(file) = &self.file {
+            let mut line = serde_json::to_string(&revision)?;
+            line.push('\n');
+
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(file)?
+                .write_all(line.as_bytes())?;
+        }
+
+        revisions.push(revision.clone());
+
+        Ok(revision)
+    }
+}
+
+fn diff(previous: &Config, config: &Config) -> ConfigDiff {
+    let settings_of = |config: &Config| match serde_json::to_value(config) {
+        Ok(Value::Object(mut settings)) => {
+            settings.remove("providers");
+            settings.into_iter().collect::<BTreeMap<_, _>>()
+        }
+        _ => BTreeMap::new(),
+    };
+
+    let providers_of = |config: &Config| {
+        config
+            .providers
+            .iter()
+            .map(|entry| {
+                (
+                    entry.provider.provider_id(),
+                    serde_json::to_value(entry).unwrap_or_default(),
+                )
+            })
+            .collect::<BTreeMap<_, _>>()
+    };
+
+    let (previous_settings, settings) = (settings_of(previous), settings_of(config));
+    let (previous_providers, providers) = (providers_of(previous), providers_of(config));
+
+    ConfigDiff {
+        settings: previous_settings
+            .keys()
+            .chain(settings.keys())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .filter(|key| previous_settings.get(*key) != settings.get(*key))
+            .cloned()
+            .collect(),
+        added_providers: providers
+            .keys()
+            .filter(|id| !previous_providers.contains_key(*id))
+            .cloned()
+            .collect(),
+        removed_providers: previous_providers
+            .keys()
+            .filter(|id| !providers.contains_key(*id))
+            .cloned()
+            .collect(),
+        changed_providers: providers
+            .iter()
+            .filter(|(id, entry)| {
+                previous_providers
+                    .get(*id)
+                    .is_some_and(|previous| previous != *entry)
+            })
+            .map(|(id, _)| id.clone())
+            .collect(),
+    }
+}
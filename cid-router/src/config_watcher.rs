@@ -0,0 +1,80 @@
+use std::{sync::Arc, time::Duration};
+
+use anyhow::Result;
+use tokio::time::MissedTickBehavior;
+use tracing::{error, info};
+
+use crate::context::Context;
+
+/// How often to check the config file for changes when none is configured, in milliseconds
+pub const DEFAULT_CONFIG_POLL_INTERVAL_MS: u64 = 5_000;
+
+/// Reload the config on SIGHUP and whenever the config file's contents change
+pub async fn start(ctx: Arc<Context>, poll_interval: Duration) -> Result<()> {
+    let Some(path) = ctx.config_path.clone() else {
+        return Ok(());
+    };
+
+    let mut hangup = Hangup::new()?;
+
+    let polling = !poll_interval.is_zero();
+    let mut interval = tokio::time::interval(poll_interval.max(Duration::from_millis(1)));
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    let mut last_contents = tokio::fs::read(&path).await.ok();
+
+    loop {
+        tokio::select! {
+            _ = hangup.recv() => info!("Reloading config on SIGHUP"),
+            _ = interval.tick(), if polling => {
+                let contents = tokio::fs::read(&path).await.ok();
+
+                // an unreadable file, e.g. mid-write by an editor, isn't a change to apply
+                if contents.is_none() || contents == last_contents {
+                    continue;
+                }
+
+                info!("Config file changed, reloading");
+            }
+        }
+
+        last_contents = tokio::fs::read(&path).await.ok();
+
+        match ctx.reload().await {
+            Ok(summary) => info!(
+                "Reloaded config, added providers={:?} removed providers={:?}",
+                summary.added, summary.removed
+            ),
+            Err(e) => error!("Failed to reload config, keeping the current one: {e:#}"),
+        }
+    }
+}
+
+/// Resolves on each SIGHUP, and never on platforms without it
+struct Hangup(#[cfg(unix)] tokio::signal::unix::Signal);
+
+impl Hangup {
+    #[cfg(unix)]
+    fn new() -> Result<Self> {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        Ok(Self(signal(SignalKind::hangup())?))
+    }
+
+    #[cfg(not(unix))]
+    fn new() -> Result<Self> {
+        Ok(Self())
+    }
+
+    #[cfg(unix)]
+    async fn recv(&mut self) {
+        if self.0.recv().await.is_none() {
+            std::future::pending::<()>().await;
+        }
+    }
+
+    #[cfg(not(unix))]
+    async fn recv(&mut self) {
+        std::future::pending::<()>().await;
+    }
+}
@@ -1,65 +1,321 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::HashMap,
+    net::{IpAddr, Ipv4Addr},
+    path::PathBuf,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
 
-use anyhow::Result;
+use anyhow::{anyhow, Context as _, Result};
 
 use crate::{
-    config::{Config, ProviderConfig},
-    crp::{external::ExternalCrp, ipfs::IpfsCrp, iroh::IrohCrp, Crp},
+    auth::ApiKeys,
+    config::{Config, ProviderConfig, ProviderEntry, ProxyConfig, RouteSelection},
+    config_history::{ConfigHistory, ConfigRevision, RevisionSource},
+    crp::{
+        external::ExternalCrp, github::GithubCrp, ipfs::IpfsCrp, iroh::IrohCrp,
+        peer_router::PeerRouterCrp, Crp,
+    },
+    egress::EgressAllowList,
+    provider::{Provider, ProviderRegistry, ProviderSettings},
+    rate_limit::{RateLimitConfig, RateLimiter},
 };
 
+/// Timeout for route lookups against a provider when none is configured, in milliseconds
+const DEFAULT_PROVIDER_TIMEOUT_MS: u64 = 30_000;
+
+const DEFAULT_BIND_ADDR: IpAddr = IpAddr::V4(Ipv4Addr::UNSPECIFIED);
+
 pub struct Context {
     pub start_time: i64,
+    pub bind_addr: IpAddr,
     pub port: u16,
-    pub providers: HashMap<String, Arc<dyn Crp + Send + Sync>>,
+    /// Config file the router was started from, if any, which reloads re-read
+    pub config_path: Option<PathBuf>,
+    pub settings: RwLock<RouterSettings>,
+    pub providers: ProviderRegistry,
+    pub config_history: ConfigHistory,
+    pub api_keys: ApiKeys,
+    pub rate_limiter: RateLimiter,
+}
+
+/// Router-wide settings, which are replaced when the config is reloaded
+#[derive(Debug, Clone)]
+pub struct RouterSettings {
+    /// Region routes are preferred from when a request doesn't give one
+    pub region: Option<String>,
+    /// Order routes for a CID are returned in, after provider priority
+    pub route_selection: RouteSelection,
+    /// Timeout for route lookups against providers that don't set one
+    pub default_provider_timeout: Duration,
+    /// Proxy for providers that don't set one
+    pub default_proxy: Option<ProxyConfig>,
+    /// File to write the egress allow-list to whenever the providers change
+    pub egress_file: Option<PathBuf>,
+    /// Rate limits on the route lookup endpoints
+    pub rate_limit: Option<RateLimitConfig>,
+}
+
+/// Provider IDs added and removed by a config reload
+#[derive(Debug, Clone)]
+pub struct ReloadSummary {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
 }
 
 impl Context {
-    pub async fn init_from_config(config: Config) -> Result<Self> {
+    pub async fn init_from_config(config: Config, config_path: Option<PathBuf>) -> Result<Self> {
         let start_time = chrono::Utc::now().timestamp();
 
+        let bind_addr = config.bind_addr.unwrap_or(DEFAULT_BIND_ADDR);
         let port = config.port;
 
-        let providers = {
-            let mut ps = config
-                .providers
-                .into_iter()
-                .map(|provider| {
-                    let provider = match provider.clone() {
-                        ProviderConfig::External(external_crp_config) => Box::new(
-                            ExternalCrp::new_from_config(external_crp_config, provider)
-                                .expect("failed to create an external crp from config"),
-                        )
-                            as Box<dyn Crp + Send + Sync>,
-                        ProviderConfig::Ipfs(ipfs_crp_config) => Box::new(
-                            IpfsCrp::new_from_config(ipfs_crp_config, provider)
-                                .expect("failed to create an ipfs crp from config"),
-                        )
-                            as Box<dyn Crp + Send + Sync>,
-                        ProviderConfig::Iroh(iroh_crp_config) => Box::new(
-                            IrohCrp::new_from_config(iroh_crp_config, provider)
-                                .expect("failed to create an iroh crp from config"),
-                        )
-                            as Box<dyn Crp + Send + Sync>,
-                    };
-                    let id = provider.provider_id();
-
-                    (id, provider)
-                })
-                .collect::<HashMap<String, Box<dyn Crp + Send + Sync>>>();
-
-            for (_, provider) in ps.iter_mut() {
-                provider.init().await?;
+        let ctx = Self {
+            start_time,
+            bind_addr,
+            port,
+            config_path,
+            settings: RwLock::new(RouterSettings::from_config(&config)),
+            providers: ProviderRegistry::default(),
+            config_history: ConfigHistory::load(config.config_history_file.clone())?,
+            api_keys: ApiKeys::load(config.api_keys_file.clone())?,
+            rate_limiter: RateLimiter::default(),
+        };
+
+        let open_admin_endpoints = config.open_admin_endpoints.unwrap_or(false);
+
+        ctx.apply_config(config.clone()).await?;
+        ctx.record_revision(config, RevisionSource::Startup, None);
+
+        if ctx.api_keys.list().is_empty() {
+            if open_admin_endpoints {
+                tracing::warn!("No api_keys are configured, admin endpoints are open");
+            } else {
+                tracing::warn!("No api_keys are configured, admin endpoints are closed");
             }
+        }
+
+        Ok(ctx)
+    }
+
+    pub fn settings(&self) -> RouterSettings {
+        self.settings
+            .read()
+            .expect("router settings lock poisoned")
+            .clone()
+    }
+
+    /// Re-read the config file and apply it.
+    ///
+    /// `bind_addr` and `port` only take effect on restart. Providers added through the API that
+    /// aren't in the config are removed.
+    pub async fn reload(&self) -> Result<ReloadSummary> {
+        let path = self
+            .config_path
+            .clone()
+            .ok_or_else(|| anyhow!("the router wasn't started from a config file"))?;
+
+        let config = Config::from_file(path)?;
+
+        let summary = self.apply_config(config.clone()).await?;
+        self.record_revision(config, RevisionSource::Reload, None);
+
+        Ok(summary)
+    }
+
+    /// Apply the config of an earlier revision, recording it as a new revision.
+    ///
+    /// The config file isn't changed, so the rollback is undone by the next reload unless the
+    /// file is changed to match.
+    pub async fn rollback(&self, revision: ConfigRevision) -> Result<ReloadSummary> {
+        let summary = self.apply_config(revision.config.clone()).await?;
+        self.record_revision(
+            revision.config,
+            RevisionSource::Rollback,
+            Some(revision.revision),
+        );
+
+        Ok(summary)
+    }
+
+    /// Record an applied config in the history, the config is already applied so failing to
+    /// record it is only logged
+    fn record_revision(&self, config: Config, source: RevisionSource, rolled_back_to: Option<u64>) {
+        if let Err(e) = self.config_history.record(config, source, rolled_back_to) {
+            tracing::error!("Failed to record config revision: {e:#}");
+        }
+    }
+
+    /// Replace the settings, configured API keys and providers with the config's. API keys created
+    /// through the API are kept.
+    ///
+    /// Providers whose config is unchanged are kept along with their stats. New providers are
+    /// initialized before anything is replaced, so if any fails the router is left as it was.
+    async fn apply_config(&self, config: Config) -> Result<ReloadSummary> {
+        if config.bind_addr.unwrap_or(DEFAULT_BIND_ADDR) != self.bind_addr
+            || config.port != self.port
+        {
+            tracing::warn!("Changes to bind_addr and port take effect on restart");
+        }
+
+        let settings = RouterSettings::from_config(&config);
 
-            ps.into_iter()
-                .map(|(id, provider)| (id, Arc::from(provider)))
-                .collect::<HashMap<String, Arc<dyn Crp + Send + Sync>>>()
+        let api_keys = config.api_keys.unwrap_or_default();
+        let open_admin_endpoints = config.open_admin_endpoints.unwrap_or(false);
+        let expose_callstacks = config.expose_callstacks;
+
+        let current = self.providers.snapshot();
+
+        let mut providers = HashMap::new();
+
+        for entry in config.providers {
+            let (mut crp, provider_settings) = new_crp(entry, &settings)?;
+
+            let id = crp.provider_id();
+
+            let provider = match current.get(&id) {
+                Some(provider) => provider.with_settings(provider_settings),
+                None => {
+                    crp.init()
+                        .await
+                        .with_context(|| format!("failed to initialize provider={id}"))?;
+
+                    Provider::new(Arc::from(crp), provider_settings)
+                }
+            };
+
+            providers.insert(id, provider);
+        }
+
+        let mut added = providers
+            .keys()
+            .filter(|id| !current.contains_key(*id))
+            .cloned()
+            .collect::<Vec<_>>();
+        added.sort();
+
+        let mut removed = current
+            .keys()
+            .filter(|id| !providers.contains_key(*id))
+            .cloned()
+            .collect::<Vec<_>>();
+        removed.sort();
+
+        self.providers.replace(providers);
+        self.api_keys.set_configured(api_keys);
+        self.api_keys.set_open_without_keys(open_admin_endpoints);
+
+        if let Some(expose_callstacks) = expose_callstacks {
+            api_utils::error::set_expose_callstacks(expose_callstacks);
+        }
+
+        self.export_egress();
+        *self
+            .settings
+            .write()
+            .expect("router settings lock poisoned") = settings;
+
+        Ok(ReloadSummary { added, removed })
+    }
+
+    /// Write the egress allow-list to the configured file, if any
+    pub fn export_egress(&self) {
+        let Some(egress_file) = self.settings().egress_file else {
+            return;
         };
 
-        Ok(Self {
-            start_time,
-            port,
-            providers,
-        })
+        if let Err(e) =
+            EgressAllowList::from_providers(&self.providers.snapshot()).write_to_file(&egress_file)
+        {
+            tracing::error!(
+                "Failed to write egress allow-list to file={}: {e:#}",
+                egress_file.display()
+            );
+        }
+    }
+
+    /// Create an uninitialized CRP and its router-side settings from a provider entry, falling
+    /// back to the router's defaults for settings the entry doesn't give
+    pub fn new_crp(
+        &self,
+        entry: ProviderEntry,
+    ) -> Result<(Box<dyn Crp + Send + Sync>, ProviderSettings)> {
+        new_crp(entry, &self.settings())
+    }
+}
+
+impl RouterSettings {
+    fn from_config(config: &Config) -> Self {
+        Self {
+            region: config.region.clone(),
+            route_selection: config.route_selection.unwrap_or_default(),
+            default_provider_timeout: Duration::from_millis(
+                config
+                    .provider_timeout_ms
+                    .unwrap_or(DEFAULT_PROVIDER_TIMEOUT_MS),
+            ),
+            default_proxy: config.proxy.clone(),
+            egress_file: config.egress_file.clone(),
+            rate_limit: config.rate_limit.clone(),
+        }
     }
 }
+
+fn new_crp(
+    entry: ProviderEntry,
+    router_settings: &RouterSettings,
+) -> Result<(Box<dyn Crp + Send + Sync>, ProviderSettings)> {
+    let ProviderEntry {
+        provider,
+        timeout_ms,
+        critical,
+        region,
+        proxy,
+        visibility,
+        priority,
+    } = entry;
+
+    // iroh connects over its own transport rather than through a proxy
+    let proxy = match provider {
+        ProviderConfig::Iroh(_) => None,
+        _ => proxy.or(router_settings.default_proxy.clone()),
+    };
+
+    let crp = match provider.clone() {
+        ProviderConfig::External(external_crp_config) => Box::new(
+            ExternalCrp::new_from_config(external_crp_config, provider, proxy.as_ref())
+                .context("failed to create an external crp from config")?,
+        ) as Box<dyn Crp + Send + Sync>,
+        ProviderConfig::Github(github_crp_config) => Box::new(
+            GithubCrp::new_from_config(github_crp_config, provider, proxy.as_ref())
+                .context("failed to create a github crp from config")?,
+        ) as Box<dyn Crp + Send + Sync>,
+        ProviderConfig::Ipfs(ipfs_crp_config) => Box::new(
+            IpfsCrp::new_from_config(ipfs_crp_config, provider, proxy.as_ref())
+                .context("failed to create an ipfs crp from config")?,
+        ) as Box<dyn Crp + Send + Sync>,
+        ProviderConfig::Iroh(iroh_crp_config) => Box::new(
+            IrohCrp::new_from_config(iroh_crp_config, provider)
+                .context("failed to create an iroh crp from config")?,
+        ) as Box<dyn Crp + Send + Sync>,
+        ProviderConfig::PeerRouter(peer_router_crp_config) => Box::new(
+            PeerRouterCrp::new_from_config(peer_router_crp_config, provider, proxy.as_ref())
+                .context("failed to create a peer router crp from config")?,
+        )
+            as Box<dyn Crp + Send + Sync>,
+    };
+
+    let settings = ProviderSettings {
+        timeout: timeout_ms.map_or(
+            router_settings.default_provider_timeout,
+            Duration::from_millis,
+        ),
+        critical: critical.unwrap_or(false),
+        region,
+        proxy,
+        visibility: visibility.unwrap_or_default(),
+        priority: priority.unwrap_or(0),
+    };
+
+    Ok((crp, settings))
+}
@@ -3,11 +3,14 @@ use async_trait::async_trait;
 use cid::Cid;
 use cid_filter::CidFilter;
 use reqwest::StatusCode;
-use routes::Route;
+use routes::{Route, RouteMethod};
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde_json::{json, Value};
 
-use crate::{config::ProviderConfig, crp::Crp};
+use crate::{
+    config::{ProviderConfig, ProxyConfig},
+    crp::{http_client_builder, Crp, CrpError, UpstreamHost},
+};
 
 #[derive(Debug)]
 pub struct ExternalCrp {
@@ -26,9 +29,10 @@ impl ExternalCrp {
     pub fn new_from_config(
         external_crp_config: ExternalCrpConfig,
         config: ProviderConfig,
+        proxy: Option<&ProxyConfig>,
     ) -> Result<Self> {
         let ExternalCrpConfig { url: base_url } = external_crp_config;
-        let client = reqwest::Client::new();
+        let client = http_client_builder(proxy)?.build()?;
         let filter = CidFilter::None;
 
         Ok(Self {
@@ -59,19 +63,104 @@ impl Crp for ExternalCrp {
 
         let url = format!("{base_url}/routes/{cid}");
 
-        let response = client.get(&url).send().await?;
-
-        let routes = if response.status() == StatusCode::OK {
-            let mut json = response.json::<Value>().await?;
-            let routes = json["routes"].take();
-            serde_json::from_value(routes)?
-        } else {
-            bail!("failed to fetch routes for CID: {}", response.text().await?);
-        };
+        let response = client
+            .get(&url)
+            .send()
+            .await
+            .map_err(CrpError::from_reqwest)?;
+
+        let status = response.status();
+        if status != StatusCode::OK {
+            let text = response.text().await.unwrap_or_default();
+            return Err(CrpError::from_status(
+                status,
+                format!("external crp responded with status {status}: {text}"),
+            )
+            .into());
+        }
+
+        let mut json = response
+            .json::<Value>()
+            .await
+            .map_err(CrpError::from_reqwest)?;
+        let routes: Vec<Route> = serde_json::from_value(json["routes"].take())
+            .map_err(|e| CrpError::Corrupt(format!("malformed routes: {e}")))?;
+
+        let provider_id = self.provider_id();
+
+        let routes = routes
+            .into_iter()
+            .filter_map(|route| match normalize_route(route, &provider_id) {
+                Ok(route) => Some(route),
+                Err(e) => {
+                    tracing::warn!(
+                        "dropping malformed route for cid={cid} from provider={provider_id}: {e}"
+                    );
+                    None
+                }
+            })
+            .collect();
 
         Ok(routes)
     }
 
+    async fn resolve_url(&self, url: &str) -> Result<Option<(Cid, Route)>> {
+        let Self {
+            base_url, client, ..
+        } = self;
+
+        let response = client
+            .post(format!("{base_url}/resolve"))
+            .json(&json!({ "url": url }))
+            .send()
+            .await
+            .map_err(CrpError::from_reqwest)?;
+
+        match response.status() {
+            StatusCode::OK => {}
+            // the crp doesn't recognize or hasn't indexed the url, or doesn't support resolving
+            StatusCode::BAD_REQUEST | StatusCode::NOT_FOUND | StatusCode::METHOD_NOT_ALLOWED => {
+                return Ok(None)
+            }
+            status => {
+                return Err(CrpError::from_status(
+                    status,
+                    format!("failed to resolve url, external crp responded with status {status}"),
+                )
+                .into())
+            }
+        }
+
+        let mut json = response
+            .json::<Value>()
+            .await
+            .map_err(CrpError::from_reqwest)?;
+        let cid = Cid::try_from(json["cid"].as_str().unwrap_or_default())
+            .map_err(|e| CrpError::Corrupt(format!("malformed cid: {e}")))?;
+        let route = serde_json::from_value::<Route>(json["route"].take())
+            .map_err(|e| CrpError::Corrupt(format!("malformed route: {e}")))?;
+
+        Ok(Some((cid, normalize_route(route, &self.provider_id())?)))
+    }
+
+    async fn check_health(&self) -> Result<()> {
+        let response = self
+            .client
+            .get(format!("{}/filter", self.base_url))
+            .send()
+            .await?;
+
+        if response.status() != StatusCode::OK {
+            bail!("external crp responded with status {}", response.status());
+        }
+
+        Ok(())
+    }
+
+    fn upstream_hosts(&self) -> Result<Vec<UpstreamHost>> {
+        Ok(vec![UpstreamHost::from_url(&self.base_url)?])
+    }
+
     fn provider_config(&self) -> Value {
         serde_json::to_value(&self.config).expect("unexpectedly failed to serialize a config type")
     }
@@ -81,7 +170,7 @@ impl ExternalCrp {
     async fn populate_filter(&mut self) -> Result<()> {
         let response = self
             .client
-            .get(&format!("{}/filter", self.base_url))
+            .get(format!("{}/filter", self.base_url))
             .send()
             .await?;
 
@@ -98,3 +187,16 @@ impl ExternalCrp {
         Ok(())
     }
 }
+
+/// Re-encode routes of known types in their canonical form and attribute all routes to the provider.
+/// Routes of unknown types are passed through as is.
+fn normalize_route(route: Route, provider_id: &str) -> Result<Route> {
+    let crp_id = Some(provider_id.to_owned());
+
+    let route = match RouteMethod::from_route(&route)? {
+        Some(route_method) => route_method.into_route(crp_id, route.metadata)?,
+        None => Route { crp_id, ..route },
+    };
+
+    Ok(route)
+}
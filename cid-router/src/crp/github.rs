@@ -0,0 +1,231 @@
+use anyhow::{bail, Result};
+use async_trait::async_trait;
+use cid::{multihash::Multihash, Cid};
+use cid_filter::{
+    table::{multicodec::GIT_RAW, multihash::SHA1},
+    CidFilter, CodeFilter,
+};
+use reqwest::{RequestBuilder, Response, StatusCode};
+use routes::{GithubRef, GithubRouteMethod, IntoRoute, Route};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    config::{ProviderConfig, ProxyConfig},
+    crp::{http_client_builder, Crp, CrpError, UpstreamHost},
+};
+
+const DEFAULT_API_URL: &str = "https://api.github.com";
+
+/// Serves GitHub routes for git commit CIDs by looking the commit up in each configured repo
+#[derive(Debug)]
+pub struct GithubCrp {
+    api_url: String,
+    repos: Vec<GithubRepo>,
+    token: Option<String>,
+    client: reqwest::Client,
+    config: ProviderConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GithubCrpConfig {
+    pub repos: Vec<GithubRepo>,
+    /// GitHub API URL (defaults to https://api.github.com)
+    pub api_url: Option<String>,
+    /// Environment variable holding a GitHub token, for private repos and higher rate limits
+    pub token_env: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GithubRepo {
+    pub owner: String,
+    pub repo: String,
+}
+
+impl GithubCrp {
+    pub fn new_from_config(
+        github_crp_config: GithubCrpConfig,
+        config: ProviderConfig,
+        proxy: Option<&ProxyConfig>,
+    ) -> Result<Self> {
+        let GithubCrpConfig {
+            repos,
+            api_url,
+            token_env,
+        } = github_crp_config;
+
+        let api_url = api_url.unwrap_or_else(|| DEFAULT_API_URL.to_owned());
+        let token = token_env.map(std::env::var).transpose()?;
+        let client = http_client_builder(proxy)?
+            .user_agent("cid-router")
+            .build()?;
+
+        Ok(Self {
+            api_url,
+            repos,
+            token,
+            client,
+            config,
+        })
+    }
+
+    fn get(&self, url: &str) -> RequestBuilder {
+        let request = self
+            .client
+            .get(url)
+            .header("Accept", "application/vnd.github+json");
+
+        match &self.token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+}
+
+#[async_trait]
+impl Crp for GithubCrp {
+    async fn init(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn cid_filter(&self) -> CidFilter {
+        CidFilter::MultihashCodeFilter(CodeFilter::Eq(SHA1))
+            & CidFilter::CodecFilter(CodeFilter::Eq(GIT_RAW))
+    }
+
+    async fn get_routes_for_cid(&self, cid: &Cid) -> Result<Vec<Route>> {
+        let Self { api_url, repos, .. } = self;
+
+        let commit = hex::encode(cid.hash().digest());
+
+        let crp_id = Some(self.provider_id());
+
+        let mut routes = vec![];
+
+        for GithubRepo { owner, repo } in repos {
+            let url = format!("{api_url}/repos/{owner}/{repo}/commits/{commit}");
+
+            let response = self
+                .get(&url)
+                .send()
+                .await
+                .map_err(CrpError::from_reqwest)?;
+
+            match response.status() {
+                StatusCode::OK => routes.push(
+                    GithubRouteMethod {
+                        owner: owner.clone(),
+                        repo: repo.clone(),
+                        ref_: GithubRef::Commit(commit.clone()),
+                        path: None,
+                    }
+                    .into_route(crp_id.clone(), None)?,
+                ),
+                // github responds 422 for commit shas it doesn't know
+                StatusCode::NOT_FOUND | StatusCode::UNPROCESSABLE_ENTITY => {}
+                _ => return Err(status_error(&response, owner, repo).into()),
+            }
+        }
+
+        Ok(routes)
+    }
+
+    async fn resolve_url(&self, url: &str) -> Result<Option<(Cid, Route)>> {
+        let Self { api_url, repos, .. } = self;
+
+        let Some((owner, repo, commit)) = parse_commit_url(url) else {
+            return Ok(None);
+        };
+
+        let Some(GithubRepo { owner, repo }) = repos
+            .iter()
+            .find(|r| r.owner.eq_ignore_ascii_case(owner) && r.repo.eq_ignore_ascii_case(repo))
+        else {
+            return Ok(None);
+        };
+
+        let url = format!("{api_url}/repos/{owner}/{repo}/commits/{commit}");
+
+        let response = self
+            .get(&url)
+            .send()
+            .await
+            .map_err(CrpError::from_reqwest)?;
+
+        match response.status() {
+            StatusCode::OK => {}
+            StatusCode::NOT_FOUND | StatusCode::UNPROCESSABLE_ENTITY => return Ok(None),
+            _ => return Err(status_error(&response, owner, repo).into()),
+        }
+
+        // a git commit's CID is its sha1 as a git-raw CID, there's no content to hash
+        let cid = Cid::new_v1(GIT_RAW, Multihash::wrap(SHA1, &hex::decode(commit)?)?);
+
+        let route = GithubRouteMethod {
+            owner: owner.clone(),
+            repo: repo.clone(),
+            ref_: GithubRef::Commit(commit.to_owned()),
+            path: None,
+        }
+        .into_route(Some(self.provider_id()), None)?;
+
+        Ok(Some((cid, route)))
+    }
+
+    async fn check_health(&self) -> Result<()> {
+        let Self { api_url, .. } = self;
+
+        let url = format!("{api_url}/rate_limit");
+
+        let response = self.get(&url).send().await?;
+
+        if response.status() != StatusCode::OK {
+            bail!("github responded with status {}", response.status());
+        }
+
+        Ok(())
+    }
+
+    fn upstream_hosts(&self) -> Result<Vec<UpstreamHost>> {
+        Ok(vec![UpstreamHost::from_url(&self.api_url)?])
+    }
+
+    fn provider_config(&self) -> Value {
+        serde_json::to_value(&self.config).expect("unexpectedly failed to serialize a config type")
+    }
+}
+
+/// Error for a GitHub API response with an unexpected status. GitHub responds 403 rather than
+/// 429 once the token's rate limit is used up.
+fn status_error(response: &Response, owner: &str, repo: &str) -> CrpError {
+    let status = response.status();
+    let message = format!("github responded with status {status} for {owner}/{repo}");
+
+    let rate_limit_exhausted = response
+        .headers()
+        .get("x-ratelimit-remaining")
+        .is_some_and(|remaining| remaining == "0");
+
+    if status == StatusCode::FORBIDDEN && rate_limit_exhausted {
+        return CrpError::RateLimited(message);
+    }
+
+    CrpError::from_status(status, message)
+}
+
+/// Owner, repo and full commit sha of a `https://github.com/{owner}/{repo}/commit/{sha}` URL
+fn parse_commit_url(url: &str) -> Option<(&str, &str, &str)> {
+    let path = url
+        .strip_prefix("https://github.com/")?
+        .split(['?', '#'])
+        .next()?;
+
+    match path.trim_end_matches('/').split('/').collect::<Vec<_>>()[..] {
+        [owner, repo, "commit", commit]
+            if commit.len() == 40 && commit.chars().all(|c| c.is_ascii_hexdigit()) =>
+        {
+            Some((owner, repo, commit))
+        }
+        _ => None,
+    }
+}
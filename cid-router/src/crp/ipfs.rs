@@ -1,4 +1,4 @@
-use anyhow::Result;
+use anyhow::{bail, Result};
 use async_trait::async_trait;
 use cid::Cid;
 use cid_filter::{CidFilter, CodeFilter};
@@ -7,7 +7,10 @@ use routes::{IntoRoute, IpfsRouteMethod, Route, UrlRouteMethod};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
-use crate::{config::ProviderConfig, crp::Crp};
+use crate::{
+    config::{ProviderConfig, ProxyConfig},
+    crp::{http_client_builder, Crp, CrpError, UpstreamHost},
+};
 
 #[derive(Debug)]
 pub struct IpfsCrp {
@@ -22,9 +25,13 @@ pub struct IpfsCrpConfig {
 }
 
 impl IpfsCrp {
-    pub fn new_from_config(ipfs_crp_config: IpfsCrpConfig, config: ProviderConfig) -> Result<Self> {
+    pub fn new_from_config(
+        ipfs_crp_config: IpfsCrpConfig,
+        config: ProviderConfig,
+        proxy: Option<&ProxyConfig>,
+    ) -> Result<Self> {
         let IpfsCrpConfig { gateway_url } = ipfs_crp_config;
-        let client = reqwest::Client::new();
+        let client = http_client_builder(proxy)?.build()?;
 
         Ok(Self {
             gateway_url,
@@ -53,7 +60,12 @@ impl Crp for IpfsCrp {
 
         let url = format!("{gateway_url}/ipfs/{cid}");
 
-        let response = self.client.head(&url).send().await?;
+        let response = self
+            .client
+            .head(&url)
+            .send()
+            .await
+            .map_err(CrpError::from_reqwest)?;
 
         let crp_id = Some(self.provider_id());
 
@@ -69,6 +81,25 @@ impl Crp for IpfsCrp {
         }
     }
 
+    async fn check_health(&self) -> Result<()> {
+        let Self { gateway_url, .. } = self;
+
+        // identity CID of empty content, any working gateway can serve it without a network lookup
+        let url = format!("{gateway_url}/ipfs/bafkqaaa");
+
+        let response = self.client.head(&url).send().await?;
+
+        if response.status() != StatusCode::OK {
+            bail!("ipfs gateway responded with status {}", response.status());
+        }
+
+        Ok(())
+    }
+
+    fn upstream_hosts(&self) -> Result<Vec<UpstreamHost>> {
+        Ok(vec![UpstreamHost::from_url(&self.gateway_url)?])
+    }
+
     fn provider_config(&self) -> Value {
         serde_json::to_value(&self.config).expect("unexpectedly failed to serialize a config type")
     }
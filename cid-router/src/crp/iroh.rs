@@ -15,9 +15,12 @@ use iroh_bytes::get::request::get_verified_size;
 use iroh_net::{key::SecretKey, MagicEndpoint};
 use routes::{IntoRoute, IrohRouteMethod, Route};
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde_json::{json, Value};
 
-use crate::{config::ProviderConfig, crp::Crp};
+use crate::{
+    config::ProviderConfig,
+    crp::{Crp, Transport, UpstreamHost},
+};
 
 #[derive(Debug)]
 pub struct IrohCrp {
@@ -70,17 +73,11 @@ impl Crp for IrohCrp {
     async fn get_routes_for_cid(&self, cid: &Cid) -> Result<Vec<Route>> {
         let Self { node_addr, .. } = self;
 
-        let secret_key = SecretKey::generate();
-
         let hash = cid.hash().digest();
         let hash: [u8; 32] = hash.try_into()?;
         let hash = Hash::from_bytes(hash);
 
-        let endpoint = MagicEndpoint::builder()
-            .alpns(vec![])
-            .secret_key(secret_key)
-            .bind(0)
-            .await?;
+        let endpoint = self.bind_endpoint().await?;
 
         let connection = endpoint
             .connect(node_addr.clone(), iroh_bytes::protocol::ALPN)
@@ -88,25 +85,62 @@ impl Crp for IrohCrp {
 
         // TODO: this just checks the node has the last blake3 chunk of the blob,
         //       it's not guaranteed to have the full blob and/or any linked blobs
+        // a verified size of 0 is a valid empty blob, the request fails if the node doesn't have it
         let (size, _) = get_verified_size(&connection, &hash).await?;
 
-        let metadata = None;
+        let metadata = Some(json!({ "size": size }));
 
-        let routes = if size > 0 {
-            // TODO: how to determine blob format? for now just only supporting raw
-            let blob_format = BlobFormat::Raw;
+        // TODO: how to determine blob format? for now just only supporting raw
+        let blob_format = BlobFormat::Raw;
 
-            let ticket = BlobTicket::new(node_addr.clone(), hash, blob_format)?.to_string();
+        let ticket = BlobTicket::new(node_addr.clone(), hash, blob_format)?.to_string();
 
-            vec![IrohRouteMethod { ticket }.into_route(Some(self.provider_id()), metadata)?]
-        } else {
-            vec![]
-        };
+        let routes =
+            vec![IrohRouteMethod { ticket }.into_route(Some(self.provider_id()), metadata)?];
 
         Ok(routes)
     }
 
+    async fn check_health(&self) -> Result<()> {
+        let endpoint = self.bind_endpoint().await?;
+
+        endpoint
+            .connect(self.node_addr.clone(), iroh_bytes::protocol::ALPN)
+            .await?;
+
+        Ok(())
+    }
+
+    fn upstream_hosts(&self) -> Result<Vec<UpstreamHost>> {
+        // a node known only by its ID is found through discovery, which isn't included
+        let mut upstream_hosts = self
+            .node_addr
+            .direct_addresses()
+            .map(|addr| UpstreamHost::from_socket_addr(*addr, Transport::Udp))
+            .collect::<Vec<_>>();
+
+        if let Some(relay_url) = self.node_addr.relay_url() {
+            upstream_hosts.push(UpstreamHost::from_url(&relay_url.to_string())?);
+        }
+
+        Ok(upstream_hosts)
+    }
+
     fn provider_config(&self) -> Value {
         serde_json::to_value(&self.config).expect("unexpectedly failed to serialize a config type")
     }
 }
+
+impl IrohCrp {
+    async fn bind_endpoint(&self) -> Result<MagicEndpoint> {
+        let secret_key = SecretKey::generate();
+
+        let endpoint = MagicEndpoint::builder()
+            .alpns(vec![])
+            .secret_key(secret_key)
+            .bind(0)
+            .await?;
+
+        Ok(endpoint)
+    }
+}
@@ -1,24 +1,67 @@
 pub mod external;
+pub mod github;
 pub mod ipfs;
 pub mod iroh;
+pub mod peer_router;
 
-use anyhow::Result;
+use std::{fmt, net::SocketAddr};
+
+use anyhow::{anyhow, Result};
+use api_utils::ErrorCode;
 use async_trait::async_trait;
 use cid::{multihash::Multihash, Cid};
 use cid_filter::CidFilter;
+use reqwest::{NoProxy, Proxy, StatusCode, Url};
 use routes::Route;
+use serde::Serialize;
 use serde_json::Value;
 use sha2::{Digest, Sha256};
+use utoipa::ToSchema;
+
+use crate::config::ProxyConfig;
 
 /// CID Route Provider (CRP) Trait
+///
+/// Lookups fail with a [`CrpError`] where the provider can tell why, other errors are treated as
+/// transient.
 #[async_trait]
 pub trait Crp {
     async fn init(&mut self) -> Result<()>;
 
     fn cid_filter(&self) -> CidFilter;
 
+    /// Only look up routes from the provider for CIDs none of the other providers have routes for
+    fn is_fallback(&self) -> bool {
+        false
+    }
+
     async fn get_routes_for_cid(&self, cid: &Cid) -> Result<Vec<Route>>;
 
+    /// CID of the content at a URL the provider serves, computing it if need be, along with the
+    /// route to it. `None` if the provider doesn't recognize the URL.
+    async fn resolve_url(&self, _url: &str) -> Result<Option<(Cid, Route)>> {
+        Ok(None)
+    }
+
+    /// Check the provider's backing service is reachable and usable
+    async fn check_health(&self) -> Result<()>;
+
+    /// Hosts the provider sends requests to, for egress allow-lists
+    fn upstream_hosts(&self) -> Result<Vec<UpstreamHost>> {
+        Ok(vec![])
+    }
+
+    /// Socket addresses of the provider's backing service, for reachability diagnostics
+    async fn socket_addrs(&self) -> Result<Vec<(SocketAddr, Transport)>> {
+        let mut socket_addrs = vec![];
+
+        for upstream_host in self.upstream_hosts()? {
+            socket_addrs.extend(upstream_host.resolve().await?);
+        }
+
+        Ok(socket_addrs)
+    }
+
     fn provider_config(&self) -> Value;
 
     fn provider_is_eligible_for_cid(&self, cid: &Cid) -> bool {
@@ -26,17 +69,178 @@ pub trait Crp {
     }
 
     fn provider_id(&self) -> String {
-        // provider ID is the JCS CID of its config
-        let jcs = serde_jcs::to_string(&self.provider_config())
-            .expect("unexpectedly failed to serialize a config type");
-        let sha256 = {
-            let mut hasher = Sha256::new();
-            hasher.update(jcs.as_bytes());
-            hasher.finalize()
+        provider_id_from_config(&self.provider_config())
+    }
+}
+
+/// Why a provider lookup failed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CrpError {
+    /// The provider doesn't have the content, a route lookup that fails with it has no routes
+    NotFound(String),
+    /// The provider rejected the router's credentials
+    Unauthorized(String),
+    /// The provider is rate limiting the router
+    RateLimited(String),
+    /// The provider failed or couldn't be reached, retrying may succeed
+    Transient(String),
+    /// The provider responded with something the router couldn't parse
+    Corrupt(String),
+}
+
+impl CrpError {
+    /// Error for an HTTP response from a provider with a status the provider didn't expect
+    pub fn from_status(status: StatusCode, message: impl Into<String>) -> Self {
+        let message = message.into();
+
+        match status {
+            StatusCode::NOT_FOUND => Self::NotFound(message),
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Self::Unauthorized(message),
+            StatusCode::TOO_MANY_REQUESTS => Self::RateLimited(message),
+            status if status.is_server_error() => Self::Transient(message),
+            _ => Self::Corrupt(message),
+        }
+    }
+
+    /// Error for a failed request to a provider, responses that couldn't be decoded are corrupt
+    pub fn from_reqwest(e: reqwest::Error) -> Self {
+        if e.is_decode() {
+            Self::Corrupt(e.to_string())
+        } else {
+            Self::Transient(e.to_string())
+        }
+    }
+
+    /// Kind of failure of an error a provider returned, errors that aren't a `CrpError` are
+    /// transient
+    pub fn of(e: &anyhow::Error) -> Self {
+        e.downcast_ref::<Self>()
+            .cloned()
+            .unwrap_or_else(|| Self::Transient(e.to_string()))
+    }
+
+    pub fn error_code(&self) -> ErrorCode {
+        match self {
+            Self::NotFound(_) => ErrorCode::NotFound,
+            Self::Unauthorized(_) => ErrorCode::ProviderUnauthorized,
+            Self::RateLimited(_) => ErrorCode::ProviderRateLimited,
+            Self::Transient(_) => ErrorCode::ProviderUnavailable,
+            Self::Corrupt(_) => ErrorCode::ProviderInvalidResponse,
+        }
+    }
+
+    /// Error code for a lookup every provider failed, the one their errors share if they failed
+    /// the same way
+    pub fn combined_error_code<'a>(errors: impl IntoIterator<Item = &'a Self>) -> ErrorCode {
+        let mut codes = errors.into_iter().map(Self::error_code);
+
+        let Some(first) = codes.next() else {
+            return ErrorCode::ProviderUnavailable;
         };
-        let multihash =
-            Multihash::wrap(0x12, &sha256).expect("unexpectedly failed to wrap a multihash");
 
-        Cid::new_v1(0xb601, multihash).to_string()
+        if codes.all(|code| code == first) {
+            first
+        } else {
+            ErrorCode::ProviderUnavailable
+        }
+    }
+}
+
+impl fmt::Display for CrpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotFound(message) => write!(f, "not found: {message}"),
+            Self::Unauthorized(message) => write!(f, "unauthorized: {message}"),
+            Self::RateLimited(message) => write!(f, "rate limited: {message}"),
+            Self::Transient(message) => write!(f, "{message}"),
+            Self::Corrupt(message) => write!(f, "invalid response: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for CrpError {}
+
+/// Provider ID for a provider config, which is the JCS CID of the config
+pub fn provider_id_from_config(provider_config: &Value) -> String {
+    let jcs = serde_jcs::to_string(provider_config)
+        .expect("unexpectedly failed to serialize a config type");
+    let sha256 = {
+        let mut hasher = Sha256::new();
+        hasher.update(jcs.as_bytes());
+        hasher.finalize()
+    };
+    let multihash =
+        Multihash::wrap(0x12, &sha256).expect("unexpectedly failed to wrap a multihash");
+
+    Cid::new_v1(0xb601, multihash).to_string()
+}
+
+/// HTTP client builder for providers, sending requests through the proxy if one is configured
+pub fn http_client_builder(proxy: Option<&ProxyConfig>) -> Result<reqwest::ClientBuilder> {
+    let builder = reqwest::Client::builder();
+
+    let Some(ProxyConfig { url, no_proxy }) = proxy else {
+        return Ok(builder);
+    };
+
+    let proxy = Proxy::all(url)?.no_proxy(no_proxy.as_deref().and_then(NoProxy::from_string));
+
+    Ok(builder.proxy(proxy))
+}
+
+/// Transport a provider's backing service is reached over
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Transport {
+    Tcp,
+    Udp,
+}
+
+/// A host and port a provider sends requests to
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, ToSchema)]
+pub struct UpstreamHost {
+    /// Host name or IP address
+    pub host: String,
+    pub port: u16,
+    pub transport: Transport,
+}
+
+impl UpstreamHost {
+    /// Host HTTP(S) requests to the URL are sent to
+    pub fn from_url(url: &str) -> Result<Self> {
+        let url = Url::parse(url)?;
+
+        let port = url
+            .port_or_known_default()
+            .ok_or_else(|| anyhow!("no port for url={url}"))?;
+
+        // ipv6 hosts are bracketed in urls, lookup_host takes ip literals without them
+        let host = url
+            .host_str()
+            .ok_or_else(|| anyhow!("no host for url={url}"))?
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .to_owned();
+
+        Ok(Self {
+            host,
+            port,
+            transport: Transport::Tcp,
+        })
+    }
+
+    pub fn from_socket_addr(addr: SocketAddr, transport: Transport) -> Self {
+        Self {
+            host: addr.ip().to_string(),
+            port: addr.port(),
+            transport,
+        }
+    }
+
+    /// Resolve the host to the socket addresses requests to it can go to
+    pub async fn resolve(&self) -> Result<Vec<(SocketAddr, Transport)>> {
+        let addrs = tokio::net::lookup_host((self.host.as_str(), self.port)).await?;
+
+        Ok(addrs.map(|addr| (addr, self.transport)).collect())
     }
 }
@@ -0,0 +1,275 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, bail, Result};
+use async_trait::async_trait;
+use cid::Cid;
+use cid_filter::CidFilter;
+use reqwest::StatusCode;
+use routes::Route;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    config::{ProviderConfig, ProxyConfig},
+    crp::{http_client_builder, Crp, CrpError, UpstreamHost},
+};
+
+/// Header marking a route lookup as made by a peer router. Peer routers aren't queried for these
+/// lookups, so routers peering with each other don't forward a lookup back and forth.
+pub const PEER_REQUEST_HEADER: &str = "x-cid-router-peer";
+
+/// Most CIDs whose routes are cached when no limit is configured
+const DEFAULT_CACHE_MAX_ENTRIES: usize = 10_000;
+
+/// Looks up routes from other cid-router instances, for CIDs none of the router's other providers
+/// have routes for
+#[derive(Debug)]
+pub struct PeerRouterCrp {
+    urls: Vec<String>,
+    api_key: Option<String>,
+    client: reqwest::Client,
+    cache: Option<RouteCache>,
+    config: ProviderConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PeerRouterCrpConfig {
+    /// Base URLs of the peer routers, e.g. "http://router.internal:3080"
+    pub urls: Vec<String>,
+    /// Environment variable holding an API key for the peers, for their non-public routes
+    pub api_key_env: Option<String>,
+    /// Keep routes from peers for this long, in milliseconds (defaults to not caching them)
+    pub cache_ttl_ms: Option<u64>,
+    /// Most CIDs to keep routes for, the oldest are dropped first (defaults to 10000)
+    pub cache_max_entries: Option<usize>,
+}
+
+/// Routes from peers by CID, along with where and when they were fetched
+#[derive(Debug)]
+struct RouteCache {
+    ttl: Duration,
+    max_entries: usize,
+    entries: Mutex<RouteCacheEntries>,
+}
+
+#[derive(Debug, Default)]
+struct RouteCacheEntries {
+    by_cid: HashMap<Cid, CachedRoutes>,
+    /// CIDs in the order they were cached, for dropping the oldest
+    order: VecDeque<Cid>,
+}
+
+#[derive(Debug, Clone)]
+struct CachedRoutes {
+    /// URL of the peer the routes came from
+    peer: String,
+    fetched_at: Instant,
+    routes: Vec<Route>,
+}
+
+impl PeerRouterCrp {
+    pub fn new_from_config(
+        peer_router_crp_config: PeerRouterCrpConfig,
+        config: ProviderConfig,
+        proxy: Option<&ProxyConfig>,
+    ) -> Result<Self> {
+        let PeerRouterCrpConfig {
+            urls,
+            api_key_env,
+            cache_ttl_ms,
+            cache_max_entries,
+        } = peer_router_crp_config;
+
+        if urls.is_empty() {
+            bail!("peer router provider has no urls");
+        }
+
+        let urls = urls
+            .into_iter()
+            .map(|url| url.trim_end_matches('/').to_owned())
+            .collect();
+        let api_key = api_key_env.map(std::env::var).transpose()?;
+        let client = http_client_builder(proxy)?.build()?;
+        let cache = cache_ttl_ms.map(|ttl_ms| RouteCache {
+            ttl: Duration::from_millis(ttl_ms),
+            max_entries: cache_max_entries.unwrap_or(DEFAULT_CACHE_MAX_ENTRIES),
+            entries: Mutex::new(RouteCacheEntries::default()),
+        });
+
+        Ok(Self {
+            urls,
+            api_key,
+            client,
+            cache,
+            config,
+        })
+    }
+
+    /// Routes a peer has for the CID, keeping the provider IDs the peer gave them so their
+    /// fingerprints match the peer's
+    async fn get_peer_routes(&self, url: &str, cid: &Cid) -> Result<Vec<Route>> {
+        let request = self
+            .client
+            .get(format!("{url}/v1/routes/{cid}"))
+            .header(PEER_REQUEST_HEADER, "1");
+
+        let request = match &self.api_key {
+            Some(api_key) => request.bearer_auth(api_key),
+            None => request,
+        };
+
+        let response = request.send().await.map_err(CrpError::from_reqwest)?;
+
+        let status = response.status();
+        if status != StatusCode::OK {
+            return Err(CrpError::from_status(
+                status,
+                format!("peer router={url} responded with status {status}"),
+            )
+            .into());
+        }
+
+        let mut json = response
+            .json::<Value>()
+            .await
+            .map_err(CrpError::from_reqwest)?;
+        let routes = serde_json::from_value::<Vec<Route>>(json["routes"].take()).map_err(|e| {
+            CrpError::Corrupt(format!("malformed routes from peer router={url}: {e}"))
+        })?;
+
+        let provider_id = self.provider_id();
+
+        let routes = routes
+            .into_iter()
+            .map(|route| Route {
+                crp_id: route.crp_id.or_else(|| Some(provider_id.clone())),
+                ..route
+            })
+            .collect();
+
+        Ok(routes)
+    }
+}
+
+#[async_trait]
+impl Crp for PeerRouterCrp {
+    async fn init(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn cid_filter(&self) -> CidFilter {
+        CidFilter::None
+    }
+
+    fn is_fallback(&self) -> bool {
+        true
+    }
+
+    async fn get_routes_for_cid(&self, cid: &Cid) -> Result<Vec<Route>> {
+        if let Some(cached) = self.cache.as_ref().and_then(|cache| cache.get(cid)) {
+            tracing::debug!(
+                "using cached routes for cid={cid} from peer router={}",
+                cached.peer
+            );
+            return Ok(cached.routes);
+        }
+
+        let responses =
+            futures::future::join_all(self.urls.iter().map(|url| self.get_peer_routes(url, cid)))
+                .await;
+
+        let mut failures = 0;
+
+        for (url, response) in self.urls.iter().zip(responses) {
+            match response {
+                Ok(routes) if !routes.is_empty() => {
+                    if let Some(cache) = &self.cache {
+                        cache.insert(*cid, url, routes.clone());
+                    }
+
+                    return Ok(routes);
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    tracing::warn!(
+                        "failed to get routes for cid={cid} from peer router={url}: {e}"
+                    );
+                    failures += 1;
+                }
+            }
+        }
+
+        if failures == self.urls.len() {
+            bail!("all peer routers failed");
+        }
+
+        Ok(vec![])
+    }
+
+    async fn check_health(&self) -> Result<()> {
+        let mut last_error = None;
+
+        for url in &self.urls {
+            match self.client.get(format!("{url}/healthz")).send().await {
+                Ok(response) if response.status() == StatusCode::OK => return Ok(()),
+                Ok(response) => {
+                    last_error = Some(anyhow!(
+                        "peer router={url} responded with status {}",
+                        response.status()
+                    ))
+                }
+                Err(e) => last_error = Some(e.into()),
+            }
+        }
+
+        Err(last_error.unwrap_or_else(|| anyhow!("peer router provider has no urls")))
+    }
+
+    fn upstream_hosts(&self) -> Result<Vec<UpstreamHost>> {
+        self.urls
+            .iter()
+            .map(|url| UpstreamHost::from_url(url))
+            .collect()
+    }
+
+    fn provider_config(&self) -> Value {
+        serde_json::to_value(&self.config).expect("unexpectedly failed to serialize a config type")
+    }
+}
+
+impl RouteCache {
+    fn get(&self, cid: &Cid) -> Option<CachedRoutes> {
+        let entries = self.entries.lock().expect("route cache lock poisoned");
+
+        entries
+            .by_cid
+            .get(cid)
+            .filter(|cached| cached.fetched_at.elapsed() < self.ttl)
+            .cloned()
+    }
+
+    fn insert(&self, cid: Cid, peer: &str, routes: Vec<Route>) {
+        let mut entries = self.entries.lock().expect("route cache lock poisoned");
+
+        let cached = CachedRoutes {
+            peer: peer.to_owned(),
+            fetched_at: Instant::now(),
+            routes,
+        };
+
+        if entries.by_cid.insert(cid, cached).is_none() {
+            entries.order.push_back(cid);
+        }
+
+        while entries.by_cid.len() > self.max_entries {
+            let Some(oldest) = entries.order.pop_front() else {
+                break;
+            };
+            entries.by_cid.remove(&oldest);
+        }
+    }
+}
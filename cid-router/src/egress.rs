@@ -0,0 +1,107 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::Path,
+    sync::Arc,
+};
+
+use anyhow::Result;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::{
+    crp::{Transport, UpstreamHost},
+    provider::Provider,
+};
+
+/// Hosts the router's providers send requests to, for deriving egress firewall rules
+#[derive(Serialize, ToSchema)]
+pub struct EgressAllowList {
+    hosts: Vec<EgressHost>,
+    /// Errors getting a provider's hosts by provider ID
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    errors: BTreeMap<String, String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct EgressHost {
+    /// Host name or IP address
+    host: String,
+    port: u16,
+    transport: Transport,
+    /// IDs of the providers sending requests to the host
+    providers: Vec<String>,
+}
+
+impl EgressAllowList {
+    /// Allow-list for the providers' hosts and the proxies they're configured with, requests through
+    /// a proxy can go to the proxy instead of the provider's hosts
+    pub fn from_providers(providers: &HashMap<String, Arc<Provider>>) -> Self {
+        let mut hosts = BTreeMap::<UpstreamHost, Vec<String>>::new();
+        let mut errors = BTreeMap::new();
+
+        for (provider_id, provider) in providers {
+            let proxy_host = provider
+                .settings
+                .proxy
+                .as_ref()
+                .map(|proxy| UpstreamHost::from_url(&proxy.url))
+                .transpose();
+
+            let upstream_hosts = provider
+                .crp
+                .upstream_hosts()
+                .and_then(|upstream_hosts| Ok(upstream_hosts.into_iter().chain(proxy_host?)));
+
+            match upstream_hosts {
+                Ok(upstream_hosts) => {
+                    for upstream_host in upstream_hosts {
+                        hosts
+                            .entry(upstream_host)
+                            .or_default()
+                            .push(provider_id.clone());
+                    }
+                }
+                Err(e) => {
+                    errors.insert(provider_id.clone(), e.to_string());
+                }
+            }
+        }
+
+        let hosts = hosts
+            .into_iter()
+            .map(
+                |(
+                    UpstreamHost {
+                        host,
+                        port,
+                        transport,
+                    },
+                    mut providers,
+                )| {
+                    providers.sort();
+
+                    EgressHost {
+                        host,
+                        port,
+                        transport,
+                        providers,
+                    }
+                },
+            )
+            .collect();
+
+        Self { hosts, errors }
+    }
+
+    /// Write the allow-list to a file as JSON, replacing the file in one step so readers never see
+    /// a partial write
+    pub fn write_to_file(&self, path: &Path) -> Result<()> {
+        let tmp_path = path.with_extension("tmp");
+
+        fs::write(&tmp_path, serde_json::to_string_pretty(self)?)?;
+        fs::rename(&tmp_path, path)?;
+
+        Ok(())
+    }
+}
@@ -1,5 +1,12 @@
 pub mod api;
+pub mod auth;
 pub mod cli;
 pub mod config;
+pub mod config_history;
+pub mod config_watcher;
 pub mod context;
 pub mod crp;
+pub mod egress;
+pub mod provider;
+pub mod rate_limit;
+pub mod telemetry;
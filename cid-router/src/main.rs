@@ -1,10 +1,17 @@
-use std::{fs, path::PathBuf, sync::Arc};
+use std::{fs, path::PathBuf, sync::Arc, time::Duration};
 
 use anyhow::Result;
-use cid_router::{api, cli, config::Config, context::Context};
+use api_utils::shutdown::DEFAULT_SHUTDOWN_TIMEOUT_MS;
+use cid_router::{
+    api, cli,
+    config::Config,
+    config_watcher::{self, DEFAULT_CONFIG_POLL_INTERVAL_MS},
+    context::Context,
+    telemetry,
+};
 use clap::Parser;
-use log::info;
 use serde_json::Value;
+use tracing::info;
 use utoipa::openapi::{Info, OpenApi, Paths};
 
 #[tokio::main]
@@ -20,17 +27,35 @@ async fn main() -> Result<()> {
 }
 
 async fn start(args: cli::Start) -> Result<()> {
-    let config = Config::from_file(args.config)?;
+    let config = Config::from_file(args.config.clone())?;
 
-    env_logger::init();
+    telemetry::init(&config)?;
 
     info!("Starting: {config:#?}");
 
-    let ctx = Context::init_from_config(config).await?;
+    let config_poll_interval = Duration::from_millis(
+        config
+            .config_poll_interval_ms
+            .unwrap_or(DEFAULT_CONFIG_POLL_INTERVAL_MS),
+    );
 
-    api::start(Arc::new(ctx)).await?;
+    let shutdown_timeout = Duration::from_millis(
+        config
+            .shutdown_timeout_ms
+            .unwrap_or(DEFAULT_SHUTDOWN_TIMEOUT_MS),
+    );
 
-    Ok(())
+    let tls = config.tls.clone();
+
+    let ctx = Arc::new(Context::init_from_config(config, Some(args.config)).await?);
+
+    tokio::spawn(config_watcher::start(ctx.clone(), config_poll_interval));
+
+    let result = api::start(ctx, tls, shutdown_timeout).await;
+
+    telemetry::shutdown();
+
+    result
 }
 
 async fn openapi(args: cli::Openapi) -> Result<()> {
@@ -0,0 +1,168 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex, RwLock},
+    time::Duration,
+};
+
+use crate::{auth::RouteVisibility, config::ProxyConfig, crp::Crp};
+
+/// Number of recent route lookups kept for latency hints
+const LATENCY_WINDOW: usize = 100;
+
+/// A CRP along with router-side settings and route lookup statistics
+pub struct Provider {
+    pub crp: Arc<dyn Crp + Send + Sync>,
+    pub settings: ProviderSettings,
+    stats: Mutex<ProviderStats>,
+}
+
+/// Router-side settings for a provider
+#[derive(Debug, Clone)]
+pub struct ProviderSettings {
+    /// Timeout for route lookups against the provider
+    pub timeout: Duration,
+    /// Whether the router should report itself as not ready while the provider is unhealthy
+    pub critical: bool,
+    /// Region the provider serves content from
+    pub region: Option<String>,
+    /// Proxy the provider's outbound HTTP requests go through
+    pub proxy: Option<ProxyConfig>,
+    /// Who the provider's routes are returned to
+    pub visibility: RouteVisibility,
+    /// Routes from providers with a higher priority are returned first
+    pub priority: i32,
+}
+
+#[derive(Default, Clone)]
+struct ProviderStats {
+    latencies: VecDeque<Duration>,
+    last_failure: Option<i64>,
+}
+
+impl Provider {
+    pub fn new(crp: Arc<dyn Crp + Send + Sync>, settings: ProviderSettings) -> Self {
+        Self {
+            crp,
+            settings,
+            stats: Mutex::new(ProviderStats::default()),
+        }
+    }
+
+    /// The same provider with other settings, keeping its route lookup statistics
+    pub fn with_settings(&self, settings: ProviderSettings) -> Self {
+        let stats = self
+            .stats
+            .lock()
+            .expect("provider stats lock poisoned")
+            .clone();
+
+        Self {
+            crp: self.crp.clone(),
+            settings,
+            stats: Mutex::new(stats),
+        }
+    }
+
+    /// Record the latency of a successful route lookup
+    pub fn record_success(&self, latency: Duration) {
+        let mut stats = self.stats.lock().expect("provider stats lock poisoned");
+
+        if stats.latencies.len() == LATENCY_WINDOW {
+            stats.latencies.pop_front();
+        }
+        stats.latencies.push_back(latency);
+    }
+
+    /// Record a failed or timed out route lookup
+    pub fn record_failure(&self) {
+        let mut stats = self.stats.lock().expect("provider stats lock poisoned");
+
+        stats.last_failure = Some(chrono::Utc::now().timestamp());
+    }
+
+    /// Median latency of recent successful route lookups
+    pub fn median_latency(&self) -> Option<Duration> {
+        let stats = self.stats.lock().expect("provider stats lock poisoned");
+
+        let mut latencies = stats.latencies.iter().copied().collect::<Vec<_>>();
+        latencies.sort();
+
+        latencies.get(latencies.len() / 2).copied()
+    }
+
+    /// Unix timestamp of the last failed route lookup
+    pub fn last_failure(&self) -> Option<i64> {
+        let stats = self.stats.lock().expect("provider stats lock poisoned");
+
+        stats.last_failure
+    }
+}
+
+/// Providers by ID, which can be added and removed while the router is running
+#[derive(Default)]
+pub struct ProviderRegistry {
+    providers: RwLock<HashMap<String, Arc<Provider>>>,
+}
+
+impl ProviderRegistry {
+    pub fn new(providers: HashMap<String, Provider>) -> Self {
+        let providers = providers
+            .into_iter()
+            .map(|(id, provider)| (id, Arc::new(provider)))
+            .collect();
+
+        Self {
+            providers: RwLock::new(providers),
+        }
+    }
+
+    /// Providers at the time of the call, unaffected by later adds and removes
+    pub fn snapshot(&self) -> HashMap<String, Arc<Provider>> {
+        self.providers
+            .read()
+            .expect("provider registry lock poisoned")
+            .clone()
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.providers
+            .read()
+            .expect("provider registry lock poisoned")
+            .contains_key(id)
+    }
+
+    /// Add a provider, returning false without replacing it if one with the ID already exists
+    pub fn insert(&self, id: String, provider: Provider) -> bool {
+        let mut providers = self
+            .providers
+            .write()
+            .expect("provider registry lock poisoned");
+
+        if providers.contains_key(&id) {
+            return false;
+        }
+
+        providers.insert(id, Arc::new(provider));
+
+        true
+    }
+
+    /// Replace all providers at once
+    pub fn replace(&self, providers: HashMap<String, Provider>) {
+        *self
+            .providers
+            .write()
+            .expect("provider registry lock poisoned") = providers
+            .into_iter()
+            .map(|(id, provider)| (id, Arc::new(provider)))
+            .collect();
+    }
+
+    /// Remove a provider, lookups already in flight against it still complete
+    pub fn remove(&self, id: &str) -> Option<Arc<Provider>> {
+        self.providers
+            .write()
+            .expect("provider registry lock poisoned")
+            .remove(id)
+    }
+}
@@ -0,0 +1,221 @@
+use std::{
+    collections::HashMap,
+    net::{IpAddr, Ipv6Addr, SocketAddr},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use anyhow::{bail, Result};
+use api_utils::ErrorCode;
+use axum::{
+    extract::{ConnectInfo, State},
+    http::{header::RETRY_AFTER, HeaderValue, Request, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::{auth::bearer_token, context::Context};
+
+/// Buckets kept before full ones are dropped, full buckets are the same as no bucket
+const PRUNE_THRESHOLD: usize = 10_000;
+
+/// Rate limits on the route lookup endpoints
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RateLimitConfig {
+    /// Limit for each client IP address, for requests without an API key
+    pub per_ip: Option<TokenBucketConfig>,
+    /// Limit for each API key, for requests with one
+    pub per_api_key: Option<TokenBucketConfig>,
+}
+
+/// Token bucket refilled at `requests_per_second`, holding up to `burst` requests
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct TokenBucketConfig {
+    pub requests_per_second: f64,
+    /// Requests allowed at once after a quiet period (defaults to `requests_per_second`, at
+    /// least 1)
+    pub burst: Option<u32>,
+}
+
+/// What a request's rate limit is counted against
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum RateLimitKey {
+    /// Client IPv4 address, or the /64 prefix of its IPv6 address, since a single client usually
+    /// has a whole /64 to pick addresses from
+    Ip(IpAddr),
+    /// Name of the API key
+    ApiKey(String),
+}
+
+/// Token buckets of the clients that have made requests recently
+#[derive(Default)]
+pub struct RateLimiter {
+    buckets: Mutex<HashMap<RateLimitKey, TokenBucket>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct TokenBucket {
+    tokens: f64,
+    updated: Instant,
+    /// Config the bucket was last taken from with, which per IP and per API key buckets differ in
+    config: TokenBucketConfig,
+}
+
+impl RateLimitConfig {
+    /// Check the limits can be enforced, the config is rejected on load otherwise
+    pub fn validate(&self) -> Result<()> {
+        for (name, config) in [("per_ip", &self.per_ip), ("per_api_key", &self.per_api_key)] {
+            let Some(config) = config else {
+                continue;
+            };
+
+            if !config.requests_per_second.is_finite() || config.requests_per_second <= 0.0 {
+                bail!(
+                    "rate_limit.{name}.requests_per_second={} must be a number greater than 0",
+                    config.requests_per_second
+                );
+            }
+
+            if config.burst == Some(0) {
+                bail!("rate_limit.{name}.burst must be at least 1");
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl TokenBucketConfig {
+    fn burst(&self) -> f64 {
+        self.burst
+            .map_or(self.requests_per_second, f64::from)
+            .max(1.0)
+    }
+}
+
+impl RateLimitKey {
+    fn from_ip(ip: IpAddr) -> Self {
+        match ip.to_canonical() {
+            IpAddr::V6(ip) => {
+                let prefix = u128::from(ip) & !(u128::from(u64::MAX));
+                Self::Ip(IpAddr::V6(Ipv6Addr::from(prefix)))
+            }
+            ip => Self::Ip(ip),
+        }
+    }
+}
+
+impl RateLimiter {
+    /// Take a token from the key's bucket, or how long until there's one
+    fn take(&self, key: RateLimitKey, config: &TokenBucketConfig) -> Result<(), Duration> {
+        let now = Instant::now();
+        let burst = config.burst();
+
+        let mut buckets = self.buckets.lock().expect("rate limiter lock poisoned");
+
+        if buckets.len() >= PRUNE_THRESHOLD {
+            buckets.retain(|_, bucket| bucket.refill(now) < bucket.config.burst());
+        }
+
+        let bucket = buckets.entry(key).or_insert(TokenBucket {
+            tokens: burst,
+            updated: now,
+            config: *config,
+        });
+
+        bucket.config = *config;
+        bucket.tokens = bucket.refill(now);
+        bucket.updated = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return Ok(());
+        }
+
+        // a wait too long to represent is capped rather than panicking with the lock held
+        Err(
+            Duration::try_from_secs_f64((1.0 - bucket.tokens) / config.requests_per_second)
+                .unwrap_or(Duration::MAX),
+        )
+    }
+}
+
+impl TokenBucket {
+    /// Tokens in the bucket at `now`
+    fn refill(&self, now: Instant) -> f64 {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+
+        (self.tokens + elapsed * self.config.requests_per_second.max(0.0)).min(self.config.burst())
+    }
+}
+
+/// Middleware rejecting requests over the configured rate limit with 429 Too Many Requests.
+///
+/// Requests with an API key count against the key's limit, others against their IP address's.
+/// Requests with an invalid key are left to the endpoint to reject.
+pub async fn limit<B>(
+    State(ctx): State<Arc<Context>>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let Some(rate_limit) = ctx.settings().rate_limit else {
+        return next.run(request).await;
+    };
+
+    let api_key = ctx
+        .api_keys
+        .authenticate(bearer_token(request.headers()))
+        .ok()
+        .flatten();
+
+    let limited = match api_key {
+        Some(api_key) => rate_limit
+            .per_api_key
+            .map(|config| (RateLimitKey::ApiKey(api_key.name), config)),
+        None => request
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .zip(rate_limit.per_ip)
+            .map(|(ConnectInfo(addr), config)| (RateLimitKey::from_ip(addr.ip()), config)),
+    };
+
+    let Some((key, config)) = limited else {
+        return next.run(request).await;
+    };
+
+    let Err(retry_after) = ctx.rate_limiter.take(key.clone(), &config) else {
+        return next.run(request).await;
+    };
+
+    let client = match key {
+        RateLimitKey::Ip(IpAddr::V6(ip)) => format!("ip={ip}/64"),
+        RateLimitKey::Ip(ip) => format!("ip={ip}"),
+        RateLimitKey::ApiKey(name) => format!("api key={name}"),
+    };
+
+    tracing::debug!(%client, "rate limit exceeded");
+
+    // built directly rather than as an `ApiError`, which is logged as an error, since rejecting
+    // requests over the limit is expected
+    let mut response = (
+        StatusCode::TOO_MANY_REQUESTS,
+        Json(json!({
+            "code": ErrorCode::RateLimited,
+            "error": format!("rate limit exceeded for {client}"),
+        })),
+    )
+        .into_response();
+
+    // whole seconds, rounded up so a client retrying after it gets a token
+    let retry_after_secs = retry_after
+        .as_secs()
+        .saturating_add(u64::from(retry_after.subsec_nanos() > 0));
+    response
+        .headers_mut()
+        .insert(RETRY_AFTER, HeaderValue::from(retry_after_secs));
+
+    response
+}
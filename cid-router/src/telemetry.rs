@@ -0,0 +1,73 @@
+use anyhow::Result;
+use axum::{http::Request, middleware::Next, response::Response};
+use opentelemetry::KeyValue;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::{runtime, trace, Resource};
+use tracing::Instrument;
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter, Layer};
+
+use crate::config::Config;
+
+/// Spans exported over OTLP, whatever `RUST_LOG` filters the logs to
+const OTLP_FILTER: &str = "cid_router=info";
+
+/// Log to stderr, filtered by `RUST_LOG` (defaults to errors only), and export spans to the
+/// configured OTLP endpoint, if any. Records from crates that use `log` are logged too.
+pub fn init(config: &Config) -> Result<()> {
+    let log_filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("error"));
+
+    let otlp_layer = match &config.otlp_endpoint {
+        Some(otlp_endpoint) => {
+            let tracer = opentelemetry_otlp::new_pipeline()
+                .tracing()
+                .with_exporter(
+                    opentelemetry_otlp::new_exporter()
+                        .tonic()
+                        .with_endpoint(otlp_endpoint),
+                )
+                .with_trace_config(trace::config().with_resource(Resource::new(vec![
+                    KeyValue::new("service.name", "cid-router"),
+                ])))
+                .install_batch(runtime::Tokio)?;
+
+            Some(
+                tracing_opentelemetry::layer()
+                    .with_tracer(tracer)
+                    .with_filter(EnvFilter::new(OTLP_FILTER)),
+            )
+        }
+        None => None,
+    };
+
+    tracing_subscriber::registry()
+        .with(tracing_subscriber::fmt::layer().with_filter(log_filter))
+        .with(otlp_layer)
+        .try_init()?;
+
+    Ok(())
+}
+
+/// Export spans that haven't been exported yet
+pub fn shutdown() {
+    opentelemetry::global::shutdown_tracer_provider();
+}
+
+/// Middleware running each request in a span, which the spans of its provider lookups are in
+pub async fn trace_request<B>(request: Request<B>, next: Next<B>) -> Response {
+    let span = tracing::info_span!(
+        "http_request",
+        method = %request.method(),
+        path = request.uri().path(),
+        status = tracing::field::Empty,
+    );
+
+    async move {
+        let response = next.run(request).await;
+
+        tracing::Span::current().record("status", response.status().as_u16());
+
+        response
+    }
+    .instrument(span)
+    .await
+}
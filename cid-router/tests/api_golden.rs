@@ -0,0 +1,832 @@
+//! Golden file tests for API response shapes.
+//!
+//! Each test requests an endpoint from a router backed by mock providers and compares the status
+//! and JSON body against `tests/golden/<name>.json`. Values that vary between runs (uptime,
+//! latencies, timestamps, callstacks, correlation IDs) are masked before comparing.
+//!
+//! Run with `UPDATE_GOLDEN=1` to rewrite the golden files after an intentional change.
+
+use std::{
+    collections::HashMap,
+    net::Ipv4Addr,
+    path::PathBuf,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use anyhow::{bail, Result};
+use async_trait::async_trait;
+use axum::{body::Body, http::Request};
+use cid::Cid;
+use cid_filter::{CidFilter, CodeFilter};
+use cid_router::{
+    api,
+    auth::{hash_key, ApiKeyScope, ApiKeys, RouteVisibility},
+    config::{ApiKeyConfig, ProviderConfig, RouteSelection},
+    config_history::ConfigHistory,
+    context::{Context, RouterSettings},
+    crp::{external::ExternalCrpConfig, Crp, CrpError, UpstreamHost},
+    provider::{Provider, ProviderRegistry, ProviderSettings},
+    rate_limit::{RateLimitConfig, RateLimiter, TokenBucketConfig},
+};
+use routes::{IntoRoute, IrohRouteMethod, Route, UrlRouteMethod};
+use serde_json::{json, Value};
+use tower::ServiceExt;
+
+const VOLATILE_KEYS: &[&str] = &[
+    "uptime",
+    "latency_ms",
+    "median_latency_ms",
+    "last_failure",
+    "callstack",
+    "correlation_id",
+];
+
+const API_KEY: &str = "test-api-key";
+
+/// Serves url routes for blake3 CIDs, one is configured in each of the "eu" and "us" regions.
+/// Resolves `http://<its host>/<cid>` URLs to the CID.
+struct MockCrp {
+    config: ProviderConfig,
+}
+
+/// Eligible for sha256 CIDs but always fails, as if down or rate limiting the router
+struct FailingCrp {
+    config: ProviderConfig,
+    rate_limited: bool,
+}
+
+#[async_trait]
+impl Crp for MockCrp {
+    async fn init(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn cid_filter(&self) -> CidFilter {
+        CidFilter::MultihashCodeFilter(CodeFilter::Eq(0x1e))
+    }
+
+    async fn get_routes_for_cid(&self, cid: &Cid) -> Result<Vec<Route>> {
+        Ok(vec![UrlRouteMethod {
+            url: format!("https://example.com/{cid}"),
+        }
+        .into_route(
+            Some(self.provider_id()),
+            Some(json!({ "size": 0 })),
+        )?])
+    }
+
+    async fn resolve_url(&self, url: &str) -> Result<Option<(Cid, Route)>> {
+        let ProviderConfig::External(ExternalCrpConfig { url: crp_url }) = &self.config else {
+            bail!("mock crp has a non-external config");
+        };
+        let base_url = crp_url.trim_end_matches("/v1/crp");
+
+        let Some(cid) = url
+            .strip_prefix(base_url)
+            .and_then(|path| path.strip_prefix('/'))
+        else {
+            return Ok(None);
+        };
+        let cid = Cid::try_from(cid)?;
+
+        let route = UrlRouteMethod {
+            url: url.to_owned(),
+        }
+        .into_route(Some(self.provider_id()), None)?;
+
+        Ok(Some((cid, route)))
+    }
+
+    async fn check_health(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn provider_config(&self) -> Value {
+        serde_json::to_value(&self.config).unwrap()
+    }
+}
+
+#[async_trait]
+impl Crp for FailingCrp {
+    async fn init(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn cid_filter(&self) -> CidFilter {
+        CidFilter::MultihashCodeFilter(CodeFilter::Eq(0x12))
+    }
+
+    async fn get_routes_for_cid(&self, _cid: &Cid) -> Result<Vec<Route>> {
+        if self.rate_limited {
+            return Err(CrpError::RateLimited("provider rate limited".to_owned()).into());
+        }
+
+        bail!("provider unavailable")
+    }
+
+    async fn check_health(&self) -> Result<()> {
+        bail!("provider unavailable")
+    }
+
+    fn upstream_hosts(&self) -> Result<Vec<UpstreamHost>> {
+        bail!("provider unavailable")
+    }
+
+    fn provider_config(&self) -> Value {
+        serde_json::to_value(&self.config).unwrap()
+    }
+}
+
+/// Peer router serving a url route for any CID, only asked when no other provider has routes
+struct MockPeerCrp {
+    config: ProviderConfig,
+}
+
+#[async_trait]
+impl Crp for MockPeerCrp {
+    async fn init(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn cid_filter(&self) -> CidFilter {
+        CidFilter::None
+    }
+
+    fn is_fallback(&self) -> bool {
+        true
+    }
+
+    async fn get_routes_for_cid(&self, cid: &Cid) -> Result<Vec<Route>> {
+        Ok(vec![UrlRouteMethod {
+            url: format!("https://peer.example.com/{cid}"),
+        }
+        .into_route(Some(self.provider_id()), None)?])
+    }
+
+    async fn check_health(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn provider_config(&self) -> Value {
+        serde_json::to_value(&self.config).unwrap()
+    }
+}
+
+/// Iroh node with every blake3 blob, its routes carry a placeholder ticket naming the CID
+struct MockIrohCrp {
+    config: ProviderConfig,
+}
+
+#[async_trait]
+impl Crp for MockIrohCrp {
+    async fn init(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn cid_filter(&self) -> CidFilter {
+        CidFilter::MultihashCodeFilter(CodeFilter::Eq(0x1e))
+    }
+
+    async fn get_routes_for_cid(&self, cid: &Cid) -> Result<Vec<Route>> {
+        Ok(vec![IrohRouteMethod {
+            ticket: format!("blob-ticket-for-{cid}"),
+        }
+        .into_route(Some(self.provider_id()), None)?])
+    }
+
+    async fn check_health(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn provider_config(&self) -> Value {
+        serde_json::to_value(&self.config).unwrap()
+    }
+}
+
+fn external_config(url: &str) -> ProviderConfig {
+    ProviderConfig::External(ExternalCrpConfig {
+        url: url.to_owned(),
+    })
+}
+
+fn context(failing_provider_is_critical: bool) -> Arc<Context> {
+    let crps: Vec<(Arc<dyn Crp + Send + Sync>, bool, Option<&str>)> = vec![
+        (
+            Arc::new(MockCrp {
+                config: external_config("http://mock-eu.invalid/v1/crp"),
+            }),
+            false,
+            Some("eu"),
+        ),
+        (
+            Arc::new(MockCrp {
+                config: external_config("http://mock-us.invalid/v1/crp"),
+            }),
+            false,
+            Some("us"),
+        ),
+        (
+            Arc::new(FailingCrp {
+                config: external_config("http://failing.invalid/v1/crp"),
+                rate_limited: false,
+            }),
+            failing_provider_is_critical,
+            None,
+        ),
+    ];
+
+    let providers = crps
+        .into_iter()
+        .map(|(crp, critical, region)| {
+            let settings = ProviderSettings {
+                timeout: Duration::from_secs(5),
+                critical,
+                region: region.map(str::to_owned),
+                proxy: None,
+                visibility: RouteVisibility::Public,
+                priority: 0,
+            };
+
+            (crp.provider_id(), Provider::new(crp, settings))
+        })
+        .collect::<HashMap<_, _>>();
+
+    let ctx = Arc::new(Context {
+        start_time: chrono::Utc::now().timestamp(),
+        bind_addr: Ipv4Addr::LOCALHOST.into(),
+        port: 0,
+        config_path: None,
+        settings: RwLock::new(RouterSettings {
+            // routes from providers in the same region come first, which keeps their order stable
+            region: Some("us".to_owned()),
+            route_selection: RouteSelection::Region,
+            default_provider_timeout: Duration::from_secs(5),
+            default_proxy: None,
+            egress_file: None,
+            rate_limit: None,
+        }),
+        providers: ProviderRegistry::new(providers),
+        config_history: ConfigHistory::default(),
+        api_keys: ApiKeys::default(),
+        rate_limiter: RateLimiter::default(),
+    });
+
+    // admin endpoints are open until a test adds an API key
+    ctx.api_keys.set_open_without_keys(true);
+
+    ctx
+}
+
+/// Context with an API key with the scopes, which turns on API key auth for admin endpoints
+fn context_with_api_key(scopes: Vec<ApiKeyScope>) -> Arc<Context> {
+    let ctx = context(false);
+
+    ctx.api_keys
+        .insert(ApiKeyConfig {
+            name: "test".to_owned(),
+            key_sha256: hash_key(API_KEY),
+            scopes,
+        })
+        .unwrap();
+
+    ctx
+}
+
+/// Context where the "eu" provider's routes are restricted to API keys with the restricted_routes
+/// scope, and there's a key with it
+fn context_with_restricted_provider() -> Arc<Context> {
+    let ctx = context_with_api_key(vec![ApiKeyScope::RestrictedRoutes]);
+
+    let id = external_config("http://mock-eu.invalid/v1/crp").provider_id();
+    let provider = ctx.providers.remove(&id).unwrap();
+
+    let settings = ProviderSettings {
+        visibility: RouteVisibility::Restricted,
+        ..provider.settings.clone()
+    };
+    ctx.providers
+        .insert(id, Provider::new(provider.crp.clone(), settings));
+
+    ctx
+}
+
+/// Context where the "eu" provider has a higher priority than the others
+fn context_with_priority() -> Arc<Context> {
+    let ctx = context(false);
+
+    let id = external_config("http://mock-eu.invalid/v1/crp").provider_id();
+    let provider = ctx.providers.remove(&id).unwrap();
+
+    let settings = ProviderSettings {
+        priority: 1,
+        ..provider.settings.clone()
+    };
+    ctx.providers
+        .insert(id, Provider::new(provider.crp.clone(), settings));
+
+    ctx
+}
+
+/// Context where the failing provider fails by rate limiting the router
+fn context_with_rate_limited_provider() -> Arc<Context> {
+    let ctx = context(false);
+
+    let crp = FailingCrp {
+        config: external_config("http://failing.invalid/v1/crp"),
+        rate_limited: true,
+    };
+    let provider = ctx.providers.remove(&crp.provider_id()).unwrap();
+
+    ctx.providers.insert(
+        crp.provider_id(),
+        Provider::new(Arc::new(crp), provider.settings.clone()),
+    );
+
+    ctx
+}
+
+/// Context with a peer router provider alongside the mock providers
+fn context_with_peer() -> Arc<Context> {
+    let ctx = context(false);
+
+    let crp = Arc::new(MockPeerCrp {
+        config: external_config("http://peer.invalid"),
+    });
+    let settings = ProviderSettings {
+        timeout: Duration::from_secs(5),
+        critical: false,
+        region: None,
+        proxy: None,
+        visibility: RouteVisibility::Public,
+        priority: 0,
+    };
+    ctx.providers
+        .insert(crp.provider_id(), Provider::new(crp, settings));
+
+    ctx
+}
+
+/// Context with an iroh provider alongside the mock providers
+fn context_with_iroh() -> Arc<Context> {
+    let ctx = context(false);
+
+    let crp = Arc::new(MockIrohCrp {
+        config: external_config("http://iroh.invalid/v1/crp"),
+    });
+    let settings = ProviderSettings {
+        timeout: Duration::from_secs(5),
+        critical: false,
+        region: None,
+        proxy: None,
+        visibility: RouteVisibility::Public,
+        priority: 0,
+    };
+    ctx.providers
+        .insert(crp.provider_id(), Provider::new(crp, settings));
+
+    ctx
+}
+
+fn mask_volatile(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                if VOLATILE_KEYS.contains(&key.as_str()) {
+                    *value = json!("<volatile>");
+                } else {
+                    mask_volatile(value);
+                }
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(mask_volatile),
+        _ => {}
+    }
+}
+
+async fn assert_golden(name: &str, ctx: Arc<Context>, uri: &str) {
+    assert_golden_request(name, ctx, Request::get(uri).body(Body::empty()).unwrap()).await;
+}
+
+async fn assert_golden_request(name: &str, ctx: Arc<Context>, request: Request<Body>) {
+    let uri = request.uri().clone();
+
+    let response = api::router(ctx).oneshot(request).await.unwrap();
+
+    let status = response.status().as_u16();
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    let mut body = serde_json::from_slice::<Value>(&body).unwrap();
+    mask_volatile(&mut body);
+
+    let actual = json!({ "status": status, "body": body });
+
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(format!("{name}.json"));
+
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        let mut golden = serde_json::to_string_pretty(&actual).unwrap();
+        golden.push('\n');
+        std::fs::write(&path, golden).unwrap();
+        return;
+    }
+
+    let expected = serde_json::from_str::<Value>(
+        &std::fs::read_to_string(&path)
+            .unwrap_or_else(|e| panic!("failed to read golden file {}: {e}", path.display())),
+    )
+    .unwrap();
+
+    assert_eq!(
+        expected,
+        actual,
+        "response for {uri} doesn't match {}, rerun with UPDATE_GOLDEN=1 if the change is intended",
+        path.display()
+    );
+}
+
+#[tokio::test]
+async fn routes() {
+    assert_golden(
+        "routes",
+        context(false),
+        "/v1/routes/bafkr4ihkr4ld3m4gqkjf4reryxsy2s5tkbxprqkow6fin2iiyvreuzzab4",
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn routes_region() {
+    assert_golden(
+        "routes_region",
+        context(false),
+        "/v1/routes/bafkr4ihkr4ld3m4gqkjf4reryxsy2s5tkbxprqkow6fin2iiyvreuzzab4?region=eu",
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn routes_priority() {
+    assert_golden(
+        "routes_priority",
+        context_with_priority(),
+        "/v1/routes/bafkr4ihkr4ld3m4gqkjf4reryxsy2s5tkbxprqkow6fin2iiyvreuzzab4",
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn routes_restricted_anonymous() {
+    assert_golden(
+        "routes_restricted_anonymous",
+        context_with_restricted_provider(),
+        "/v1/routes/bafkr4ihkr4ld3m4gqkjf4reryxsy2s5tkbxprqkow6fin2iiyvreuzzab4",
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn routes_restricted_with_api_key() {
+    assert_golden_request(
+        "routes_restricted_with_api_key",
+        context_with_restricted_provider(),
+        Request::get("/v1/routes/bafkr4ihkr4ld3m4gqkjf4reryxsy2s5tkbxprqkow6fin2iiyvreuzzab4")
+            .header("Authorization", format!("Bearer {API_KEY}"))
+            .body(Body::empty())
+            .unwrap(),
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn routes_rate_limited() {
+    let ctx = context_with_api_key(vec![]);
+
+    ctx.settings.write().unwrap().rate_limit = Some(RateLimitConfig {
+        per_ip: None,
+        per_api_key: Some(TokenBucketConfig {
+            requests_per_second: 0.001,
+            burst: Some(1),
+        }),
+    });
+
+    let request = || {
+        Request::get("/v1/routes/bafkr4ihkr4ld3m4gqkjf4reryxsy2s5tkbxprqkow6fin2iiyvreuzzab4")
+            .header("Authorization", format!("Bearer {API_KEY}"))
+            .body(Body::empty())
+            .unwrap()
+    };
+
+    let response = api::router(ctx.clone()).oneshot(request()).await.unwrap();
+    assert_eq!(response.status(), 200);
+
+    assert_golden_request("routes_rate_limited", ctx, request()).await;
+}
+
+#[tokio::test]
+async fn routes_failing_provider() {
+    assert_golden(
+        "routes_failing_provider",
+        context(false),
+        "/v1/routes/bafkreihdwdcefgh4dqkjv67uzcmw7ojee6xedzdetojuzjevtenxquvyku",
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn routes_provider_rate_limited() {
+    assert_golden(
+        "routes_provider_rate_limited",
+        context_with_rate_limited_provider(),
+        "/v1/routes/bafkreihdwdcefgh4dqkjv67uzcmw7ojee6xedzdetojuzjevtenxquvyku",
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn routes_peer_not_needed() {
+    assert_golden(
+        "routes_peer_not_needed",
+        context_with_peer(),
+        "/v1/routes/bafkr4ihkr4ld3m4gqkjf4reryxsy2s5tkbxprqkow6fin2iiyvreuzzab4",
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn routes_peer_fallback() {
+    assert_golden(
+        "routes_peer_fallback",
+        context_with_peer(),
+        "/v1/routes/bafkreihdwdcefgh4dqkjv67uzcmw7ojee6xedzdetojuzjevtenxquvyku",
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn routes_peer_request() {
+    assert_golden_request(
+        "routes_peer_request",
+        context_with_peer(),
+        Request::get("/v1/routes/bafkreihdwdcefgh4dqkjv67uzcmw7ojee6xedzdetojuzjevtenxquvyku")
+            .header("x-cid-router-peer", "1")
+            .body(Body::empty())
+            .unwrap(),
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn routes_invalid_cid() {
+    assert_golden("routes_invalid_cid", context(false), "/v1/routes/not-a-cid").await;
+}
+
+#[tokio::test]
+async fn routing_providers() {
+    assert_golden(
+        "routing_providers",
+        context(false),
+        "/routing/v1/providers/bafkr4ihkr4ld3m4gqkjf4reryxsy2s5tkbxprqkow6fin2iiyvreuzzab4",
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn routing_providers_not_found() {
+    assert_golden(
+        "routing_providers_not_found",
+        context(false),
+        "/routing/v1/providers/bafkqaaa",
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn tickets() {
+    assert_golden(
+        "tickets",
+        context_with_iroh(),
+        "/v1/tickets/bafkr4ihkr4ld3m4gqkjf4reryxsy2s5tkbxprqkow6fin2iiyvreuzzab4",
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn tickets_not_found() {
+    assert_golden(
+        "tickets_not_found",
+        context(false),
+        "/v1/tickets/bafkr4ihkr4ld3m4gqkjf4reryxsy2s5tkbxprqkow6fin2iiyvreuzzab4",
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn tickets_not_blake3() {
+    assert_golden(
+        "tickets_not_blake3",
+        context_with_iroh(),
+        "/v1/tickets/bafkreihdwdcefgh4dqkjv67uzcmw7ojee6xedzdetojuzjevtenxquvyku",
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn resolve() {
+    assert_golden_request(
+        "resolve",
+        context(false),
+        Request::post("/v1/resolve")
+            .header("Content-Type", "application/json")
+            .body(Body::from(
+                json!({
+                    "url": "http://mock-eu.invalid/bafkr4ihkr4ld3m4gqkjf4reryxsy2s5tkbxprqkow6fin2iiyvreuzzab4"
+                })
+                .to_string(),
+            ))
+            .unwrap(),
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn resolve_not_found() {
+    assert_golden_request(
+        "resolve_not_found",
+        context(false),
+        Request::post("/v1/resolve")
+            .header("Content-Type", "application/json")
+            .body(Body::from(
+                json!({ "url": "https://unknown.invalid/file.txt" }).to_string(),
+            ))
+            .unwrap(),
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn providers() {
+    assert_golden("providers", context(false), "/v1/providers").await;
+}
+
+#[tokio::test]
+async fn providers_restricted_anonymous() {
+    assert_golden(
+        "providers_restricted_anonymous",
+        context_with_restricted_provider(),
+        "/v1/providers",
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn delete_provider() {
+    let ctx = context(false);
+
+    let id = FailingCrp {
+        config: external_config("http://failing.invalid/v1/crp"),
+        rate_limited: false,
+    }
+    .provider_id();
+
+    assert_golden_request(
+        "delete_provider",
+        ctx.clone(),
+        Request::delete(format!("/v1/providers/{id}"))
+            .body(Body::empty())
+            .unwrap(),
+    )
+    .await;
+
+    assert!(!ctx.providers.contains(&id));
+}
+
+#[tokio::test]
+async fn delete_provider_not_found() {
+    assert_golden_request(
+        "delete_provider_not_found",
+        context(false),
+        Request::delete("/v1/providers/not-a-provider")
+            .body(Body::empty())
+            .unwrap(),
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn config_history() {
+    assert_golden("config_history", context(false), "/v1/admin/config/history").await;
+}
+
+#[tokio::test]
+async fn config_rollback_not_found() {
+    assert_golden_request(
+        "config_rollback_not_found",
+        context(false),
+        Request::post("/v1/admin/config/rollback/1")
+            .body(Body::empty())
+            .unwrap(),
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn api_keys() {
+    assert_golden_request(
+        "api_keys",
+        context_with_api_key(vec![ApiKeyScope::ApiKeys]),
+        Request::get("/v1/admin/api-keys")
+            .header("Authorization", format!("Bearer {API_KEY}"))
+            .body(Body::empty())
+            .unwrap(),
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn reload_unauthorized() {
+    assert_golden_request(
+        "reload_unauthorized",
+        context_with_api_key(vec![ApiKeyScope::Config]),
+        Request::post("/v1/admin/reload")
+            .header("Authorization", "Bearer not-the-key")
+            .body(Body::empty())
+            .unwrap(),
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn reload_forbidden() {
+    assert_golden_request(
+        "reload_forbidden",
+        context_with_api_key(vec![ApiKeyScope::Providers]),
+        Request::post("/v1/admin/reload")
+            .header("Authorization", format!("Bearer {API_KEY}"))
+            .body(Body::empty())
+            .unwrap(),
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn reload_without_api_keys() {
+    let ctx = context(false);
+    ctx.api_keys.set_open_without_keys(false);
+
+    assert_golden_request(
+        "reload_without_api_keys",
+        ctx,
+        Request::post("/v1/admin/reload")
+            .body(Body::empty())
+            .unwrap(),
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn reload_without_config_file() {
+    assert_golden_request(
+        "reload_without_config_file",
+        context(false),
+        Request::post("/v1/admin/reload")
+            .body(Body::empty())
+            .unwrap(),
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn status() {
+    assert_golden("status", context(false), "/v1/status").await;
+}
+
+#[tokio::test]
+async fn reachability() {
+    assert_golden(
+        "reachability",
+        context(false),
+        "/v1/diagnostics/reachability",
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn egress() {
+    assert_golden("egress", context(false), "/v1/diagnostics/egress").await;
+}
+
+#[tokio::test]
+async fn healthz() {
+    assert_golden("healthz", context(false), "/healthz").await;
+}
+
+#[tokio::test]
+async fn readyz() {
+    assert_golden("readyz", context(false), "/readyz").await;
+}
+
+#[tokio::test]
+async fn readyz_critical_provider_down() {
+    assert_golden("readyz_critical_provider_down", context(true), "/readyz").await;
+}
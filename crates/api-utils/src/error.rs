@@ -1,9 +1,27 @@
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
 use axum::{
-    http::StatusCode,
+    http::{HeaderValue, StatusCode},
     response::{IntoResponse, Response},
 };
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use utoipa::ToSchema;
+
+/// Header carrying the correlation ID of an error response
+pub const CORRELATION_ID_HEADER: &str = "x-correlation-id";
+
+// Internal callstacks are only sent to clients in debug builds unless configured otherwise
+static EXPOSE_CALLSTACKS: AtomicBool = AtomicBool::new(cfg!(debug_assertions));
+
+/// Set whether internal callstacks are included in error responses.
+/// They're always logged server-side along with the response's correlation ID.
+pub fn set_expose_callstacks(expose: bool) {
+    EXPOSE_CALLSTACKS.store(expose, Ordering::Relaxed);
+}
 
 #[derive(Debug)]
 pub struct ApiError {
@@ -11,13 +29,63 @@ pub struct ApiError {
     body: ApiErrorBody,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
 pub struct ApiErrorBody {
+    code: ErrorCode,
     error: String,
+    /// ID to quote when reporting the error, matching the server-side log entry
+    #[serde(skip_serializing_if = "Option::is_none")]
+    correlation_id: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Object>)]
     callstack: Option<Callstack>,
 }
 
+/// Machine-readable error code, stable for clients to branch on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    /// The request is malformed
+    BadRequest,
+    /// A CID in the request couldn't be parsed
+    CidInvalid,
+    /// The request has no valid API key
+    Unauthorized,
+    /// The request's API key doesn't have the scope the endpoint needs
+    Forbidden,
+    /// The requested resource doesn't exist
+    NotFound,
+    /// The client made too many requests, retry after the `Retry-After` header's seconds
+    RateLimited,
+    /// An upstream provider failed or couldn't be reached
+    ProviderUnavailable,
+    /// An upstream provider rejected the service's credentials
+    ProviderUnauthorized,
+    /// An upstream provider is rate limiting the service, retrying later may succeed
+    ProviderRateLimited,
+    /// An upstream provider responded with something the service couldn't use
+    ProviderInvalidResponse,
+    /// An unexpected error
+    Internal,
+}
+
+impl ErrorCode {
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            Self::BadRequest | Self::CidInvalid => StatusCode::BAD_REQUEST,
+            Self::Unauthorized => StatusCode::UNAUTHORIZED,
+            Self::Forbidden => StatusCode::FORBIDDEN,
+            Self::NotFound => StatusCode::NOT_FOUND,
+            Self::RateLimited => StatusCode::TOO_MANY_REQUESTS,
+            Self::ProviderUnavailable
+            | Self::ProviderUnauthorized
+            | Self::ProviderInvalidResponse => StatusCode::BAD_GATEWAY,
+            Self::ProviderRateLimited => StatusCode::SERVICE_UNAVAILABLE,
+            Self::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
 #[derive(Deserialize, Serialize)]
 #[serde(rename_all = "snake_case")]
 pub enum Callstack {
@@ -30,22 +98,29 @@ pub enum Callstack {
 }
 
 impl ApiError {
-    pub fn new(status_code: StatusCode, error: impl Into<String>) -> Self {
+    pub fn new(code: ErrorCode, error: impl Into<String>) -> Self {
+        let status_code = code.status_code();
         let error = error.into();
         let callstack = None;
 
         Self {
             status_code,
-            body: ApiErrorBody { error, callstack },
+            body: ApiErrorBody {
+                code,
+                error,
+                correlation_id: None,
+                callstack,
+            },
         }
     }
 
     pub fn new_with_external_error(
-        status_code: StatusCode,
+        code: ErrorCode,
         error: impl Into<String>,
         url: impl Into<String>,
         external_error: Option<Value>,
     ) -> Self {
+        let status_code = code.status_code();
         let error = error.into();
         let url = url.into();
         let callstack = Some(Callstack::External {
@@ -55,7 +130,12 @@ impl ApiError {
 
         Self {
             status_code,
-            body: ApiErrorBody { error, callstack },
+            body: ApiErrorBody {
+                code,
+                error,
+                correlation_id: None,
+                callstack,
+            },
         }
     }
 }
@@ -66,13 +146,19 @@ where
 {
     fn from(err: E) -> Self {
         let err = err.into();
-        let status_code = StatusCode::INTERNAL_SERVER_ERROR;
+        let code = ErrorCode::Internal;
+        let status_code = code.status_code();
         let error = err.to_string();
         let callstack = Some(Callstack::Internal(err.backtrace().to_string()));
 
         Self {
             status_code,
-            body: ApiErrorBody { error, callstack },
+            body: ApiErrorBody {
+                code,
+                error,
+                correlation_id: None,
+                callstack,
+            },
         }
     }
 }
@@ -90,14 +176,51 @@ impl std::fmt::Debug for Callstack {
 
 impl IntoResponse for ApiError {
     fn into_response(self) -> Response {
-        log::error!("API error: {:#?}", self);
+        let correlation_id = new_correlation_id();
 
-        let Self { status_code, body } = self;
+        log::error!("API error (correlation_id={correlation_id}): {:#?}", self);
 
-        (
+        let Self {
+            status_code,
+            mut body,
+        } = self;
+
+        if matches!(body.callstack, Some(Callstack::Internal(_)))
+            && !EXPOSE_CALLSTACKS.load(Ordering::Relaxed)
+        {
+            body.callstack = None;
+        }
+
+        body.correlation_id = Some(correlation_id.clone());
+
+        let mut response = (
             status_code,
             serde_json::to_string(&body).unwrap_or("Unrepresentable error.".to_owned()),
         )
-            .into_response()
+            .into_response();
+
+        if let Ok(correlation_id) = HeaderValue::from_str(&correlation_id) {
+            response
+                .headers_mut()
+                .insert(CORRELATION_ID_HEADER, correlation_id);
+        }
+
+        response
+    }
+}
+
+fn new_correlation_id() -> String {
+    let mut bytes = [0u8; 8];
+
+    match getrandom::getrandom(&mut bytes) {
+        Ok(()) => hex::encode(bytes),
+        // fall back to the time, still good enough to find the log entry
+        Err(_) => format!(
+            "{:x}",
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_nanos())
+                .unwrap_or_default()
+        ),
     }
 }
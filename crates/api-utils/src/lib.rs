@@ -1,5 +1,7 @@
 pub mod error;
 pub mod result;
+pub mod shutdown;
+pub mod tls;
 
-pub use error::ApiError;
+pub use error::{ApiError, ApiErrorBody, ErrorCode};
 pub use result::ApiResult;
@@ -0,0 +1,129 @@
+use std::{
+    future::Future,
+    net::SocketAddr,
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use axum::Router;
+
+use crate::tls::TlsConfig;
+
+/// How long in-flight requests are given to finish on shutdown when none is configured, in
+/// milliseconds
+pub const DEFAULT_SHUTDOWN_TIMEOUT_MS: u64 = 30_000;
+
+/// Resolves on SIGINT (ctrl-c) or SIGTERM
+pub async fn signal() {
+    let interrupt = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            log::error!("Failed to listen for SIGINT: {e}");
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                terminate.recv().await;
+            }
+            Err(e) => {
+                log::error!("Failed to listen for SIGTERM: {e}");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = interrupt => {}
+        _ = terminate => {}
+    }
+}
+
+/// Serve the router until `shutdown` resolves, then stop accepting connections and give
+/// in-flight requests up to `timeout` to finish. Requests still running after that, e.g.
+/// long-lived event streams, are dropped.
+pub async fn serve(
+    router: Router,
+    addr: SocketAddr,
+    shutdown: impl Future<Output = ()>,
+    timeout: Duration,
+) -> Result<()> {
+    serve_with_tls(router, addr, None, shutdown, timeout).await
+}
+
+/// [`serve`] over HTTPS if TLS is configured
+pub async fn serve_with_tls(
+    router: Router,
+    addr: SocketAddr,
+    tls: Option<&TlsConfig>,
+    shutdown: impl Future<Output = ()>,
+    timeout: Duration,
+) -> Result<()> {
+    if let Some(tls) = tls {
+        return serve_https(router, addr, tls, shutdown, timeout).await;
+    }
+
+    let (shutting_down_tx, shutting_down_rx) = tokio::sync::oneshot::channel();
+
+    let server = axum::Server::bind(&addr)
+        .serve(router.into_make_service_with_connect_info::<SocketAddr>())
+        .with_graceful_shutdown(async {
+            shutdown.await;
+            log::info!("Shutting down, waiting up to {timeout:?} for in-flight requests");
+            let _ = shutting_down_tx.send(());
+        });
+    tokio::pin!(server);
+
+    tokio::select! {
+        result = &mut server => return Ok(result?),
+        _ = shutting_down_rx => {}
+    }
+
+    match tokio::time::timeout(timeout, server).await {
+        Ok(result) => result?,
+        Err(_) => log::warn!("Dropping requests still in flight after {timeout:?}"),
+    }
+
+    Ok(())
+}
+
+async fn serve_https(
+    router: Router,
+    addr: SocketAddr,
+    tls: &TlsConfig,
+    shutdown: impl Future<Output = ()>,
+    timeout: Duration,
+) -> Result<()> {
+    let handle = axum_server::Handle::new();
+
+    let server = axum_server::bind_rustls(addr, tls.rustls_config()?)
+        .handle(handle.clone())
+        .serve(router.into_make_service_with_connect_info::<SocketAddr>());
+    tokio::pin!(server);
+
+    tokio::select! {
+        result = &mut server => return Ok(result?),
+        _ = shutdown => {}
+    }
+
+    log::info!("Shutting down, waiting up to {timeout:?} for in-flight requests");
+
+    // connections still open after the timeout are closed
+    let shutdown_started = Instant::now();
+    handle.graceful_shutdown(Some(timeout));
+
+    server.await?;
+
+    if shutdown_started.elapsed() >= timeout {
+        log::warn!("Dropped requests still in flight after {timeout:?}");
+    }
+
+    Ok(())
+}
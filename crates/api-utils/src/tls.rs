@@ -0,0 +1,83 @@
+use std::{fs::File, io::BufReader, path::PathBuf, sync::Arc};
+
+use anyhow::{anyhow, bail, Context, Result};
+use axum_server::tls_rustls::RustlsConfig;
+use rustls::{
+    server::AllowAnyAuthenticatedClient, Certificate, PrivateKey, RootCertStore, ServerConfig,
+};
+use rustls_pemfile::Item;
+use serde::{Deserialize, Serialize};
+
+/// Serve HTTPS rather than HTTP
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TlsConfig {
+    /// PEM file with the server's certificate chain, leaf first
+    pub cert_file: PathBuf,
+    /// PEM file with the server's private key, PKCS#8, PKCS#1 (RSA) or SEC1 (EC)
+    pub key_file: PathBuf,
+    /// PEM file with the CA certificates client certificates are verified against, turns on
+    /// mutual TLS so clients without a certificate from one of them are refused
+    pub client_ca_file: Option<PathBuf>,
+}
+
+impl TlsConfig {
+    pub(crate) fn rustls_config(&self) -> Result<RustlsConfig> {
+        let Self {
+            cert_file,
+            key_file,
+            client_ca_file,
+        } = self;
+
+        let certs = read_certs(cert_file)?;
+        let key = read_key(key_file)?;
+
+        let builder = ServerConfig::builder().with_safe_defaults();
+
+        let builder = match client_ca_file {
+            Some(client_ca_file) => {
+                let mut roots = RootCertStore::empty();
+                for cert in read_certs(client_ca_file)? {
+                    roots.add(&cert)?;
+                }
+
+                builder.with_client_cert_verifier(AllowAnyAuthenticatedClient::new(roots).boxed())
+            }
+            None => builder.with_no_client_auth(),
+        };
+
+        let mut config = builder
+            .with_single_cert(certs, key)
+            .context("tls certificate doesn't match its private key")?;
+        config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+
+        Ok(RustlsConfig::from_config(Arc::new(config)))
+    }
+}
+
+fn read_certs(path: &PathBuf) -> Result<Vec<Certificate>> {
+    let file = File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+
+    let certs = rustls_pemfile::certs(&mut BufReader::new(file))?;
+
+    if certs.is_empty() {
+        bail!("no certificates in {}", path.display());
+    }
+
+    Ok(certs.into_iter().map(Certificate).collect())
+}
+
+fn read_key(path: &PathBuf) -> Result<PrivateKey> {
+    let file = File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+    let mut reader = BufReader::new(file);
+
+    while let Some(item) = rustls_pemfile::read_one(&mut reader)? {
+        match item {
+            Item::PKCS8Key(key) | Item::RSAKey(key) | Item::ECKey(key) => {
+                return Ok(PrivateKey(key))
+            }
+            _ => {}
+        }
+    }
+
+    Err(anyhow!("no private key in {}", path.display()))
+}
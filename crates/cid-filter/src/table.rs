@@ -11,4 +11,5 @@ pub mod multicodec {
     pub const DAG_CBOR: u64 = 0x71;
     pub const GIT_RAW: u64 = 0x78;
     pub const BLAKE3_HASHSEQ: u64 = 0x80;
+    pub const JSON_JCS: u64 = 0xb601;
 }
@@ -0,0 +1,209 @@
+use std::fmt;
+
+use cid::Cid;
+use futures::{stream, StreamExt};
+use reqwest::{RequestBuilder, Response, StatusCode};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::json;
+
+/// Route lookups run at once by [`Client::get_routes_batch`]
+const BATCH_CONCURRENCY: usize = 8;
+
+/// Client for the CID Router HTTP API
+#[derive(Debug, Clone)]
+pub struct Client {
+    base_url: String,
+    api_key: Option<String>,
+    http: reqwest::Client,
+}
+
+/// A route as the router returns it, with the router's fingerprint and hints
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Route {
+    /// Deterministic identity of the route, for deduplicating routes across routers
+    pub fingerprint: String,
+    #[serde(flatten)]
+    pub route: routes::Route,
+    pub hints: Option<ResolutionHints>,
+}
+
+/// Hints about the provider a route came from, so clients can choose between routes
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResolutionHints {
+    /// Timeout the router uses for route lookups against the provider, in milliseconds
+    pub timeout_ms: u64,
+    /// Region the provider serves content from
+    pub region: Option<String>,
+    /// Median latency of recent route lookups against the provider, in milliseconds
+    pub median_latency_ms: Option<u64>,
+    /// Unix timestamp of the last failed route lookup against the provider
+    pub last_failure: Option<i64>,
+}
+
+/// CID and route of the content at a URL
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Resolution {
+    pub cid: String,
+    pub route: Route,
+}
+
+#[derive(Deserialize)]
+struct RoutesResponse {
+    routes: Vec<Route>,
+}
+
+/// Error body of the router's API
+#[derive(Deserialize)]
+struct ApiErrorBody {
+    code: String,
+    error: String,
+}
+
+#[derive(Debug)]
+pub enum Error {
+    /// The request couldn't be sent or its response couldn't be read
+    Http(reqwest::Error),
+    /// The router responded with an error
+    Api {
+        status: StatusCode,
+        /// Machine-readable error code, e.g. "NOT_FOUND" or "PROVIDER_UNAVAILABLE"
+        code: String,
+        message: String,
+    },
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Client {
+    /// Client for the router at `base_url`, e.g. "http://localhost:3080"
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self {
+            base_url: base_url.into().trim_end_matches('/').to_owned(),
+            api_key: None,
+            http: reqwest::Client::new(),
+        }
+    }
+
+    /// Send the API key with every request, for restricted routes
+    pub fn with_api_key(self, api_key: impl Into<String>) -> Self {
+        Self {
+            api_key: Some(api_key.into()),
+            ..self
+        }
+    }
+
+    /// Send requests with the HTTP client, e.g. one with timeouts or a proxy configured
+    pub fn with_http_client(self, http: reqwest::Client) -> Self {
+        Self { http, ..self }
+    }
+
+    /// Routes for a CID, the router's selected route first. Routes are preferred from `region`,
+    /// or the router's configured region if it's `None`.
+    pub async fn get_routes(&self, cid: &Cid, region: Option<&str>) -> Result<Vec<Route>> {
+        let mut request = self.get(&format!("/v1/routes/{cid}"));
+
+        if let Some(region) = region {
+            request = request.query(&[("region", region)]);
+        }
+
+        let RoutesResponse { routes } = parse(request.send().await?).await?;
+
+        Ok(routes)
+    }
+
+    /// Routes for each of the CIDs, looked up a few at a time, in the order of the CIDs
+    pub async fn get_routes_batch(
+        &self,
+        cids: &[Cid],
+        region: Option<&str>,
+    ) -> Vec<(Cid, Result<Vec<Route>>)> {
+        stream::iter(cids)
+            .map(|cid| async move { (*cid, self.get_routes(cid, region).await) })
+            .buffered(BATCH_CONCURRENCY)
+            .collect()
+            .await
+    }
+
+    /// CID and route of the content at a URL a provider serves, `None` if no provider does
+    pub async fn resolve(&self, url: &str) -> Result<Option<Resolution>> {
+        let request = self.authorize(
+            self.http
+                .post(format!("{}/v1/resolve", self.base_url))
+                .json(&json!({ "url": url })),
+        );
+
+        match parse(request.send().await?).await {
+            Ok(resolution) => Ok(Some(resolution)),
+            Err(Error::Api {
+                status: StatusCode::NOT_FOUND,
+                ..
+            }) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn get(&self, path: &str) -> RequestBuilder {
+        self.authorize(self.http.get(format!("{}{path}", self.base_url)))
+    }
+
+    fn authorize(&self, request: RequestBuilder) -> RequestBuilder {
+        match &self.api_key {
+            Some(api_key) => request.bearer_auth(api_key),
+            None => request,
+        }
+    }
+}
+
+/// Body of a successful response, or the router's error
+async fn parse<T: DeserializeOwned>(response: Response) -> Result<T> {
+    let status = response.status();
+
+    if status.is_success() {
+        return Ok(response.json().await?);
+    }
+
+    let text = response.text().await?;
+
+    let (code, message) = match serde_json::from_str::<ApiErrorBody>(&text) {
+        Ok(ApiErrorBody { code, error }) => (code, error),
+        // e.g. from a proxy in front of the router
+        Err(_) => (String::new(), text),
+    };
+
+    Err(Error::Api {
+        status,
+        code,
+        message,
+    })
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        Self::Http(e)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Http(e) => write!(f, "{e}"),
+            Self::Api {
+                status,
+                code,
+                message,
+            } => write!(
+                f,
+                "router responded with status {status} code={code}: {message}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Http(e) => Some(e),
+            Self::Api { .. } => None,
+        }
+    }
+}
@@ -0,0 +1,192 @@
+//! Client tests against a router served on a local port, backed by a mock provider.
+#![cfg(not(target_arch = "wasm32"))]
+
+use std::{
+    collections::HashMap,
+    net::{Ipv4Addr, SocketAddr, TcpListener},
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use cid::Cid;
+use cid_filter::{CidFilter, CodeFilter};
+use cid_router::{
+    api,
+    auth::{ApiKeys, RouteVisibility},
+    config::{ProviderConfig, RouteSelection},
+    config_history::ConfigHistory,
+    context::{Context, RouterSettings},
+    crp::{external::ExternalCrpConfig, Crp},
+    provider::{Provider, ProviderRegistry, ProviderSettings},
+    rate_limit::RateLimiter,
+};
+use cid_router_client::{Client, Error};
+use reqwest::StatusCode;
+use routes::{IntoRoute, Route, UrlRouteMethod};
+use serde_json::Value;
+
+const BLAKE3_CID: &str = "bafkr4ihkr4ld3m4gqkjf4reryxsy2s5tkbxprqkow6fin2iiyvreuzzab4";
+const SHA256_CID: &str = "bafkreihdwdcefgh4dqkjv67uzcmw7ojee6xedzdetojuzjevtenxquvyku";
+
+/// Serves url routes for blake3 CIDs and resolves `https://example.com/<cid>` URLs to the CID
+struct MockCrp {
+    config: ProviderConfig,
+}
+
+#[async_trait]
+impl Crp for MockCrp {
+    async fn init(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn cid_filter(&self) -> CidFilter {
+        CidFilter::MultihashCodeFilter(CodeFilter::Eq(0x1e))
+    }
+
+    async fn get_routes_for_cid(&self, cid: &Cid) -> Result<Vec<Route>> {
+        Ok(vec![UrlRouteMethod {
+            url: format!("https://example.com/{cid}"),
+        }
+        .into_route(Some(self.provider_id()), None)?])
+    }
+
+    async fn resolve_url(&self, url: &str) -> Result<Option<(Cid, Route)>> {
+        let Some(cid) = url.strip_prefix("https://example.com/") else {
+            return Ok(None);
+        };
+
+        let route = UrlRouteMethod {
+            url: url.to_owned(),
+        }
+        .into_route(Some(self.provider_id()), None)?;
+
+        Ok(Some((Cid::try_from(cid)?, route)))
+    }
+
+    async fn check_health(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn provider_config(&self) -> Value {
+        serde_json::to_value(&self.config).unwrap()
+    }
+}
+
+/// Serve a router with the mock provider in the "eu" region, returning its URL
+fn serve_router() -> String {
+    let crp: Arc<dyn Crp + Send + Sync> = Arc::new(MockCrp {
+        config: ProviderConfig::External(ExternalCrpConfig {
+            url: "http://mock.invalid/v1/crp".to_owned(),
+        }),
+    });
+    let settings = ProviderSettings {
+        timeout: Duration::from_secs(5),
+        critical: false,
+        region: Some("eu".to_owned()),
+        proxy: None,
+        visibility: RouteVisibility::Public,
+        priority: 0,
+    };
+    let providers = HashMap::from([(crp.provider_id(), Provider::new(crp, settings))]);
+
+    let ctx = Arc::new(Context {
+        start_time: 0,
+        bind_addr: Ipv4Addr::LOCALHOST.into(),
+        port: 0,
+        config_path: None,
+        settings: RwLock::new(RouterSettings {
+            region: None,
+            route_selection: RouteSelection::Region,
+            default_provider_timeout: Duration::from_secs(5),
+            default_proxy: None,
+            egress_file: None,
+            rate_limit: None,
+        }),
+        providers: ProviderRegistry::new(providers),
+        config_history: ConfigHistory::default(),
+        api_keys: ApiKeys::default(),
+        rate_limiter: RateLimiter::default(),
+    });
+
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let server = axum::Server::from_tcp(listener)
+        .unwrap()
+        .serve(api::router(ctx).into_make_service_with_connect_info::<SocketAddr>());
+    tokio::spawn(server);
+
+    format!("http://{addr}")
+}
+
+#[tokio::test]
+async fn get_routes() {
+    let client = Client::new(serve_router());
+    let cid = Cid::try_from(BLAKE3_CID).unwrap();
+
+    let routes = client.get_routes(&cid, Some("eu")).await.unwrap();
+
+    assert_eq!(routes.len(), 1);
+    assert_eq!(routes[0].route.type_, "url");
+    assert_eq!(
+        routes[0].route.method["url"],
+        format!("https://example.com/{cid}")
+    );
+    assert_eq!(
+        routes[0].route.fingerprint(BLAKE3_CID).unwrap(),
+        routes[0].fingerprint
+    );
+    assert_eq!(
+        routes[0].hints.as_ref().unwrap().region.as_deref(),
+        Some("eu")
+    );
+}
+
+#[tokio::test]
+async fn get_routes_batch() {
+    let client = Client::new(serve_router());
+    let cids = [
+        Cid::try_from(BLAKE3_CID).unwrap(),
+        Cid::try_from(SHA256_CID).unwrap(),
+    ];
+
+    let results = client.get_routes_batch(&cids, None).await;
+
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0].0, cids[0]);
+    assert_eq!(results[0].1.as_ref().unwrap().len(), 1);
+    // the mock provider isn't eligible for sha256 CIDs
+    assert_eq!(results[1].0, cids[1]);
+    assert!(results[1].1.as_ref().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn resolve() {
+    let client = Client::new(serve_router());
+
+    let resolution = client
+        .resolve(&format!("https://example.com/{BLAKE3_CID}"))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(resolution.cid, BLAKE3_CID);
+
+    let resolution = client.resolve("https://unknown.invalid/file.txt").await;
+    assert!(matches!(resolution, Ok(None)));
+}
+
+#[tokio::test]
+async fn invalid_api_key() {
+    let client = Client::new(serve_router()).with_api_key("not-a-key");
+    let cid = Cid::try_from(BLAKE3_CID).unwrap();
+
+    match client.get_routes(&cid, None).await {
+        Err(Error::Api { status, code, .. }) => {
+            assert_eq!(status, StatusCode::UNAUTHORIZED);
+            assert_eq!(code, "UNAUTHORIZED");
+        }
+        result => panic!("expected an unauthorized error, got {result:?}"),
+    }
+}
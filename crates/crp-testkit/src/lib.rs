@@ -0,0 +1,457 @@
+use anyhow::{anyhow, bail, ensure, Result};
+use cid::Cid;
+use cid_filter::{table::multicodec::JSON_JCS, CidFilter};
+use cid_router::{
+    config::ProviderConfig,
+    crp::{Crp, CrpError},
+};
+use routes::Route;
+
+/// URL no provider serves, which resolving should find nothing for
+const UNKNOWN_URL: &str = "https://crp-testkit.invalid/unknown";
+
+/// Content the provider has, written to its backing service before the checks run
+#[derive(Debug, Clone)]
+pub struct Sample {
+    pub cid: Cid,
+    /// Size of the content, which routes with `size` metadata have to agree with
+    pub size: Option<u64>,
+    /// URL of the content, which the provider has to resolve to the CID
+    pub url: Option<String>,
+}
+
+/// CIDs to run the checks with
+#[derive(Debug, Clone, Default)]
+pub struct Samples {
+    /// Content the provider has
+    pub present: Vec<Sample>,
+    /// CIDs the provider doesn't have content for, including CIDs it isn't eligible for
+    pub absent: Vec<Cid>,
+}
+
+impl Samples {
+    pub fn cids(&self) -> Vec<Cid> {
+        self.present
+            .iter()
+            .map(|sample| sample.cid)
+            .chain(self.absent.iter().copied())
+            .collect()
+    }
+}
+
+/// Run every conformance check against an initialized CRP. The CRP is initialized again to check
+/// reindexing, which is why it's taken mutably.
+pub async fn run_all<C>(crp: &mut C, samples: &Samples) -> Result<()>
+where
+    C: Crp + Sync + ?Sized,
+{
+    let cids = samples.cids();
+
+    check_provider_id(crp)?;
+    check_filter_coherence(crp, &cids)?;
+    check_routes(crp, &cids).await?;
+    check_size_agreement(crp, &samples.present).await?;
+    check_resolver_round_trip(crp, &samples.present).await?;
+    check_error_taxonomy(crp, &samples.absent).await?;
+    check_reindex_idempotency(crp, &cids).await?;
+
+    Ok(())
+}
+
+/// Check the provider ID is deterministic and derived from a config that round-trips through
+/// [`ProviderConfig`].
+pub fn check_provider_id<C>(crp: &C) -> Result<()>
+where
+    C: Crp + ?Sized,
+{
+    let provider_id = crp.provider_id();
+
+    ensure!(
+        provider_id == crp.provider_id(),
+        "provider id is not deterministic"
+    );
+
+    let cid = Cid::try_from(provider_id.as_str())?;
+    ensure!(
+        cid.codec() == JSON_JCS,
+        "provider id={provider_id} is not a JCS CID"
+    );
+
+    let provider_config = crp.provider_config();
+    let round_tripped = serde_json::to_value(serde_json::from_value::<ProviderConfig>(
+        provider_config.clone(),
+    )?)?;
+    ensure!(
+        round_tripped == provider_config,
+        "provider config does not round-trip through ProviderConfig: {provider_config}"
+    );
+
+    Ok(())
+}
+
+/// Check the CID filter keeps its meaning over a JSON round trip (as used by external CRPs) and
+/// agrees with provider eligibility.
+pub fn check_filter_coherence<C>(crp: &C, samples: &[Cid]) -> Result<()>
+where
+    C: Crp + ?Sized,
+{
+    let filter = crp.cid_filter();
+    let round_tripped = serde_json::from_value::<CidFilter>(serde_json::to_value(&filter)?)?;
+
+    for cid in samples {
+        let is_match = filter.is_match(cid);
+
+        ensure!(
+            round_tripped.is_match(cid) == is_match,
+            "cid filter changes meaning after a JSON round trip for cid={cid}"
+        );
+        ensure!(
+            crp.provider_is_eligible_for_cid(cid) == is_match,
+            "provider eligibility disagrees with its cid filter for cid={cid}"
+        );
+    }
+
+    Ok(())
+}
+
+/// Check routes for eligible CIDs are stable across repeated lookups and attributed to the
+/// provider.
+pub async fn check_routes<C>(crp: &C, samples: &[Cid]) -> Result<()>
+where
+    C: Crp + Sync + ?Sized,
+{
+    let provider_id = crp.provider_id();
+
+    for cid in samples {
+        if !crp.provider_is_eligible_for_cid(cid) {
+            continue;
+        }
+
+        let routes = lookup_routes(crp, cid).await?;
+        let routes_again = lookup_routes(crp, cid).await?;
+
+        ensure!(
+            routes == routes_again,
+            "routes for cid={cid} changed between identical lookups"
+        );
+
+        for route in &routes {
+            if let Some(crp_id) = &route.crp_id {
+                ensure!(
+                    *crp_id == provider_id,
+                    "route for cid={cid} is attributed to crp_id={crp_id} instead of provider id={provider_id}"
+                );
+            }
+            ensure!(
+                route.method.is_object(),
+                "route method for cid={cid} is not a JSON object"
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Check the provider has routes for the content it has, and that routes with `size` metadata
+/// agree with the content's size.
+pub async fn check_size_agreement<C>(crp: &C, present: &[Sample]) -> Result<()>
+where
+    C: Crp + Sync + ?Sized,
+{
+    for Sample { cid, size, .. } in present {
+        ensure!(
+            crp.provider_is_eligible_for_cid(cid),
+            "provider isn't eligible for cid={cid}, which it has content for"
+        );
+
+        let routes = lookup_routes(crp, cid).await?;
+        ensure!(
+            !routes.is_empty(),
+            "no routes for cid={cid}, which the provider has content for"
+        );
+
+        for route in &routes {
+            let Some(route_size) = route.metadata.as_ref().and_then(|m| m.get("size")) else {
+                continue;
+            };
+
+            let route_size = route_size.as_u64().ok_or_else(|| {
+                anyhow!("route size={route_size} for cid={cid} isn't a number of bytes")
+            })?;
+
+            if let Some(size) = size {
+                ensure!(
+                    route_size == *size,
+                    "route for cid={cid} has size={route_size} instead of the content's size={size}"
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Check URLs of the provider's content resolve to its CID, with a route the provider also returns
+/// for the CID, and that a URL the provider doesn't serve resolves to nothing.
+pub async fn check_resolver_round_trip<C>(crp: &C, present: &[Sample]) -> Result<()>
+where
+    C: Crp + Sync + ?Sized,
+{
+    for Sample { cid, url, .. } in present {
+        let Some(url) = url else {
+            continue;
+        };
+
+        let Some((resolved_cid, route)) = crp.resolve_url(url).await? else {
+            bail!("url={url} of cid={cid} didn't resolve");
+        };
+
+        ensure!(
+            resolved_cid == *cid,
+            "url={url} resolved to cid={resolved_cid} instead of cid={cid}"
+        );
+
+        let fingerprint = route.fingerprint(&cid.to_string())?;
+        ensure!(
+            fingerprints(&lookup_routes(crp, cid).await?, cid)?.contains(&fingerprint),
+            "route url={url} resolved to isn't among the routes for cid={cid}"
+        );
+    }
+
+    ensure!(
+        crp.resolve_url(UNKNOWN_URL).await?.is_none(),
+        "url={UNKNOWN_URL}, which the provider doesn't serve, resolved"
+    );
+
+    Ok(())
+}
+
+/// Check lookups of eligible CIDs the provider doesn't have content for find no routes, or fail
+/// with [`CrpError::NotFound`], so the router doesn't count them as provider failures.
+pub async fn check_error_taxonomy<C>(crp: &C, absent: &[Cid]) -> Result<()>
+where
+    C: Crp + Sync + ?Sized,
+{
+    for cid in absent {
+        if !crp.provider_is_eligible_for_cid(cid) {
+            continue;
+        }
+
+        match crp.get_routes_for_cid(cid).await {
+            Ok(routes) => ensure!(
+                routes.is_empty(),
+                "found routes for cid={cid}, which the provider doesn't have content for"
+            ),
+            Err(e) => ensure!(
+                matches!(CrpError::of(&e), CrpError::NotFound(_)),
+                "lookup of cid={cid}, which the provider doesn't have content for, failed with an error other than not found: {e}"
+            ),
+        }
+    }
+
+    Ok(())
+}
+
+/// Check initializing the provider again, which is when providers index their content, keeps its
+/// ID, CID filter and routes.
+pub async fn check_reindex_idempotency<C>(crp: &mut C, samples: &[Cid]) -> Result<()>
+where
+    C: Crp + Sync + ?Sized,
+{
+    let provider_id = crp.provider_id();
+    let filter = serde_json::to_value(crp.cid_filter())?;
+    let routes = eligible_route_fingerprints(crp, samples).await?;
+
+    crp.init().await?;
+
+    ensure!(
+        crp.provider_id() == provider_id,
+        "provider id changed on reindexing"
+    );
+    ensure!(
+        serde_json::to_value(crp.cid_filter())? == filter,
+        "cid filter changed on reindexing"
+    );
+    ensure!(
+        eligible_route_fingerprints(crp, samples).await? == routes,
+        "routes changed on reindexing"
+    );
+
+    Ok(())
+}
+
+/// Routes for a CID, none if the lookup fails with [`CrpError::NotFound`] as it does for the router
+async fn lookup_routes<C>(crp: &C, cid: &Cid) -> Result<Vec<Route>>
+where
+    C: Crp + Sync + ?Sized,
+{
+    match crp.get_routes_for_cid(cid).await {
+        Ok(routes) => Ok(routes),
+        Err(e) => match CrpError::of(&e) {
+            CrpError::NotFound(_) => Ok(vec![]),
+            _ => Err(e),
+        },
+    }
+}
+
+/// Sorted fingerprints of the routes for each eligible CID
+async fn eligible_route_fingerprints<C>(crp: &C, samples: &[Cid]) -> Result<Vec<Vec<String>>>
+where
+    C: Crp + Sync + ?Sized,
+{
+    let mut all_fingerprints = vec![];
+
+    for cid in samples {
+        if crp.provider_is_eligible_for_cid(cid) {
+            all_fingerprints.push(fingerprints(&lookup_routes(crp, cid).await?, cid)?);
+        }
+    }
+
+    Ok(all_fingerprints)
+}
+
+/// Sorted fingerprints of routes for a CID, which leave out metadata such as when a route was
+/// last checked
+fn fingerprints(routes: &[Route], cid: &Cid) -> Result<Vec<String>> {
+    let cid = cid.to_string();
+
+    let mut fingerprints = routes
+        .iter()
+        .map(|route| route.fingerprint(&cid))
+        .collect::<Result<Vec<_>, _>>()?;
+    fingerprints.sort();
+
+    Ok(fingerprints)
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use async_trait::async_trait;
+    use cid::multihash::Multihash;
+    use cid_filter::{
+        table::{
+            multicodec::{DAG_CBOR, RAW},
+            multihash::{BLAKE3, SHA256},
+        },
+        CodeFilter,
+    };
+    use cid_router::crp::external::ExternalCrpConfig;
+    use routes::{IntoRoute, UrlRouteMethod};
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    /// Has 5 bytes of content for one blake3 CID, at `https://example.com/<cid>`
+    struct MockCrp {
+        config: ProviderConfig,
+        crp_id: Option<String>,
+        size: u64,
+    }
+
+    impl MockCrp {
+        fn new() -> Self {
+            let config = ProviderConfig::External(ExternalCrpConfig {
+                url: "http://localhost:3081/v1/crp".to_owned(),
+            });
+
+            Self {
+                config,
+                crp_id: None,
+                size: 5,
+            }
+        }
+
+        fn route(&self, cid: &Cid) -> Result<Route> {
+            let crp_id = self.crp_id.clone().or(Some(self.provider_id()));
+
+            Ok(UrlRouteMethod {
+                url: format!("https://example.com/{cid}"),
+            }
+            .into_route(crp_id, Some(json!({ "size": self.size })))?)
+        }
+    }
+
+    #[async_trait]
+    impl Crp for MockCrp {
+        async fn init(&mut self) -> Result<()> {
+            Ok(())
+        }
+
+        fn cid_filter(&self) -> CidFilter {
+            CidFilter::MultihashCodeFilter(CodeFilter::Eq(BLAKE3))
+        }
+
+        async fn get_routes_for_cid(&self, cid: &Cid) -> Result<Vec<Route>> {
+            if *cid != present_cid() {
+                return Err(CrpError::NotFound(format!("no content for cid={cid}")).into());
+            }
+
+            Ok(vec![self.route(cid)?])
+        }
+
+        async fn resolve_url(&self, url: &str) -> Result<Option<(Cid, Route)>> {
+            let cid = present_cid();
+
+            if url != format!("https://example.com/{cid}") {
+                return Ok(None);
+            }
+
+            Ok(Some((cid, self.route(&cid)?)))
+        }
+
+        async fn check_health(&self) -> Result<()> {
+            Ok(())
+        }
+
+        fn provider_config(&self) -> Value {
+            serde_json::to_value(&self.config).unwrap()
+        }
+    }
+
+    fn present_cid() -> Cid {
+        Cid::new_v1(RAW, Multihash::wrap(BLAKE3, &[0; 32]).unwrap())
+    }
+
+    fn samples() -> Samples {
+        Samples {
+            present: vec![Sample {
+                cid: present_cid(),
+                size: Some(5),
+                url: Some(format!("https://example.com/{}", present_cid())),
+            }],
+            absent: vec![
+                Cid::new_v1(RAW, Multihash::wrap(BLAKE3, &[1; 32]).unwrap()),
+                Cid::new_v1(RAW, Multihash::wrap(SHA256, &[0; 32]).unwrap()),
+                Cid::new_v1(DAG_CBOR, Multihash::wrap(SHA256, &[0; 32]).unwrap()),
+            ],
+        }
+    }
+
+    #[tokio::test]
+    async fn conforming_crp() {
+        run_all(&mut MockCrp::new(), &samples()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn misattributed_routes() {
+        let crp = MockCrp {
+            crp_id: Some("someone-else".to_owned()),
+            ..MockCrp::new()
+        };
+
+        assert!(check_routes(&crp, &samples().cids()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn wrong_size() {
+        let crp = MockCrp {
+            size: 6,
+            ..MockCrp::new()
+        };
+
+        assert!(check_size_agreement(&crp, &samples().present)
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,252 @@
+//! Conformance checks for the router's providers against a mock of each one's backing service,
+//! served on a local port. The iroh provider needs a running iroh node, so it isn't covered.
+
+use std::net::{Ipv4Addr, TcpListener};
+
+use axum::{
+    extract::Path,
+    http::StatusCode,
+    routing::{get, post},
+    Json, Router,
+};
+use cid::{multihash::Multihash, Cid};
+use cid_filter::{
+    table::{
+        multicodec::{DAG_CBOR, GIT_RAW, RAW},
+        multihash::{BLAKE3, SHA1, SHA256},
+    },
+    CidFilter, CodeFilter,
+};
+use cid_router::{
+    config::ProviderConfig,
+    crp::{
+        external::{ExternalCrp, ExternalCrpConfig},
+        github::{GithubCrp, GithubCrpConfig, GithubRepo},
+        ipfs::{IpfsCrp, IpfsCrpConfig},
+        peer_router::{PeerRouterCrp, PeerRouterCrpConfig},
+        Crp,
+    },
+};
+use crp_testkit::{Sample, Samples};
+use serde_json::{json, Value};
+
+/// Size of the content of the blake3 CID the mock external CRP and peer router have
+const BLOB_SIZE: u64 = 5;
+
+fn blob_cid() -> Cid {
+    Cid::new_v1(RAW, Multihash::wrap(BLAKE3, &[1; 32]).unwrap())
+}
+
+fn dag_cbor_cid() -> Cid {
+    Cid::new_v1(DAG_CBOR, Multihash::wrap(SHA256, &[1; 32]).unwrap())
+}
+
+fn commit_cid() -> Cid {
+    Cid::new_v1(GIT_RAW, Multihash::wrap(SHA1, &[1; 20]).unwrap())
+}
+
+/// CIDs none of the mocks have content for, of each kind a provider here is eligible for
+fn absent_cids() -> Vec<Cid> {
+    vec![
+        Cid::new_v1(RAW, Multihash::wrap(BLAKE3, &[2; 32]).unwrap()),
+        Cid::new_v1(DAG_CBOR, Multihash::wrap(SHA256, &[2; 32]).unwrap()),
+        Cid::new_v1(GIT_RAW, Multihash::wrap(SHA1, &[2; 20]).unwrap()),
+    ]
+}
+
+fn blob_route(base_url: &str) -> Value {
+    json!({
+        "type": "url",
+        "method": { "url": format!("{base_url}/blobs/{}", blob_cid()) },
+        "metadata": { "size": BLOB_SIZE },
+    })
+}
+
+/// Serve mocks of an external CRP, an IPFS gateway, the GitHub API and a peer router, each with
+/// content for one CID, returning their base URL
+fn serve_mocks() -> String {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    let base_url = format!("http://{}", listener.local_addr().unwrap());
+
+    let url = base_url.clone();
+    let crp_routes = move |Path(cid): Path<String>| {
+        let url = url.clone();
+        async move {
+            if cid == blob_cid().to_string() {
+                Ok(Json(json!({ "routes": [blob_route(&url)] })))
+            } else {
+                Err(StatusCode::NOT_FOUND)
+            }
+        }
+    };
+
+    let url = base_url.clone();
+    let crp_resolve = move |Json(body): Json<Value>| {
+        let url = url.clone();
+        async move {
+            if body["url"] == format!("{url}/blobs/{}", blob_cid()) {
+                Ok(Json(
+                    json!({ "cid": blob_cid().to_string(), "route": blob_route(&url) }),
+                ))
+            } else {
+                Err(StatusCode::NOT_FOUND)
+            }
+        }
+    };
+
+    let crp_filter = || async {
+        Json(json!({ "filter": CidFilter::MultihashCodeFilter(CodeFilter::Eq(BLAKE3)) }))
+    };
+
+    let ipfs = |Path(cid): Path<String>| async move {
+        if cid == dag_cbor_cid().to_string() {
+            StatusCode::OK
+        } else {
+            StatusCode::NOT_FOUND
+        }
+    };
+
+    // github responds 422 for commit shas it doesn't know
+    let github_commit = |Path((_, _, sha)): Path<(String, String, String)>| async move {
+        if sha == hex::encode(commit_cid().hash().digest()) {
+            StatusCode::OK
+        } else {
+            StatusCode::UNPROCESSABLE_ENTITY
+        }
+    };
+
+    let url = base_url.clone();
+    let peer_routes = move |Path(cid): Path<String>| {
+        let url = url.clone();
+        async move {
+            let routes = if cid == blob_cid().to_string() {
+                vec![blob_route(&url)]
+            } else {
+                vec![]
+            };
+
+            Json(json!({ "routes": routes }))
+        }
+    };
+
+    let app = Router::new()
+        .route("/v1/crp/filter", get(crp_filter))
+        .route("/v1/crp/routes/:cid", get(crp_routes))
+        .route("/v1/crp/resolve", post(crp_resolve))
+        .route("/ipfs/:cid", get(ipfs))
+        .route("/repos/:owner/:repo/commits/:sha", get(github_commit))
+        .route("/v1/routes/:cid", get(peer_routes))
+        .route("/healthz", get(|| async { StatusCode::OK }));
+
+    let server = axum::Server::from_tcp(listener)
+        .unwrap()
+        .serve(app.into_make_service());
+    tokio::spawn(server);
+
+    base_url
+}
+
+#[tokio::test]
+async fn external() {
+    let base_url = serve_mocks();
+
+    let config = ExternalCrpConfig {
+        url: format!("{base_url}/v1/crp"),
+    };
+    let mut crp =
+        ExternalCrp::new_from_config(config.clone(), ProviderConfig::External(config), None)
+            .unwrap();
+    crp.init().await.unwrap();
+
+    let samples = Samples {
+        present: vec![Sample {
+            cid: blob_cid(),
+            size: Some(BLOB_SIZE),
+            url: Some(format!("{base_url}/blobs/{}", blob_cid())),
+        }],
+        absent: absent_cids(),
+    };
+
+    crp_testkit::run_all(&mut crp, &samples).await.unwrap();
+}
+
+#[tokio::test]
+async fn ipfs() {
+    let base_url = serve_mocks();
+
+    let config = IpfsCrpConfig {
+        gateway_url: base_url,
+    };
+    let mut crp =
+        IpfsCrp::new_from_config(config.clone(), ProviderConfig::Ipfs(config), None).unwrap();
+    crp.init().await.unwrap();
+
+    let samples = Samples {
+        present: vec![Sample {
+            cid: dag_cbor_cid(),
+            size: None,
+            url: None,
+        }],
+        absent: absent_cids(),
+    };
+
+    crp_testkit::run_all(&mut crp, &samples).await.unwrap();
+}
+
+#[tokio::test]
+async fn github() {
+    let base_url = serve_mocks();
+
+    let config = GithubCrpConfig {
+        repos: vec![GithubRepo {
+            owner: "eqtylab".to_owned(),
+            repo: "cid-router".to_owned(),
+        }],
+        api_url: Some(base_url),
+        token_env: None,
+    };
+    let mut crp =
+        GithubCrp::new_from_config(config.clone(), ProviderConfig::Github(config), None).unwrap();
+    crp.init().await.unwrap();
+
+    let sha = hex::encode(commit_cid().hash().digest());
+    let samples = Samples {
+        present: vec![Sample {
+            cid: commit_cid(),
+            size: None,
+            url: Some(format!(
+                "https://github.com/eqtylab/cid-router/commit/{sha}"
+            )),
+        }],
+        absent: absent_cids(),
+    };
+
+    crp_testkit::run_all(&mut crp, &samples).await.unwrap();
+}
+
+#[tokio::test]
+async fn peer_router() {
+    let base_url = serve_mocks();
+
+    let config = PeerRouterCrpConfig {
+        urls: vec![base_url],
+        api_key_env: None,
+        cache_ttl_ms: Some(60 * 1000),
+        cache_max_entries: None,
+    };
+    let mut crp =
+        PeerRouterCrp::new_from_config(config.clone(), ProviderConfig::PeerRouter(config), None)
+            .unwrap();
+    crp.init().await.unwrap();
+
+    let samples = Samples {
+        present: vec![Sample {
+            cid: blob_cid(),
+            size: Some(BLOB_SIZE),
+            url: None,
+        }],
+        absent: absent_cids(),
+    };
+
+    crp_testkit::run_all(&mut crp, &samples).await.unwrap();
+}
@@ -1,9 +1,11 @@
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use sha2::{Digest, Sha256};
 use utoipa::ToSchema;
 
 /// A route defining a method for resolving a CID to its content and/or metadata associated with its content.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[schema(as = routes::Route)]
 pub struct Route {
     /// CID Route Provider ID.
     /// This optional value is only meant for use by CID Routers with multiple CID Route Providers.
@@ -22,6 +24,23 @@ pub struct Route {
     pub metadata: Option<Value>,
 }
 
+impl Route {
+    /// Deterministic identity of the route to a CID.
+    /// Hex encoded sha256 of the JCS of the CID, `crp_id`, `type` and `method`. Metadata isn't
+    /// included, so the same route seen through different routers or imports gets the same
+    /// fingerprint and can be deduplicated.
+    pub fn fingerprint(&self, cid: &str) -> Result<String, serde_json::Error> {
+        let jcs = serde_jcs::to_string(&serde_json::json!({
+            "cid": cid,
+            "crp_id": self.crp_id,
+            "type": self.type_,
+            "method": self.method,
+        }))?;
+
+        Ok(hex::encode(Sha256::digest(jcs.as_bytes())))
+    }
+}
+
 pub trait IntoRoute: Sized + Serialize {
     fn type_str() -> &'static str;
 
@@ -39,6 +58,72 @@ pub trait IntoRoute: Sized + Serialize {
     }
 }
 
+/// A route method of one of the known route types
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", content = "method")]
+pub enum RouteMethod {
+    #[serde(rename = "url")]
+    Url(UrlRouteMethod),
+    #[serde(rename = "ipfs")]
+    Ipfs(IpfsRouteMethod),
+    #[serde(rename = "iroh")]
+    Iroh(IrohRouteMethod),
+    #[serde(rename = "azure_blob_storage")]
+    AzureBlobStorage(AzureBlobStorageRouteMethod),
+    #[serde(rename = "aws_s3")]
+    AwsS3(AwsS3RouteMethod),
+    #[serde(rename = "github")]
+    Github(GithubRouteMethod),
+    #[serde(rename = "huggingface")]
+    HuggingFace(HuggingFaceRouteMethod),
+}
+
+impl RouteMethod {
+    /// Parse the method of a route.
+    /// Returns `Ok(None)` if the route's type isn't a known route type, and an error if the type is
+    /// known but the method doesn't match its schema.
+    pub fn from_route(route: &Route) -> Result<Option<Self>, serde_json::Error> {
+        if !Self::is_known_type(&route.type_) {
+            return Ok(None);
+        }
+
+        serde_json::from_value(serde_json::json!({
+            "type": route.type_,
+            "method": route.method,
+        }))
+        .map(Some)
+    }
+
+    pub fn is_known_type(type_: &str) -> bool {
+        [
+            UrlRouteMethod::type_str(),
+            IpfsRouteMethod::type_str(),
+            IrohRouteMethod::type_str(),
+            AzureBlobStorageRouteMethod::type_str(),
+            AwsS3RouteMethod::type_str(),
+            GithubRouteMethod::type_str(),
+            HuggingFaceRouteMethod::type_str(),
+        ]
+        .contains(&type_)
+    }
+
+    pub fn into_route(
+        self,
+        crp_id: Option<String>,
+        metadata: Option<Value>,
+    ) -> Result<Route, serde_json::Error> {
+        match self {
+            Self::Url(method) => method.into_route(crp_id, metadata),
+            Self::Ipfs(method) => method.into_route(crp_id, metadata),
+            Self::Iroh(method) => method.into_route(crp_id, metadata),
+            Self::AzureBlobStorage(method) => method.into_route(crp_id, metadata),
+            Self::AwsS3(method) => method.into_route(crp_id, metadata),
+            Self::Github(method) => method.into_route(crp_id, metadata),
+            Self::HuggingFace(method) => method.into_route(crp_id, metadata),
+        }
+    }
+}
+
 /// URL Route Method
 ///
 /// Resolve a CID by fetching content from a URL.
@@ -187,6 +272,38 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn route_fingerprint() {
+        let cid = "bafkr4ihkr4ld3m4gqkjf4reryxsy2s5tkbxprqkow6fin2iiyvreuzzab4";
+
+        let route = UrlRouteMethod {
+            url: "https://example.com".to_owned(),
+        }
+        .into_route(Some("crp".to_owned()), None)
+        .unwrap();
+
+        let with_metadata = Route {
+            metadata: Some(json!({ "size": 1 })),
+            ..route.clone()
+        };
+        let other_crp = Route {
+            crp_id: Some("other".to_owned()),
+            ..route.clone()
+        };
+
+        let fingerprint = route.fingerprint(cid).unwrap();
+
+        assert_eq!(fingerprint.len(), 64);
+        assert_eq!(fingerprint, with_metadata.fingerprint(cid).unwrap());
+        assert_ne!(fingerprint, other_crp.fingerprint(cid).unwrap());
+        assert_ne!(
+            fingerprint,
+            route
+                .fingerprint("bafkreihdwdcefgh4dqkjv67uzcmw7ojee6xedzdetojuzjevtenxquvyku")
+                .unwrap()
+        );
+    }
+
     #[test]
     fn url_route_method() {
         let url_route_method = UrlRouteMethod {
@@ -392,4 +509,60 @@ mod tests {
             huggingface_route_method.into_route(None, None).unwrap()
         );
     }
+
+    #[test]
+    fn route_method_from_route() {
+        let route = Route {
+            crp_id: None,
+            type_: "aws_s3".to_owned(),
+            method: json!({
+                "bucket": "bucket",
+                "object": "object",
+                "region": "ignored",
+            }),
+            metadata: Some(json!({ "size": 1 })),
+        };
+
+        let route_method = RouteMethod::from_route(&route).unwrap().unwrap();
+        assert!(matches!(route_method, RouteMethod::AwsS3(_)));
+
+        let normalized = route_method
+            .into_route(Some("crp".to_owned()), route.metadata.clone())
+            .unwrap();
+        assert_eq!(
+            normalized,
+            Route {
+                crp_id: Some("crp".to_owned()),
+                method: json!({
+                    "bucket": "bucket",
+                    "object": "object",
+                }),
+                ..route
+            }
+        );
+    }
+
+    #[test]
+    fn route_method_from_route_unknown_type() {
+        let route = Route {
+            crp_id: None,
+            type_: "carrier_pigeon".to_owned(),
+            method: json!({}),
+            metadata: None,
+        };
+
+        assert!(RouteMethod::from_route(&route).unwrap().is_none());
+    }
+
+    #[test]
+    fn route_method_from_route_malformed() {
+        let route = Route {
+            crp_id: None,
+            type_: "url".to_owned(),
+            method: json!({ "href": "https://example.com" }),
+            metadata: None,
+        };
+
+        assert!(RouteMethod::from_route(&route).is_err());
+    }
 }
@@ -1,31 +1,95 @@
 pub mod v1;
 
-use std::{net::SocketAddr, sync::Arc};
+use std::{net::SocketAddr, sync::Arc, time::Duration};
 
 use anyhow::Result;
-use axum::{response::Redirect, routing::get, Router};
+use api_utils::shutdown;
+use axum::{
+    middleware,
+    response::Redirect,
+    routing::{delete, get, post},
+    Router,
+};
 use log::info;
+use tower_http::compression::{
+    predicate::{NotForContentType, Predicate},
+    CompressionLayer, DefaultPredicate,
+};
 use utoipa::OpenApi;
 use utoipa_swagger_ui::SwaggerUi;
 
-use crate::context::Context;
+use crate::{auth, context::Context, db, events, scheduler};
 
 #[derive(OpenApi)]
 #[openapi(
     paths(
+        v1::admin::force_prune::post_force_prune,
+        v1::admin::maintenance::get_maintenance_tasks,
+        v1::admin::maintenance::post_maintenance_task,
+        v1::admin::prune_stale_stubs::post_prune_stale_stubs,
+        v1::admin::stats::get_stats,
         v1::crp::filter::get_filter,
+        v1::crp::resolve::post_resolve,
         v1::crp::routes::get_routes,
+        v1::data::ls::get_ls,
         v1::db::tables::blob_index::get_blob_index_table,
         v1::db::tables::collection_index::get_collection_index_table,
         v1::db::tables::hash_index::get_hash_index_table,
         v1::db::tables::hash_index_detailed::get_hash_index_detailed_table,
+        v1::events::list::get_events,
+        v1::events::stream::get_events_stream,
+        v1::indexer::jobs::get_jobs,
+        v1::indexer::jobs::get_job,
+        v1::indexer::shutdown_report::get_shutdown_report,
+        v1::routes::by_url::get_route_by_url,
+        v1::routes::duplicates::get_duplicates,
+        v1::routes::tombstones::delete_route,
+        v1::routes::tombstones::delete_container_routes,
+        v1::routes::tombstones::get_tombstones,
+        v1::routes::tombstones::delete_tombstone,
         v1::status::get_status,
+        v1::watches::delete_watch,
+        v1::watches::get_watches,
+        v1::watches::post_watch,
     ),
     components(
         schemas(
+            api_utils::ApiErrorBody,
+            api_utils::ErrorCode,
+            v1::admin::force_prune::ForcePruneResponse,
+            v1::admin::maintenance::MaintenanceTasksResponse,
+            v1::admin::prune_stale_stubs::PruneStaleStubsResponse,
             v1::crp::filter::CrpGetFilterResponse,
+            v1::crp::resolve::CrpResolveRequest,
+            v1::crp::resolve::CrpResolveResponse,
             v1::crp::routes::CrpGetRoutesResponse,
-            v1::crp::routes::Route,
+            routes::Route,
+            v1::data::ls::LsResponse,
+            v1::events::list::EventsResponse,
+            v1::indexer::jobs::IndexerJobsResponse,
+            v1::routes::by_url::BlobHashState,
+            v1::routes::by_url::ByUrlResponse,
+            v1::routes::duplicates::DuplicatesResponse,
+            v1::routes::duplicates::DuplicateGroup,
+            v1::routes::tombstones::DeleteRoutesResponse,
+            v1::routes::tombstones::TombstonesResponse,
+            v1::watches::CreateWatchRequest,
+            v1::watches::WatchesResponse,
+            db::CollectionChild,
+            db::Tombstone,
+            db::Watch,
+            db::ContainerStats,
+            db::DbStats,
+            db::Job,
+            db::JobState,
+            db::JobPhase,
+            db::ShutdownReport,
+            events::PruneCounts,
+            events::RouteEvent,
+            events::RouteEventKind,
+            scheduler::MaintenanceTask,
+            scheduler::MaintenanceTaskRun,
+            scheduler::MaintenanceTaskStatus,
             v1::status::StatusResponse,
         )
     ),
@@ -35,12 +99,16 @@ use crate::context::Context;
 )]
 struct ApiDoc;
 
-pub async fn start(ctx: Arc<Context>) -> Result<()> {
+/// Serve the API until SIGINT or SIGTERM, then give in-flight requests up to `shutdown_timeout`
+/// to finish
+pub async fn start(ctx: Arc<Context>, shutdown_timeout: Duration) -> Result<()> {
     let addr = SocketAddr::from(([0, 0, 0, 0], ctx.port));
 
     info!("🚀 Starting Azure Blob Storage CRP");
     info!("🚀 HTTP API = {addr}");
 
+    let require_admin = || middleware::from_fn_with_state(ctx.clone(), auth::require_admin);
+
     let router = Router::new()
         .merge(
             SwaggerUi::new("/swagger")
@@ -51,8 +119,27 @@ pub async fn start(ctx: Arc<Context>) -> Result<()> {
             "/",
             get(move || async move { Redirect::temporary("/swagger") }),
         )
+        .route(
+            "/v1/admin/force-prune",
+            post(v1::admin::force_prune::post_force_prune).route_layer(require_admin()),
+        )
+        .route(
+            "/v1/admin/maintenance",
+            get(v1::admin::maintenance::get_maintenance_tasks),
+        )
+        .route(
+            "/v1/admin/maintenance/:task",
+            post(v1::admin::maintenance::post_maintenance_task).route_layer(require_admin()),
+        )
+        .route(
+            "/v1/admin/prune-stale-stubs",
+            post(v1::admin::prune_stale_stubs::post_prune_stale_stubs).route_layer(require_admin()),
+        )
+        .route("/v1/admin/stats", get(v1::admin::stats::get_stats))
         .route("/v1/crp/filter", get(v1::crp::filter::get_filter))
+        .route("/v1/crp/resolve", post(v1::crp::resolve::post_resolve))
         .route("/v1/crp/routes/:cid", get(v1::crp::routes::get_routes))
+        .route("/v1/data/:cid/ls", get(v1::data::ls::get_ls))
         .route(
             "/v1/db/tables/blob-index",
             get(v1::db::tables::blob_index::get_blob_index_table),
@@ -69,12 +156,53 @@ pub async fn start(ctx: Arc<Context>) -> Result<()> {
             "/v1/db/tables/hash-index-detailed",
             get(v1::db::tables::hash_index_detailed::get_hash_index_detailed_table),
         )
+        .route("/v1/events", get(v1::events::list::get_events))
+        .route(
+            "/v1/events/stream",
+            get(v1::events::stream::get_events_stream),
+        )
+        .route("/v1/indexer/jobs", get(v1::indexer::jobs::get_jobs))
+        .route("/v1/indexer/jobs/:id", get(v1::indexer::jobs::get_job))
+        .route(
+            "/v1/indexer/shutdown-report",
+            get(v1::indexer::shutdown_report::get_shutdown_report),
+        )
+        .route(
+            "/v1/routes/by-url",
+            get(v1::routes::by_url::get_route_by_url),
+        )
+        .route(
+            "/v1/routes/duplicates",
+            get(v1::routes::duplicates::get_duplicates),
+        )
+        .route(
+            "/v1/routes/tombstones",
+            get(v1::routes::tombstones::get_tombstones),
+        )
+        .route(
+            "/v1/routes/tombstones/:account/:container/*name",
+            delete(v1::routes::tombstones::delete_tombstone).route_layer(require_admin()),
+        )
+        .route(
+            "/v1/routes/:account/:container",
+            delete(v1::routes::tombstones::delete_container_routes).route_layer(require_admin()),
+        )
+        .route(
+            "/v1/routes/:account/:container/*name",
+            delete(v1::routes::tombstones::delete_route).route_layer(require_admin()),
+        )
         .route("/v1/status", get(v1::status::get_status))
+        .route(
+            "/v1/watches",
+            get(v1::watches::get_watches).post(v1::watches::post_watch),
+        )
+        .route("/v1/watches/:cid", delete(v1::watches::delete_watch))
+        // gzip or zstd as the request accepts, event streams aren't compressed so events aren't
+        // held back in the encoder's buffer
+        .layer(CompressionLayer::new().compress_when(
+            DefaultPredicate::new().and(NotForContentType::const_new("text/event-stream")),
+        ))
         .with_state(ctx);
 
-    axum::Server::bind(&addr)
-        .serve(router.into_make_service())
-        .await?;
-
-    Ok(())
+    shutdown::serve(router, addr, shutdown::signal(), shutdown_timeout).await
 }
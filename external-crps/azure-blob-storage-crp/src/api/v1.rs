@@ -1,3 +1,9 @@
+pub mod admin;
 pub mod crp;
+pub mod data;
 pub mod db;
+pub mod events;
+pub mod indexer;
+pub mod routes;
 pub mod status;
+pub mod watches;
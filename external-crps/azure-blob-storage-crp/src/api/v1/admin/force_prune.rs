@@ -0,0 +1,68 @@
+use std::sync::Arc;
+
+use api_utils::{ApiError, ApiResult, ErrorCode};
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{config::ContainerConfig, context::Context};
+
+#[derive(Deserialize, IntoParams)]
+pub struct ForcePruneQuery {
+    account: String,
+    container: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ForcePruneResponse {
+    pruned: u64,
+}
+
+/// Force Prune
+///
+/// Prune index entries for deleted or filtered blobs in a container, ignoring `max_prune_percent`.
+#[utoipa::path(
+    post,
+    path = "/v1/admin/force-prune",
+    tag = "/v1/admin/force-prune",
+    params(ForcePruneQuery),
+    responses(
+        (status = 200, description = "Prune a container's index entries without a churn limit", body = ForcePruneResponse),
+        (status = 401, description = "Missing or invalid admin API key", body = ApiErrorBody),
+        (status = 404, description = "Container not configured", body = ApiErrorBody)
+    )
+)]
+pub async fn post_force_prune(
+    Query(query): Query<ForcePruneQuery>,
+    State(ctx): State<Arc<Context>>,
+) -> ApiResult<Json<ForcePruneResponse>> {
+    let Context {
+        db,
+        blob_storage_config,
+        ..
+    } = &*ctx;
+
+    let ForcePruneQuery { account, container } = query;
+
+    let ContainerConfig { filter, .. } = blob_storage_config
+        .containers
+        .iter()
+        .find(|c| c.account == account && c.container == container)
+        .ok_or_else(|| {
+            ApiError::new(
+                ErrorCode::NotFound,
+                format!("account={account} container={container} is not configured"),
+            )
+        })?;
+
+    log::info!("Force pruning index entries for account={account} container={container}");
+
+    let pruned = db
+        .prune_index_entries_for_deleted_or_filtered_blobs(&account, &container, filter, None)
+        .await?;
+
+    Ok(Json(ForcePruneResponse { pruned }))
+}
@@ -0,0 +1,74 @@
+use std::sync::Arc;
+
+use api_utils::{ApiError, ApiResult, ErrorCode};
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{context::Context, scheduler::MaintenanceTaskStatus};
+
+#[derive(Serialize, ToSchema)]
+pub struct MaintenanceTasksResponse {
+    tasks: Vec<MaintenanceTaskStatus>,
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct SetMaintenanceTaskQuery {
+    /// Run the task on its schedule
+    enabled: bool,
+}
+
+/// Get Maintenance Tasks
+#[utoipa::path(
+    get,
+    path = "/v1/admin/maintenance",
+    tag = "/v1/admin/maintenance",
+    responses(
+        (status = 200, description = "Get maintenance task schedules and last runs", body = MaintenanceTasksResponse)
+    )
+)]
+pub async fn get_maintenance_tasks(
+    State(ctx): State<Arc<Context>>,
+) -> ApiResult<Json<MaintenanceTasksResponse>> {
+    let Context { scheduler, .. } = &*ctx;
+
+    let tasks = scheduler.get_statuses();
+
+    Ok(Json(MaintenanceTasksResponse { tasks }))
+}
+
+/// Enable or Disable Maintenance Task
+///
+/// Takes effect until restart, set `enabled` in the task's config to persist it.
+#[utoipa::path(
+    post,
+    path = "/v1/admin/maintenance/{task}",
+    tag = "/v1/admin/maintenance/{task}",
+    params(SetMaintenanceTaskQuery),
+    responses(
+        (status = 200, description = "Enable or disable a maintenance task", body = MaintenanceTaskStatus),
+        (status = 401, description = "Missing or invalid admin API key", body = ApiErrorBody),
+        (status = 404, description = "Task not found", body = ApiErrorBody)
+    )
+)]
+pub async fn post_maintenance_task(
+    Path(task): Path<String>,
+    Query(query): Query<SetMaintenanceTaskQuery>,
+    State(ctx): State<Arc<Context>>,
+) -> ApiResult<Json<MaintenanceTaskStatus>> {
+    let Context { scheduler, .. } = &*ctx;
+
+    let status = scheduler.set_enabled(&task, query.enabled).ok_or_else(|| {
+        ApiError::new(
+            ErrorCode::NotFound,
+            format!("maintenance task={task} not found"),
+        )
+    })?;
+
+    log::info!("Set maintenance task={task} enabled={}", query.enabled);
+
+    Ok(Json(status))
+}
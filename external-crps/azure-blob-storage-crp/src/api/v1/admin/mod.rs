@@ -0,0 +1,4 @@
+pub mod force_prune;
+pub mod maintenance;
+pub mod prune_stale_stubs;
+pub mod stats;
@@ -0,0 +1,63 @@
+use std::sync::Arc;
+
+use api_utils::{ApiError, ApiResult, ErrorCode};
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::context::Context;
+
+#[derive(Deserialize, IntoParams)]
+pub struct PruneStaleStubsQuery {
+    /// Prune unhashed entries first indexed more than this many seconds ago whose blob is gone or
+    /// keeps failing to hash (defaults to the configured `stub_retention`)
+    older_than: Option<u64>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct PruneStaleStubsResponse {
+    pruned: u64,
+}
+
+/// Prune Stale Stubs
+///
+/// Containers where this would prune more than `max_prune_percent` of the entries are skipped.
+#[utoipa::path(
+    post,
+    path = "/v1/admin/prune-stale-stubs",
+    tag = "/v1/admin/prune-stale-stubs",
+    params(PruneStaleStubsQuery),
+    responses(
+        (status = 200, description = "Prune unhashed blob index entries", body = PruneStaleStubsResponse),
+        (status = 401, description = "Missing or invalid admin API key", body = ApiErrorBody),
+        (status = 400, description = "No retention given or configured", body = ApiErrorBody)
+    )
+)]
+pub async fn post_prune_stale_stubs(
+    Query(query): Query<PruneStaleStubsQuery>,
+    State(ctx): State<Arc<Context>>,
+) -> ApiResult<Json<PruneStaleStubsResponse>> {
+    let Context {
+        db,
+        blob_storage_config,
+        stub_retention,
+        max_prune_percent,
+        ..
+    } = &*ctx;
+
+    let older_than = query.older_than.or(*stub_retention).ok_or_else(|| {
+        ApiError::new(
+            ErrorCode::BadRequest,
+            "older_than must be given when no stub_retention is configured",
+        )
+    })?;
+
+    let pruned = db
+        .prune_stale_stubs(blob_storage_config, older_than, *max_prune_percent)
+        .await?;
+
+    Ok(Json(PruneStaleStubsResponse { pruned }))
+}
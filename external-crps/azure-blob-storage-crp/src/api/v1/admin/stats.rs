@@ -0,0 +1,23 @@
+use std::sync::Arc;
+
+use api_utils::ApiResult;
+use axum::{extract::State, Json};
+
+use crate::{context::Context, db::DbStats};
+
+/// Get DB Stats
+#[utoipa::path(
+    get,
+    path = "/v1/admin/stats",
+    tag = "/v1/admin/stats",
+    responses(
+        (status = 200, description = "Get counts and sizes of what's indexed", body = DbStats)
+    )
+)]
+pub async fn get_stats(State(ctx): State<Arc<Context>>) -> ApiResult<Json<DbStats>> {
+    let Context { db, .. } = &*ctx;
+
+    let stats = db.get_stats()?;
+
+    Ok(Json(stats))
+}
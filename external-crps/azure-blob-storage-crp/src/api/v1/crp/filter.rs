@@ -5,7 +5,7 @@ use axum::{extract::State, Json};
 use cid_filter::{
     table::{
         multicodec::{BLAKE3_HASHSEQ, RAW},
-        multihash::BLAKE3,
+        multihash::{BLAKE3, SHA256},
     },
     CidFilter, CodeFilter,
 };
@@ -31,9 +31,12 @@ pub struct CrpGetFilterResponse {
 pub async fn get_filter(State(ctx): State<Arc<Context>>) -> ApiResult<Json<CrpGetFilterResponse>> {
     let _ = &*ctx;
 
-    let filter = CidFilter::MultihashCodeFilter(CodeFilter::Eq(BLAKE3))
+    // blobs are indexed by blake3 and can also be found by their sha256
+    let filter = (CidFilter::MultihashCodeFilter(CodeFilter::Eq(BLAKE3))
         & (CidFilter::CodecFilter(CodeFilter::Eq(RAW))
-            | CidFilter::CodecFilter(CodeFilter::Eq(BLAKE3_HASHSEQ)));
+            | CidFilter::CodecFilter(CodeFilter::Eq(BLAKE3_HASHSEQ))))
+        | (CidFilter::MultihashCodeFilter(CodeFilter::Eq(SHA256))
+            & CidFilter::CodecFilter(CodeFilter::Eq(RAW)));
 
     let filter = serde_json::to_value(filter)?;
 
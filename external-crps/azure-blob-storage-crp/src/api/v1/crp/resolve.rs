@@ -0,0 +1,123 @@
+use std::sync::Arc;
+
+use api_utils::{ApiError, ApiResult, ErrorCode};
+use axum::{extract::State, Json};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::{api::v1::crp::routes::get_blob_route, context::Context};
+
+#[derive(Deserialize, ToSchema)]
+pub struct CrpResolveRequest {
+    /// Storage URL of the blob, `https://{account}.blob.core.windows.net/{container}/{name}`
+    url: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct CrpResolveResponse {
+    cid: String,
+    route: routes::Route,
+}
+
+/// Resolve CRP URL
+///
+/// CID and route of the blob at a storage URL. A blob in a configured container that the indexer
+/// hasn't listed yet is indexed now, and a blob that isn't hashed yet is hashed before responding.
+/// Hashing carries on if the request is dropped, so the blob is hashed by the time it's retried.
+#[utoipa::path(
+    post,
+    path = "/v1/crp/resolve",
+    tag = "/v1/crp/resolve",
+    request_body = CrpResolveRequest,
+    responses(
+        (status = 200, description = "Get a blob's CID and route by its URL", body = CrpResolveResponse),
+        (status = 400, description = "Not an azure blob storage URL, or its container isn't configured", body = ApiErrorBody),
+        (status = 404, description = "Blob not found, filtered out, or its route was deleted", body = ApiErrorBody),
+        (status = 502, description = "Blob storage failed or the blob couldn't be hashed", body = ApiErrorBody)
+    )
+)]
+pub async fn post_resolve(
+    State(ctx): State<Arc<Context>>,
+    Json(request): Json<CrpResolveRequest>,
+) -> ApiResult<Json<CrpResolveResponse>> {
+    let Context {
+        db,
+        blob_storage_config,
+        ..
+    } = &*ctx;
+
+    let (blob_id, blob_info) = db
+        .routes_for_url(&request.url)
+        .map_err(|e| ApiError::new(ErrorCode::BadRequest, e.to_string()))?;
+
+    let blob_info = match blob_info {
+        Some(blob_info) => blob_info,
+        None => {
+            let container_config = blob_storage_config
+                .containers
+                .iter()
+                .find(|c| c.account == blob_id.account && c.container == blob_id.container)
+                .ok_or_else(|| {
+                    ApiError::new(
+                        ErrorCode::BadRequest,
+                        format!(
+                            "account={} container={} is not configured",
+                            blob_id.account, blob_id.container
+                        ),
+                    )
+                })?;
+
+            db.index_blob(&blob_id, &container_config.filter)
+                .await
+                .map_err(|e| {
+                    ApiError::new(
+                        ErrorCode::ProviderUnavailable,
+                        format!(
+                            "failed to get blob account={} container={} name={} from blob storage: {e}",
+                            blob_id.account, blob_id.container, blob_id.name
+                        ),
+                    )
+                })?
+                .ok_or_else(|| {
+                    ApiError::new(
+                        ErrorCode::NotFound,
+                        format!(
+                            "blob account={} container={} name={} not found, doesn't match the container's filter, or its route was deleted",
+                            blob_id.account, blob_id.container, blob_id.name
+                        ),
+                    )
+                })?
+        }
+    };
+
+    let blob_info = if blob_info.hash.is_some() {
+        blob_info
+    } else {
+        ctx.start_hashing(blob_id.clone(), blob_info);
+        ctx.wait_for_hashing(&blob_id).await;
+
+        db.get_blob_info(&blob_id)?.ok_or_else(|| {
+            ApiError::new(
+                ErrorCode::NotFound,
+                format!(
+                    "blob account={} container={} name={} is no longer indexed",
+                    blob_id.account, blob_id.container, blob_id.name
+                ),
+            )
+        })?
+    };
+
+    let Some(cid) = blob_info.cid() else {
+        return Err(ApiError::new(
+            ErrorCode::ProviderUnavailable,
+            format!(
+                "failed to hash blob account={} container={} name={}",
+                blob_id.account, blob_id.container, blob_id.name
+            ),
+        ));
+    };
+
+    let route = get_blob_route(db, blob_id, blob_info)?;
+
+    Ok(Json(CrpResolveResponse { cid, route }))
+}
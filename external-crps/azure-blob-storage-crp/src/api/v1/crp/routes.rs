@@ -1,34 +1,24 @@
-use std::sync::Arc;
+use std::{str::FromStr, sync::Arc};
 
 use anyhow::Result;
-use api_utils::ApiResult;
+use api_utils::{ApiError, ApiResult, ErrorCode};
 use axum::{
     extract::{Path, State},
     Json,
 };
+use cid::Cid;
 use routes::{AzureBlobStorageRouteMethod, IntoRoute};
 use serde::Serialize;
-use serde_json::{json, Value};
+use serde_json::json;
 use utoipa::ToSchema;
 
 use crate::{
     context::Context,
-    db::{BlobId, BlobInfo},
+    db::{BlobId, BlobInfo, Db},
 };
 #[derive(Serialize, ToSchema)]
 pub struct CrpGetRoutesResponse {
-    routes: Vec<Route>,
-}
-
-#[derive(Serialize, ToSchema)]
-pub struct Route {
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub crp_id: Option<String>,
-    #[serde(rename = "type")]
-    pub type_: String,
-    pub method: Value,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub metadata: Option<Value>,
+    routes: Vec<routes::Route>,
 }
 
 /// Get CID Routes
@@ -37,7 +27,8 @@ pub struct Route {
     path = "/v1/crp/routes/{cid}",
     tag = "/v1/crp/routes/{cid}",
     responses(
-        (status = 200, description = "Get CID Routes", body = CrpGetRoutesResponse)
+        (status = 200, description = "Get CID Routes", body = CrpGetRoutesResponse),
+        (status = 400, description = "Invalid CID", body = ApiErrorBody)
     )
 )]
 pub async fn get_routes(
@@ -46,59 +37,57 @@ pub async fn get_routes(
 ) -> ApiResult<Json<CrpGetRoutesResponse>> {
     let Context { db, .. } = &*ctx;
 
-    let routes = db
-        .get_blob_ids_and_infos_for_cid(cid)?
-        .into_iter()
-        .map(
-            |(
-                BlobId {
-                    account,
-                    container,
-                    name,
-                },
-                BlobInfo {
-                    timestamp,
-                    size,
-                    time_first_indexed,
-                    time_last_checked,
-                    ..
-                },
-            )| {
-                let method = AzureBlobStorageRouteMethod {
-                    account,
-                    container,
-                    name,
-                };
-                let metadata = json!({
-                    "timestamp": timestamp,
-                    "size": size,
-                    "time_first_indexed": time_first_indexed,
-                    "time_last_checked": time_last_checked,
-                });
+    let cid = Cid::from_str(&cid)
+        .map_err(|e| ApiError::new(ErrorCode::CidInvalid, format!("invalid cid={cid}: {e}")))?;
 
-                Ok(method.into_route(None, Some(metadata))?)
-            },
-        )
-        .collect::<Result<Vec<_>>>()?;
-    let routes = routes.into_iter().map(Into::into).collect();
+    let routes = get_routes_for_cid(db, &cid)?;
 
     Ok(Json(CrpGetRoutesResponse { routes }))
 }
 
-impl From<routes::Route> for Route {
-    fn from(route: routes::Route) -> Self {
-        let routes::Route {
-            crp_id,
-            type_,
-            method,
-            metadata,
-        } = route;
+/// Routes to the blobs and iroh collections with the CID's content
+pub fn get_routes_for_cid(db: &Db, cid: &Cid) -> Result<Vec<routes::Route>> {
+    db.get_blob_ids_and_infos_for_cid(cid.to_string())?
+        .into_iter()
+        .map(|(blob_id, blob_info)| get_blob_route(db, blob_id, blob_info))
+        .collect()
+}
+
+/// Route to a blob, with its info as metadata
+pub fn get_blob_route(db: &Db, blob_id: BlobId, blob_info: BlobInfo) -> Result<routes::Route> {
+    let media_type = db.get_blob_content_type(&blob_id)?;
+    let sample = db.get_blob_sample(&blob_id)?;
+
+    let BlobId {
+        account,
+        container,
+        name,
+    } = blob_id;
+    let BlobInfo {
+        timestamp,
+        size,
+        time_first_indexed,
+        time_last_checked,
+        ..
+    } = blob_info;
 
-        Self {
-            crp_id,
-            type_,
-            method,
-            metadata,
-        }
+    let method = AzureBlobStorageRouteMethod {
+        account,
+        container,
+        name,
+    };
+    let mut metadata = json!({
+        "timestamp": timestamp,
+        "size": size,
+        "time_first_indexed": time_first_indexed,
+        "time_last_checked": time_last_checked,
+    });
+    if let Some(media_type) = media_type {
+        metadata["media_type"] = json!(media_type);
     }
+    if let Some(sample) = sample {
+        metadata["sample_fingerprint"] = json!(hex::encode(sample));
+    }
+
+    Ok(method.into_route(None, Some(metadata))?)
 }
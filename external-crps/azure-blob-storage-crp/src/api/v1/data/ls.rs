@@ -0,0 +1,70 @@
+use std::{str::FromStr, sync::Arc};
+
+use api_utils::{ApiError, ApiResult, ErrorCode};
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use cid::Cid;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::{
+    context::Context,
+    db::{BlobId, CollectionChild},
+};
+
+#[derive(Serialize, ToSchema)]
+pub struct LsResponse {
+    cid: String,
+    account: String,
+    container: String,
+    /// Directory the collection was built from
+    path: String,
+    children: Vec<CollectionChild>,
+}
+
+/// List Collection
+///
+/// Names, CIDs and sizes of the blobs in an iroh collection, so a directory can be browsed
+/// without downloading it.
+#[utoipa::path(
+    get,
+    path = "/v1/data/{cid}/ls",
+    tag = "/v1/data/{cid}/ls",
+    responses(
+        (status = 200, description = "Get the collection's children", body = LsResponse),
+        (status = 400, description = "Invalid CID", body = ApiErrorBody),
+        (status = 404, description = "CID isn't an indexed collection", body = ApiErrorBody)
+    )
+)]
+pub async fn get_ls(
+    Path(cid): Path<String>,
+    State(ctx): State<Arc<Context>>,
+) -> ApiResult<Json<LsResponse>> {
+    let Context { db, .. } = &*ctx;
+
+    let cid = Cid::from_str(&cid)
+        .map_err(|e| ApiError::new(ErrorCode::CidInvalid, format!("invalid cid={cid}: {e}")))?;
+
+    let Some((collection_id, children)) = db.get_collection_children(&cid)? else {
+        return Err(ApiError::new(
+            ErrorCode::NotFound,
+            format!("cid={cid} isn't an indexed collection"),
+        ));
+    };
+
+    let BlobId {
+        account,
+        container,
+        name: path,
+    } = collection_id;
+
+    Ok(Json(LsResponse {
+        cid: cid.to_string(),
+        account,
+        container,
+        path,
+        children,
+    }))
+}
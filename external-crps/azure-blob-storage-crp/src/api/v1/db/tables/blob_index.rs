@@ -1,23 +1,48 @@
 use std::sync::Arc;
 
-use api_utils::ApiResult;
-use axum::extract::State;
+use api_utils::{ApiError, ApiResult, ErrorCode};
+use axum::extract::{Query, State};
 
-use crate::context::Context;
+use crate::{context::Context, db::BlobEntryFilter};
 
 /// Get Blob Index Table
+///
+/// Entries can be filtered by container, size and the time they were first indexed.
 #[utoipa::path(
     get,
     path = "/v1/db/tables/blob-index",
     tag = "/v1/db/tables/blob-index",
+    params(BlobEntryFilter),
     responses(
-        (status = 200, description = "Get Blob Index Table", body = BlobIndexTableResponse)
+        (status = 200, description = "Get Blob Index Table", body = BlobIndexTableResponse),
+        (status = 400, description = "Empty size or time range", body = ApiErrorBody)
     )
 )]
-pub async fn get_blob_index_table(State(ctx): State<Arc<Context>>) -> ApiResult<String> {
+pub async fn get_blob_index_table(
+    Query(filter): Query<BlobEntryFilter>,
+    State(ctx): State<Arc<Context>>,
+) -> ApiResult<String> {
     let Context { db, .. } = &*ctx;
 
-    let table = db.get_all_blob_entries_ascii_table()?;
+    if let (Some(min_size), Some(max_size)) = (filter.min_size, filter.max_size) {
+        if min_size > max_size {
+            return Err(ApiError::new(
+                ErrorCode::BadRequest,
+                format!("min_size={min_size} is greater than max_size={max_size}"),
+            ));
+        }
+    }
+
+    if let (Some(after), Some(before)) = (filter.indexed_after, filter.indexed_before) {
+        if after >= before {
+            return Err(ApiError::new(
+                ErrorCode::BadRequest,
+                format!("indexed_after={after} isn't before indexed_before={before}"),
+            ));
+        }
+    }
+
+    let table = db.get_blob_entries_ascii_table(&filter)?;
 
     Ok(table)
 }
@@ -0,0 +1,72 @@
+use std::sync::Arc;
+
+use api_utils::{ApiError, ApiResult, ErrorCode};
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{context::Context, events::RouteEvent};
+
+/// Number of events returned when no limit is given
+const DEFAULT_LIMIT: usize = 100;
+
+/// Most events returned at once
+const MAX_LIMIT: usize = 1000;
+
+#[derive(Deserialize, IntoParams)]
+pub struct EventsQuery {
+    /// ID of the first event to return (defaults to the oldest retained event)
+    from: Option<u64>,
+    /// Number of events to return (defaults to 100, at most 1000)
+    limit: Option<usize>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct EventsResponse {
+    events: Vec<RouteEvent>,
+    /// ID to get the next page from, which is also where new events start once this is the last
+    /// page
+    next_from: u64,
+}
+
+/// Get Route Events
+///
+/// Route events in the order they happened, oldest first.
+#[utoipa::path(
+    get,
+    path = "/v1/events",
+    tag = "/v1/events",
+    params(EventsQuery),
+    responses(
+        (status = 200, description = "Get route events", body = EventsResponse),
+        (status = 400, description = "Limit out of range", body = ApiErrorBody)
+    )
+)]
+pub async fn get_events(
+    Query(query): Query<EventsQuery>,
+    State(ctx): State<Arc<Context>>,
+) -> ApiResult<Json<EventsResponse>> {
+    let Context { db, .. } = &*ctx;
+
+    let from = query.from.unwrap_or(0);
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT);
+
+    if limit == 0 || limit > MAX_LIMIT {
+        return Err(ApiError::new(
+            ErrorCode::BadRequest,
+            format!("limit={limit} must be between 1 and {MAX_LIMIT}"),
+        ));
+    }
+
+    let events = db.get_events(from, limit)?;
+
+    let next_from = match events.last() {
+        Some(event) => event.id + 1,
+        None => db.get_next_event_id()?.max(from),
+    };
+
+    Ok(Json(EventsResponse { events, next_from }))
+}
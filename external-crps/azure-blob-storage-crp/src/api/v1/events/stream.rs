@@ -0,0 +1,142 @@
+use std::{collections::VecDeque, convert::Infallible, sync::Arc};
+
+use api_utils::ApiResult;
+use axum::{
+    extract::{Query, State},
+    http::HeaderMap,
+    response::sse::{Event, KeepAlive, Sse},
+};
+use futures::{stream, Stream};
+use serde::Deserialize;
+use tokio::sync::broadcast::{self, error::RecvError};
+use utoipa::IntoParams;
+
+use crate::{context::Context, events::RouteEvent};
+
+/// Most events read from the event table at once
+const STREAM_BATCH_SIZE: usize = 100;
+
+#[derive(Deserialize, IntoParams)]
+pub struct EventsStreamQuery {
+    /// Only events for blobs in this storage account
+    account: Option<String>,
+    /// Only events for blobs in this container
+    container: Option<String>,
+    /// Only events for blobs whose CID starts with this, which leaves out `inserted` events as
+    /// blobs have no CID until they're hashed
+    cid_prefix: Option<String>,
+}
+
+impl EventsStreamQuery {
+    fn matches(&self, event: &RouteEvent) -> bool {
+        let Self {
+            account,
+            container,
+            cid_prefix,
+        } = self;
+
+        account.as_ref().map_or(true, |a| *a == event.account)
+            && container.as_ref().map_or(true, |c| *c == event.container)
+            && cid_prefix.as_ref().map_or(true, |prefix| {
+                event
+                    .cid
+                    .as_ref()
+                    .is_some_and(|cid| cid.starts_with(prefix.as_str()))
+            })
+    }
+}
+
+struct StreamState {
+    ctx: Arc<Context>,
+    query: EventsStreamQuery,
+    new_events: broadcast::Receiver<RouteEvent>,
+    /// ID of the next event to read from the event table
+    cursor: u64,
+    pending: VecDeque<RouteEvent>,
+}
+
+/// Stream Route Events
+///
+/// Server-sent events of route changes as they happen, each with the event's ID as its `id` and
+/// its kind as its `event`. A client reconnecting with a `Last-Event-ID` header gets the retained
+/// events it missed first.
+#[utoipa::path(
+    get,
+    path = "/v1/events/stream",
+    tag = "/v1/events/stream",
+    params(EventsStreamQuery),
+    responses(
+        (status = 200, description = "Stream of route events", content_type = "text/event-stream", body = RouteEvent)
+    )
+)]
+pub async fn get_events_stream(
+    Query(query): Query<EventsStreamQuery>,
+    headers: HeaderMap,
+    State(ctx): State<Arc<Context>>,
+) -> ApiResult<Sse<impl Stream<Item = Result<Event, Infallible>>>> {
+    // subscribed before reading the cursor so no event is missed between the two
+    let new_events = ctx.db.subscribe_events();
+
+    let last_event_id = headers
+        .get("last-event-id")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<u64>().ok());
+
+    let cursor = match last_event_id {
+        Some(id) => id + 1,
+        None => ctx.db.get_next_event_id()?,
+    };
+
+    let state = StreamState {
+        ctx,
+        query,
+        new_events,
+        cursor,
+        pending: VecDeque::new(),
+    };
+
+    Ok(Sse::new(stream::unfold(state, next_event)).keep_alive(KeepAlive::default()))
+}
+
+/// Next event matching the stream's query, reading events from the event table rather than the
+/// channel so a slow client doesn't miss any still retained
+async fn next_event(mut state: StreamState) -> Option<(Result<Event, Infallible>, StreamState)> {
+    loop {
+        if let Some(event) = state.pending.pop_front() {
+            let sse_event = Event::default()
+                .id(event.id.to_string())
+                .event(event.kind.as_str());
+
+            let sse_event = match sse_event.json_data(&event) {
+                Ok(sse_event) => sse_event,
+                Err(e) => {
+                    log::error!("Error serializing route event={}: {e}", event.id);
+                    continue;
+                }
+            };
+
+            return Some((Ok(sse_event), state));
+        }
+
+        let batch = match state.ctx.db.get_events(state.cursor, STREAM_BATCH_SIZE) {
+            Ok(batch) => batch,
+            Err(e) => {
+                log::error!("Error reading route events, ending stream: {e:?}");
+                return None;
+            }
+        };
+
+        let Some(last) = batch.last().map(|event| event.id) else {
+            match state.new_events.recv().await {
+                Ok(_) | Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return None,
+            }
+        };
+
+        state.cursor = last + 1;
+        state.pending = batch
+            .into_iter()
+            .filter(|event| state.query.matches(event))
+            .collect();
+    }
+}
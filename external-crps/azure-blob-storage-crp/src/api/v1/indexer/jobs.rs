@@ -0,0 +1,53 @@
+use std::sync::Arc;
+
+use api_utils::{ApiError, ApiResult, ErrorCode};
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::{context::Context, db::Job};
+
+#[derive(Serialize, ToSchema)]
+pub struct IndexerJobsResponse {
+    jobs: Vec<Job>,
+}
+
+/// Get Indexer Jobs
+#[utoipa::path(
+    get,
+    path = "/v1/indexer/jobs",
+    tag = "/v1/indexer/jobs",
+    responses(
+        (status = 200, description = "Get indexer jobs, most recent first", body = IndexerJobsResponse)
+    )
+)]
+pub async fn get_jobs(State(ctx): State<Arc<Context>>) -> ApiResult<Json<IndexerJobsResponse>> {
+    let Context { db, .. } = &*ctx;
+
+    let jobs = db.get_jobs()?;
+
+    Ok(Json(IndexerJobsResponse { jobs }))
+}
+
+/// Get Indexer Job
+#[utoipa::path(
+    get,
+    path = "/v1/indexer/jobs/{id}",
+    tag = "/v1/indexer/jobs/{id}",
+    responses(
+        (status = 200, description = "Get an indexer job", body = Job),
+        (status = 404, description = "Job not found", body = ApiErrorBody)
+    )
+)]
+pub async fn get_job(Path(id): Path<u64>, State(ctx): State<Arc<Context>>) -> ApiResult<Json<Job>> {
+    let Context { db, .. } = &*ctx;
+
+    let job = db
+        .get_job(id)?
+        .ok_or_else(|| ApiError::new(ErrorCode::NotFound, format!("job id={id} not found")))?;
+
+    Ok(Json(job))
+}
@@ -0,0 +1,31 @@
+use std::sync::Arc;
+
+use api_utils::{ApiError, ApiResult, ErrorCode};
+use axum::{extract::State, Json};
+
+use crate::{context::Context, db::ShutdownReport};
+
+/// Get Shutdown Report
+///
+/// Jobs completed and aborted by the process that last shut down cleanly. Jobs found still running
+/// on startup instead are marked as interrupted.
+#[utoipa::path(
+    get,
+    path = "/v1/indexer/shutdown-report",
+    tag = "/v1/indexer/shutdown-report",
+    responses(
+        (status = 200, description = "Get the report of the last shutdown", body = ShutdownReport),
+        (status = 404, description = "No shutdown recorded", body = ApiErrorBody)
+    )
+)]
+pub async fn get_shutdown_report(
+    State(ctx): State<Arc<Context>>,
+) -> ApiResult<Json<ShutdownReport>> {
+    let Context { db, .. } = &*ctx;
+
+    let report = db
+        .get_last_shutdown_report()?
+        .ok_or_else(|| ApiError::new(ErrorCode::NotFound, "no shutdown recorded"))?;
+
+    Ok(Json(report))
+}
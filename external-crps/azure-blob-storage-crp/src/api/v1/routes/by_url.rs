@@ -0,0 +1,117 @@
+use std::sync::Arc;
+
+use api_utils::{ApiError, ApiResult, ErrorCode};
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{api::v1::crp::routes::get_blob_route, context::Context, db::BlobId};
+
+#[derive(Deserialize, IntoParams)]
+pub struct ByUrlQuery {
+    /// Storage URL of the blob, `https://{account}.blob.core.windows.net/{container}/{name}`
+    url: String,
+    /// Start hashing the blob now if it's indexed but not hashed yet (defaults to false)
+    hash: Option<bool>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ByUrlResponse {
+    account: String,
+    container: String,
+    name: String,
+    state: BlobHashState,
+    /// CID of the blob's content, once it's hashed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cid: Option<String>,
+    /// Route to the blob, once it's hashed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    route: Option<routes::Route>,
+}
+
+#[derive(Clone, Copy, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BlobHashState {
+    Hashed,
+    /// Listed by the indexer but not hashed yet
+    Unhashed,
+    /// Being hashed on request
+    Hashing,
+}
+
+/// Get Route By URL
+///
+/// CID and route of the blob at a storage URL, if it's indexed. With `hash=true` a blob that's
+/// indexed but not hashed yet is hashed in the background, responding 202 until it's done.
+#[utoipa::path(
+    get,
+    path = "/v1/routes/by-url",
+    tag = "/v1/routes/by-url",
+    params(ByUrlQuery),
+    responses(
+        (status = 200, description = "Get a blob's CID and route by its URL", body = ByUrlResponse),
+        (status = 202, description = "Blob is being hashed", body = ByUrlResponse),
+        (status = 400, description = "Not an azure blob storage URL", body = ApiErrorBody),
+        (status = 404, description = "Blob not indexed", body = ApiErrorBody)
+    )
+)]
+pub async fn get_route_by_url(
+    Query(query): Query<ByUrlQuery>,
+    State(ctx): State<Arc<Context>>,
+) -> ApiResult<(StatusCode, Json<ByUrlResponse>)> {
+    let Context { db, .. } = &*ctx;
+
+    let (blob_id, blob_info) = db
+        .routes_for_url(&query.url)
+        .map_err(|e| ApiError::new(ErrorCode::BadRequest, e.to_string()))?;
+
+    let Some(blob_info) = blob_info else {
+        return Err(ApiError::new(
+            ErrorCode::NotFound,
+            format!(
+                "blob account={} container={} name={} not indexed",
+                blob_id.account, blob_id.container, blob_id.name
+            ),
+        ));
+    };
+
+    let (status, state) = if blob_info.hash.is_some() {
+        (StatusCode::OK, BlobHashState::Hashed)
+    } else if ctx.is_hashing(&blob_id) {
+        (StatusCode::ACCEPTED, BlobHashState::Hashing)
+    } else if query.hash.unwrap_or(false) {
+        ctx.start_hashing(blob_id.clone(), blob_info.clone());
+        (StatusCode::ACCEPTED, BlobHashState::Hashing)
+    } else {
+        (StatusCode::OK, BlobHashState::Unhashed)
+    };
+
+    let cid = blob_info.cid();
+
+    let route = match cid {
+        Some(_) => Some(get_blob_route(db, blob_id.clone(), blob_info)?),
+        None => None,
+    };
+
+    let BlobId {
+        account,
+        container,
+        name,
+    } = blob_id;
+
+    Ok((
+        status,
+        Json(ByUrlResponse {
+            account,
+            container,
+            name,
+            state,
+            cid,
+            route,
+        }),
+    ))
+}
@@ -0,0 +1,112 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use api_utils::{ApiError, ApiResult, ErrorCode};
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{api::v1::crp::routes::get_blob_route, context::Context, db::DuplicateBlobs};
+
+/// Number of groups returned when no limit is given
+const DEFAULT_LIMIT: usize = 100;
+
+/// Most groups returned at once
+const MAX_LIMIT: usize = 1000;
+
+#[derive(Deserialize, IntoParams)]
+pub struct DuplicatesQuery {
+    /// Number of groups to skip (defaults to 0)
+    offset: Option<usize>,
+    /// Number of groups to return (defaults to 100, at most 1000)
+    limit: Option<usize>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct DuplicatesResponse {
+    groups: Vec<DuplicateGroup>,
+    /// Number of groups in total
+    total: usize,
+    /// Offset of the next page, unset on the last page
+    #[serde(skip_serializing_if = "Option::is_none")]
+    next_offset: Option<usize>,
+}
+
+/// Routes to blobs with the same content
+#[derive(Serialize, ToSchema)]
+pub struct DuplicateGroup {
+    cid: String,
+    /// Size of the content, in bytes
+    size: u64,
+    /// Bytes taken by all but one copy of the content
+    redundant_bytes: u64,
+    routes: Vec<routes::Route>,
+}
+
+/// Get Duplicate Routes
+///
+/// Groups of routes to blobs with the same content, most redundant bytes first.
+#[utoipa::path(
+    get,
+    path = "/v1/routes/duplicates",
+    tag = "/v1/routes/duplicates",
+    params(DuplicatesQuery),
+    responses(
+        (status = 200, description = "Get groups of routes sharing a CID", body = DuplicatesResponse),
+        (status = 400, description = "Limit out of range", body = ApiErrorBody)
+    )
+)]
+pub async fn get_duplicates(
+    Query(query): Query<DuplicatesQuery>,
+    State(ctx): State<Arc<Context>>,
+) -> ApiResult<Json<DuplicatesResponse>> {
+    let Context { db, .. } = &*ctx;
+
+    let offset = query.offset.unwrap_or(0);
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT);
+
+    if limit == 0 || limit > MAX_LIMIT {
+        return Err(ApiError::new(
+            ErrorCode::BadRequest,
+            format!("limit={limit} must be between 1 and {MAX_LIMIT}"),
+        ));
+    }
+
+    let duplicates = db.get_duplicate_blobs()?;
+
+    let total = duplicates.len();
+
+    let groups = duplicates
+        .into_iter()
+        .skip(offset)
+        .take(limit)
+        .map(|duplicates| {
+            let redundant_bytes = duplicates.redundant_bytes();
+
+            let DuplicateBlobs { cid, size, blobs } = duplicates;
+
+            let routes = blobs
+                .into_iter()
+                .map(|(blob_id, blob_info)| get_blob_route(db, blob_id, blob_info))
+                .collect::<Result<Vec<_>>>()?;
+
+            Ok(DuplicateGroup {
+                cid,
+                size,
+                redundant_bytes,
+                routes,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let next_offset = Some(offset.saturating_add(limit)).filter(|next| *next < total);
+
+    Ok(Json(DuplicatesResponse {
+        groups,
+        total,
+        next_offset,
+    }))
+}
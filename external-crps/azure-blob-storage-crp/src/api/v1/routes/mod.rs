@@ -0,0 +1,3 @@
+pub mod by_url;
+pub mod duplicates;
+pub mod tombstones;
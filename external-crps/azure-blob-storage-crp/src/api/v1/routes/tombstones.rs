@@ -0,0 +1,148 @@
+use std::sync::Arc;
+
+use api_utils::{ApiError, ApiResult, ErrorCode};
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::{
+    context::Context,
+    db::{BlobId, Tombstone},
+};
+
+#[derive(Serialize, ToSchema)]
+pub struct DeleteRoutesResponse {
+    /// Number of blobs whose routes were deleted and tombstoned
+    tombstoned: u64,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct TombstonesResponse {
+    tombstones: Vec<Tombstone>,
+}
+
+/// Delete Route
+///
+/// Delete the route to a blob and tombstone it, so reindexing doesn't add it back until the blob
+/// changes.
+#[utoipa::path(
+    delete,
+    path = "/v1/routes/{account}/{container}/{name}",
+    tag = "/v1/routes/{account}/{container}/{name}",
+    responses(
+        (status = 200, description = "Delete and tombstone a blob's route", body = DeleteRoutesResponse),
+        (status = 401, description = "Missing or invalid admin API key", body = ApiErrorBody),
+        (status = 404, description = "Blob not indexed", body = ApiErrorBody)
+    )
+)]
+pub async fn delete_route(
+    Path((account, container, name)): Path<(String, String, String)>,
+    State(ctx): State<Arc<Context>>,
+) -> ApiResult<Json<DeleteRoutesResponse>> {
+    let Context { db, .. } = &*ctx;
+
+    let blob_id = BlobId {
+        account,
+        container,
+        name,
+    };
+
+    if !db.tombstone_blob(&blob_id)? {
+        return Err(ApiError::new(
+            ErrorCode::NotFound,
+            format!(
+                "blob account={} container={} name={} not indexed",
+                blob_id.account, blob_id.container, blob_id.name
+            ),
+        ));
+    }
+
+    Ok(Json(DeleteRoutesResponse { tombstoned: 1 }))
+}
+
+/// Delete Container Routes
+///
+/// Delete the routes to every blob indexed in a container and tombstone them, so reindexing
+/// doesn't add them back until they change. New blobs in the container are still indexed; remove
+/// the container from the config to stop indexing it altogether.
+#[utoipa::path(
+    delete,
+    path = "/v1/routes/{account}/{container}",
+    tag = "/v1/routes/{account}/{container}",
+    responses(
+        (status = 200, description = "Delete and tombstone the routes of a container's blobs", body = DeleteRoutesResponse),
+        (status = 401, description = "Missing or invalid admin API key", body = ApiErrorBody)
+    )
+)]
+pub async fn delete_container_routes(
+    Path((account, container)): Path<(String, String)>,
+    State(ctx): State<Arc<Context>>,
+) -> ApiResult<Json<DeleteRoutesResponse>> {
+    let Context { db, .. } = &*ctx;
+
+    let tombstoned = db.tombstone_container(&account, &container)?;
+
+    Ok(Json(DeleteRoutesResponse { tombstoned }))
+}
+
+/// Get Tombstones
+#[utoipa::path(
+    get,
+    path = "/v1/routes/tombstones",
+    tag = "/v1/routes/tombstones",
+    responses(
+        (status = 200, description = "Get blobs whose routes were deleted", body = TombstonesResponse)
+    )
+)]
+pub async fn get_tombstones(
+    State(ctx): State<Arc<Context>>,
+) -> ApiResult<Json<TombstonesResponse>> {
+    let Context { db, .. } = &*ctx;
+
+    let tombstones = db.get_tombstones()?;
+
+    Ok(Json(TombstonesResponse { tombstones }))
+}
+
+/// Delete Tombstone
+///
+/// Remove a blob's tombstone, so the next indexer pass indexes it again.
+#[utoipa::path(
+    delete,
+    path = "/v1/routes/tombstones/{account}/{container}/{name}",
+    tag = "/v1/routes/tombstones/{account}/{container}/{name}",
+    responses(
+        (status = 200, description = "Remove a blob's tombstone", body = Tombstone),
+        (status = 401, description = "Missing or invalid admin API key", body = ApiErrorBody),
+        (status = 404, description = "Blob not tombstoned", body = ApiErrorBody)
+    )
+)]
+pub async fn delete_tombstone(
+    Path((account, container, name)): Path<(String, String, String)>,
+    State(ctx): State<Arc<Context>>,
+) -> ApiResult<Json<Tombstone>> {
+    let Context { db, .. } = &*ctx;
+
+    let blob_id = BlobId {
+        account,
+        container,
+        name,
+    };
+
+    let tombstone = db.get_tombstone(&blob_id)?.ok_or_else(|| {
+        ApiError::new(
+            ErrorCode::NotFound,
+            format!(
+                "blob account={} container={} name={} not tombstoned",
+                blob_id.account, blob_id.container, blob_id.name
+            ),
+        )
+    })?;
+
+    db.remove_tombstone(&blob_id)?;
+
+    Ok(Json(tombstone))
+}
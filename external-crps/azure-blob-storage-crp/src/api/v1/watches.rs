@@ -0,0 +1,108 @@
+use std::{str::FromStr, sync::Arc};
+
+use api_utils::{ApiError, ApiResult, ErrorCode};
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use cid::Cid;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::{context::Context, db::Watch};
+
+#[derive(Deserialize, ToSchema)]
+pub struct CreateWatchRequest {
+    cid: String,
+    /// Unix timestamp by which the CID is expected to have a route, a `watch_expired` event is
+    /// recorded if it doesn't (waits indefinitely if unset)
+    deadline: Option<i64>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct WatchesResponse {
+    watches: Vec<Watch>,
+}
+
+/// Create Watch
+///
+/// Watch a CID expected to become available, e.g. an artifact a pipeline is producing. Watches
+/// are checked after every indexer pass, which records a `watch_found` route event once the CID
+/// has a route and stops watching it, or a `watch_expired` event if the deadline passes first.
+/// Watching a CID again replaces its watch.
+#[utoipa::path(
+    post,
+    path = "/v1/watches",
+    tag = "/v1/watches",
+    request_body = CreateWatchRequest,
+    responses(
+        (status = 200, description = "Watch a CID", body = Watch),
+        (status = 400, description = "Invalid CID", body = ApiErrorBody)
+    )
+)]
+pub async fn post_watch(
+    State(ctx): State<Arc<Context>>,
+    Json(request): Json<CreateWatchRequest>,
+) -> ApiResult<Json<Watch>> {
+    let Context { db, .. } = &*ctx;
+
+    let CreateWatchRequest { cid, deadline } = request;
+
+    let cid = Cid::from_str(&cid)
+        .map_err(|e| ApiError::new(ErrorCode::CidInvalid, format!("invalid cid={cid}: {e}")))?;
+
+    let watch = db.insert_watch(&cid, deadline)?;
+
+    Ok(Json(watch))
+}
+
+/// Get Watches
+///
+/// CIDs watched until they get a route.
+#[utoipa::path(
+    get,
+    path = "/v1/watches",
+    tag = "/v1/watches",
+    responses(
+        (status = 200, description = "Get watched CIDs", body = WatchesResponse)
+    )
+)]
+pub async fn get_watches(State(ctx): State<Arc<Context>>) -> ApiResult<Json<WatchesResponse>> {
+    let Context { db, .. } = &*ctx;
+
+    let watches = db.get_watches()?;
+
+    Ok(Json(WatchesResponse { watches }))
+}
+
+/// Delete Watch
+///
+/// Stop watching a CID, without recording an event.
+#[utoipa::path(
+    delete,
+    path = "/v1/watches/{cid}",
+    tag = "/v1/watches/{cid}",
+    responses(
+        (status = 200, description = "Stop watching a CID"),
+        (status = 400, description = "Invalid CID", body = ApiErrorBody),
+        (status = 404, description = "CID not watched", body = ApiErrorBody)
+    )
+)]
+pub async fn delete_watch(
+    Path(cid): Path<String>,
+    State(ctx): State<Arc<Context>>,
+) -> ApiResult<()> {
+    let Context { db, .. } = &*ctx;
+
+    let cid = Cid::from_str(&cid)
+        .map_err(|e| ApiError::new(ErrorCode::CidInvalid, format!("invalid cid={cid}: {e}")))?;
+
+    if !db.remove_watch(&cid)? {
+        return Err(ApiError::new(
+            ErrorCode::NotFound,
+            format!("cid={cid} not watched"),
+        ));
+    }
+
+    Ok(())
+}
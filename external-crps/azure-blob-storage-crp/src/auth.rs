@@ -0,0 +1,103 @@
+use std::sync::Arc;
+
+use api_utils::{ApiError, ApiResult, ErrorCode};
+use axum::{
+    extract::State,
+    http::{header::AUTHORIZATION, HeaderMap, Request},
+    middleware::Next,
+    response::Response,
+};
+use sha2::{Digest, Sha256};
+
+use crate::{config::AdminApiKeyConfig, context::Context};
+
+/// API keys for admin endpoints. While there are none the admin endpoints are closed, unless
+/// they're configured to be open.
+#[derive(Debug, Default)]
+pub struct AdminKeys {
+    keys: Vec<AdminApiKeyConfig>,
+    /// Whether admin endpoints are open while there are no keys
+    open_without_keys: bool,
+}
+
+impl AdminKeys {
+    pub fn new(keys: Vec<AdminApiKeyConfig>, open_without_keys: bool) -> Self {
+        Self {
+            keys,
+            open_without_keys,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Check a request's bearer token is an admin key
+    pub fn authorize(&self, token: Option<&str>) -> ApiResult<()> {
+        if self.keys.is_empty() {
+            if self.open_without_keys {
+                return Ok(());
+            }
+
+            return Err(ApiError::new(
+                ErrorCode::Unauthorized,
+                "no admin api keys are configured, admin endpoints are closed",
+            ));
+        }
+
+        let key_sha256 = token.map(hash_key).unwrap_or_default();
+
+        // every key is compared, so how long this takes doesn't tell which key's hash is closest
+        let is_key = self.keys.iter().fold(false, |found, k| {
+            let matches = constant_time_eq(
+                k.key_sha256.to_lowercase().as_bytes(),
+                key_sha256.as_bytes(),
+            );
+            found | matches
+        });
+
+        if !is_key {
+            return Err(ApiError::new(
+                ErrorCode::Unauthorized,
+                "missing or invalid admin api key",
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Token from a request's `Authorization: Bearer` header
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+}
+
+/// Hex sha256 of an API key, which is what's kept in the config
+fn hash_key(key: &str) -> String {
+    hex::encode(Sha256::digest(key.as_bytes()))
+}
+
+/// Whether two byte strings are equal, taking the same time wherever they differ
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+
+    let diff = a.iter().zip(b).fold(0u8, |diff, (a, b)| diff | (a ^ b));
+
+    std::hint::black_box(diff) == 0
+}
+
+/// Middleware rejecting requests without an admin API key, as an `Authorization: Bearer` header
+pub async fn require_admin<B>(
+    State(ctx): State<Arc<Context>>,
+    request: Request<B>,
+    next: Next<B>,
+) -> ApiResult<Response> {
+    ctx.admin_keys.authorize(bearer_token(request.headers()))?;
+
+    Ok(next.run(request).await)
+}
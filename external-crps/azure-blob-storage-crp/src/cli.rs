@@ -15,6 +15,9 @@ pub struct Args {
 #[derive(Debug, Clone, Parser)]
 pub enum Subcommand {
     Start(Start),
+    Routes(Routes),
+    Simulate(Simulate),
+    Stats(Stats),
 }
 
 /// Start service
@@ -24,6 +27,79 @@ pub struct Start {
     pub common_args: CommonArgs,
 }
 
+/// Query the routes in the DB directly, while the service isn't running
+#[derive(Debug, Clone, Parser)]
+pub struct Routes {
+    #[clap(flatten)]
+    pub common_args: CommonArgs,
+
+    #[clap(subcommand)]
+    pub cmd: RoutesSubcommand,
+}
+
+/// Route queries
+#[derive(Debug, Clone, Parser)]
+pub enum RoutesSubcommand {
+    List(RoutesList),
+    Get(RoutesGet),
+    ForCid(RoutesForCid),
+    Export(RoutesExport),
+}
+
+/// Print a table of the indexed blobs and iroh collections
+#[derive(Debug, Clone, Parser)]
+pub struct RoutesList {}
+
+/// Print the route to a blob as JSON
+#[derive(Debug, Clone, Parser)]
+pub struct RoutesGet {
+    pub account: String,
+    pub container: String,
+    /// Blob name
+    pub name: String,
+}
+
+/// Print the routes for a CID as JSON, as returned by the routes endpoint
+#[derive(Debug, Clone, Parser)]
+pub struct RoutesForCid {
+    pub cid: String,
+}
+
+/// Print the CID and route of every hashed blob and iroh collection, as JSON lines
+#[derive(Debug, Clone, Parser)]
+pub struct RoutesExport {}
+
+/// Project indexing a container from a listing of its blobs
+///
+/// Reports the routes, bytes to hash, indexing time and DB size for the container's configured
+/// filter, with hashing throughput measured on this machine.
+#[derive(Debug, Clone, Parser)]
+pub struct Simulate {
+    #[clap(flatten)]
+    pub common_args: CommonArgs,
+
+    /// CSV of `name,size` lines, with an optional header line
+    #[clap(short, long)]
+    pub listing: PathBuf,
+
+    /// Configured container the listing is of, as `account/container` (defaults to the only
+    /// configured container)
+    #[clap(long)]
+    pub container: Option<String>,
+
+    /// Download throughput from blob storage in MB/s, if known, which limits how fast blobs are
+    /// hashed
+    #[clap(long)]
+    pub download_mbps: Option<f64>,
+}
+
+/// Print counts and sizes of what's indexed in the DB, while the service isn't running
+#[derive(Debug, Clone, Parser)]
+pub struct Stats {
+    #[clap(flatten)]
+    pub common_args: CommonArgs,
+}
+
 /// Common Args
 #[derive(Debug, Clone, Parser)]
 pub struct CommonArgs {
@@ -1,16 +1,79 @@
-use std::{fs, path::PathBuf};
+use std::{collections::HashMap, fs, path::PathBuf};
 
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 
+use crate::events::RouteEventKind;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
     pub port: u16,
     pub blob_storage: BlobStorageConfig,
     pub indexing_strategy: IndexingStrategy,
+    /// Unhashed blob index entries first indexed more than this many seconds ago are
+    /// periodically pruned if their blob is gone or keeps failing to hash (never pruned if unset)
+    pub stub_retention: Option<u64>,
+    /// Refuse to prune more than this percentage of a container's index entries in a single
+    /// indexing pass or stale stub prune (no limit if unset)
+    pub max_prune_percent: Option<u8>,
+    /// Maintenance task schedules by task name (`prune_stale_stubs`)
+    pub maintenance: Option<HashMap<String, MaintenanceTaskConfig>>,
     pub db_file: PathBuf,
+    /// Targets POSTed route events as blobs are indexed and removed, watched CIDs turn up or
+    /// expire, and prunes are refused
+    pub webhooks: Option<Vec<WebhookConfig>>,
     pub log_level_default: Option<String>,
     pub log_level_app: Option<String>,
+    /// Include internal callstacks in API error responses (defaults to true in debug builds only)
+    pub expose_callstacks: Option<bool>,
+    /// Proxy for blob storage and webhook requests (defaults to the `HTTP_PROXY`, `HTTPS_PROXY` and
+    /// `NO_PROXY` environment variables)
+    pub proxy: Option<ProxyConfig>,
+    /// How long in-flight requests are given to finish on SIGINT or SIGTERM, in milliseconds
+    /// (defaults to 30000)
+    pub shutdown_timeout_ms: Option<u64>,
+    /// API keys for the admin endpoints, which are closed while there are none unless
+    /// `open_admin_endpoints` is set
+    pub admin_api_keys: Option<Vec<AdminApiKeyConfig>>,
+    /// Leave the admin endpoints open to anyone while there are no admin API keys, e.g. for local
+    /// development (defaults to false)
+    pub open_admin_endpoints: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdminApiKeyConfig {
+    pub name: String,
+    /// Hex sha256 of the key, e.g. from `printf %s "$KEY" | sha256sum`
+    pub key_sha256: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MaintenanceTaskConfig {
+    /// Cron expression "minute hour day-of-month month day-of-week", in UTC
+    pub schedule: Option<String>,
+    /// Run the task on its schedule (`prune_stale_stubs` defaults to true if `stub_retention` is
+    /// set)
+    pub enabled: Option<bool>,
+}
+
+/// Outbound HTTP(S) proxy
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProxyConfig {
+    /// Proxy URL, e.g. "http://proxy.internal:3128"
+    pub url: String,
+    /// Comma separated hosts, domains and IP ranges to connect to directly, as in `NO_PROXY`
+    pub no_proxy: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookConfig {
+    /// URL POSTed batches of events, as `{"events": [...]}`
+    pub url: String,
+    /// Kinds of events sent (defaults to all)
+    pub events: Option<Vec<RouteEventKind>>,
+    /// Retry a failed delivery this many times before skipping its events (retried until it
+    /// succeeds if unset)
+    pub max_retries: Option<u32>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -30,6 +93,13 @@ pub struct ContainerConfig {
     pub account: String,
     pub container: String,
     pub filter: ContainerBlobFilter,
+    /// Overrides the top-level `indexing_strategy` for this container
+    pub indexing_strategy: Option<IndexingStrategy>,
+    /// Maximum number of blobs hashed in parallel (defaults to 4)
+    pub hashing_concurrency: Option<usize>,
+    /// Also compute sha256 hashes while indexing so blobs can be found by sha256 CIDs (defaults
+    /// to false)
+    pub sha256: Option<bool>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -1,38 +1,153 @@
-use std::sync::Arc;
+use std::{
+    collections::BTreeSet,
+    sync::{Arc, Mutex},
+};
 
 use anyhow::Result;
+use tokio::sync::Notify;
 
 use crate::{
-    config::{BlobStorageConfig, Config, IndexingStrategy},
-    db::Db,
+    auth::AdminKeys,
+    config::{BlobStorageConfig, Config, IndexingStrategy, ProxyConfig, WebhookConfig},
+    db::{BlobId, BlobInfo, Db},
+    scheduler::Scheduler,
 };
 
 pub struct Context {
     pub start_time: i64,
     pub port: u16,
     pub indexing_strategy: IndexingStrategy,
+    pub stub_retention: Option<u64>,
+    pub max_prune_percent: Option<u8>,
     pub blob_storage_config: BlobStorageConfig,
+    pub webhooks: Vec<WebhookConfig>,
+    pub proxy: Option<ProxyConfig>,
     pub db: Arc<Db>,
+    pub scheduler: Scheduler,
+    /// Keys admin endpoints require
+    pub admin_keys: AdminKeys,
+    /// Blobs being hashed on request, ahead of the indexer
+    pub hashing_on_demand: Mutex<BTreeSet<BlobId>>,
+    /// Notified each time a blob hashed on request is done
+    pub hashing_done: Notify,
 }
 
 impl Context {
     pub fn init(config: Config) -> Result<Self> {
         let start_time = chrono::Utc::now().timestamp();
 
+        let scheduler = Scheduler::init(&config)?;
+
         let port = config.port;
 
         let indexing_strategy = config.indexing_strategy;
 
+        let stub_retention = config.stub_retention;
+
+        let max_prune_percent = config.max_prune_percent;
+
         let blob_storage_config = config.blob_storage;
 
-        let db = Arc::new(Db::init(config.db_file)?);
+        let webhooks = config.webhooks.unwrap_or_default();
+
+        let proxy = config.proxy;
+
+        let db = Arc::new(Db::init(config.db_file, proxy.as_ref())?);
+
+        let open_admin_endpoints = config.open_admin_endpoints.unwrap_or(false);
+        let admin_keys = AdminKeys::new(
+            config.admin_api_keys.unwrap_or_default(),
+            open_admin_endpoints,
+        );
+        if admin_keys.is_empty() {
+            if open_admin_endpoints {
+                log::warn!("no admin api keys are configured, admin endpoints are open to anyone");
+            } else {
+                log::warn!("no admin api keys are configured, admin endpoints are closed");
+            }
+        }
 
         Ok(Self {
             start_time,
             port,
             indexing_strategy,
+            stub_retention,
+            max_prune_percent,
             blob_storage_config,
+            webhooks,
+            proxy,
             db,
+            scheduler,
+            admin_keys,
+            hashing_on_demand: Mutex::default(),
+            hashing_done: Notify::new(),
         })
     }
+
+    pub fn is_hashing(&self, blob_id: &BlobId) -> bool {
+        self.hashing_on_demand
+            .lock()
+            .expect("hashing on demand lock poisoned")
+            .contains(blob_id)
+    }
+
+    /// Hash a blob in the background, unless it's already being hashed on request
+    pub fn start_hashing(self: &Arc<Self>, blob_id: BlobId, blob_info: BlobInfo) {
+        let newly_hashing = self
+            .hashing_on_demand
+            .lock()
+            .expect("hashing on demand lock poisoned")
+            .insert(blob_id.clone());
+
+        if !newly_hashing {
+            return;
+        }
+
+        let ctx = self.clone();
+
+        tokio::spawn(async move {
+            let Context {
+                db,
+                blob_storage_config,
+                ..
+            } = &*ctx;
+
+            let sha256 = blob_storage_config
+                .containers
+                .iter()
+                .find(|c| c.account == blob_id.account && c.container == blob_id.container)
+                .and_then(|c| c.sha256)
+                .unwrap_or(false);
+
+            if let Err(e) = db.hash_blob(blob_id.clone(), blob_info, sha256).await {
+                log::error!(
+                    "Error hashing blob on request: account={} container={} name={}: {e:?}",
+                    blob_id.account,
+                    blob_id.container,
+                    blob_id.name
+                );
+            }
+
+            ctx.hashing_on_demand
+                .lock()
+                .expect("hashing on demand lock poisoned")
+                .remove(&blob_id);
+
+            ctx.hashing_done.notify_waiters();
+        });
+    }
+
+    /// Wait until a blob being hashed on request is done, whether or not hashing succeeded
+    pub async fn wait_for_hashing(&self, blob_id: &BlobId) {
+        loop {
+            // created before checking, so a notification in between isn't missed
+            let done = self.hashing_done.notified();
+
+            if !self.is_hashing(blob_id) {
+                return;
+            }
+
+            done.await;
+        }
+    }
 }
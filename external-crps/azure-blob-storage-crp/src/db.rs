@@ -1,21 +1,38 @@
-use std::{collections::HashMap, num::NonZeroU32, path::PathBuf};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    num::NonZeroU32,
+    path::PathBuf,
+    sync::Arc,
+};
 
-use anyhow::Result;
+use anyhow::{anyhow, bail, Result};
+use azure_core::{request_options::NextMarker, ClientOptions, TransportOptions};
 use azure_storage::prelude::*;
 use azure_storage_blobs::prelude::*;
 use cid::{multihash::Multihash, Cid};
+use cid_filter::table::{multicodec, multihash};
 use futures::StreamExt;
 use iroh_base::hash::Hash;
 use iroh_bytes::format::collection::Collection;
 use itertools::Itertools;
 use multimap::MultiMap;
-use redb::{MultimapTableDefinition, ReadableMultimapTable, ReadableTable, TableDefinition};
+use redb::{
+    MultimapTableDefinition, ReadableMultimapTable, ReadableTable, ReadableTableMetadata,
+    TableDefinition,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use tabled::{
     settings::{Alignment, Style},
     Table, Tabled,
 };
+use tokio::sync::broadcast;
+use utoipa::{IntoParams, ToSchema};
 
-use crate::config::{BlobStorageConfig, ContainerBlobFilter, ContainerConfig};
+use crate::{
+    config::{BlobStorageConfig, ContainerBlobFilter, ContainerConfig, ProxyConfig},
+    events::{PruneCounts, RouteEvent, RouteEventKind},
+};
 
 type BlobIdTuple = (String, String, String); // (account, container, path)
 
@@ -26,6 +43,53 @@ pub struct BlobId {
     pub name: String,
 }
 
+impl BlobId {
+    /// Blob at a storage URL, `https://{account}.blob.core.windows.net/{container}/{name}`
+    pub fn from_url(url: &str) -> Result<Self> {
+        let url = reqwest::Url::parse(url)?;
+
+        let account = url
+            .host_str()
+            .and_then(|host| host.strip_suffix(".blob.core.windows.net"))
+            .ok_or_else(|| anyhow!("not an azure blob storage url: {url}"))?;
+
+        let (container, name) = url
+            .path()
+            .trim_start_matches('/')
+            .split_once('/')
+            .filter(|(container, name)| !container.is_empty() && !name.is_empty())
+            .ok_or_else(|| anyhow!("no container and blob name in url: {url}"))?;
+
+        Ok(Self {
+            account: account.to_owned(),
+            container: percent_decode(container)?,
+            name: percent_decode(name)?,
+        })
+    }
+}
+
+/// Decode a percent-encoded URL path
+fn percent_decode(s: &str) -> Result<String> {
+    let mut bytes = Vec::with_capacity(s.len());
+    let mut rest = s.as_bytes();
+
+    while let Some((&b, tail)) = rest.split_first() {
+        if b == b'%' {
+            let hex = tail
+                .get(..2)
+                .and_then(|hex| std::str::from_utf8(hex).ok())
+                .ok_or_else(|| anyhow!("invalid percent-encoding in {s}"))?;
+            bytes.push(u8::from_str_radix(hex, 16)?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(b);
+            rest = tail;
+        }
+    }
+
+    Ok(String::from_utf8(bytes)?)
+}
+
 impl From<BlobIdTuple> for BlobId {
     fn from(tuple: BlobIdTuple) -> Self {
         let (account, container, name) = tuple;
@@ -59,6 +123,14 @@ pub struct BlobInfo {
     pub time_last_checked: i64,
 }
 
+impl BlobInfo {
+    /// CID of the blob's content, once it's hashed
+    pub fn cid(&self) -> Option<String> {
+        self.hash
+            .map(|hash| hash_to_cid(multihash::BLAKE3, &hash, multicodec::RAW))
+    }
+}
+
 impl From<BlobInfoTuple> for BlobInfo {
     fn from(tuple: BlobInfoTuple) -> Self {
         let (timestamp, size, hash, time_first_indexed, time_last_checked) = tuple;
@@ -85,6 +157,296 @@ impl From<BlobInfo> for BlobInfoTuple {
     }
 }
 
+type JobTuple = (String, String, i64, Option<i64>, String, u64, Vec<String>); // (account, container, started_at, finished_at, state, items_indexed, errors)
+
+/// A blob whose route was deleted, which reindexing skips until the blob changes
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct Tombstone {
+    pub account: String,
+    pub container: String,
+    pub name: String,
+    /// Unix timestamp the route was deleted at
+    pub tombstoned_at: i64,
+    /// Etag of the blob when its route was deleted, unset if none was recorded, in which case the
+    /// blob stays skipped even if it changes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub etag: Option<String>,
+}
+
+/// A CID expected to get a route, reported with a `watch_found` event when it does or a
+/// `watch_expired` event if its deadline passes first
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct Watch {
+    pub cid: String,
+    /// Unix timestamp the watch was registered at
+    pub watched_at: i64,
+    /// Unix timestamp by which the CID is expected to have a route, unset to wait indefinitely
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deadline: Option<i64>,
+    /// The deadline passed without a route, the CID is still watched in case it turns up late
+    pub expired: bool,
+}
+
+type WatchTuple = (i64, Option<i64>, bool); // (watched_at, deadline, expired)
+
+/// A blob in an iroh collection
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct CollectionChild {
+    /// Name relative to the collection's directory, as in the collection
+    pub name: String,
+    pub cid: String,
+    /// Size in bytes
+    pub size: u64,
+}
+
+/// A single pass of the indexer over a container
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct Job {
+    pub id: u64,
+    pub account: String,
+    pub container: String,
+    pub started_at: i64,
+    pub finished_at: Option<i64>,
+    pub state: JobState,
+    /// Indexing step the job is in, or was in when it stopped
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub phase: Option<JobPhase>,
+    /// Unfinished job for the same container that this job picks up from after a restart
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resumes: Option<u64>,
+    /// Blob index entries added, blob hashes computed, and iroh collections indexed so far
+    pub items_indexed: u64,
+    pub errors: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum JobState {
+    Running,
+    /// Finished, possibly with errors in some indexing steps
+    Completed,
+    /// Found still running on startup, e.g. after a crash
+    Interrupted,
+    /// Stopped by a shutdown before finishing
+    Aborted,
+}
+
+impl JobState {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Running => "running",
+            Self::Completed => "completed",
+            Self::Interrupted => "interrupted",
+            Self::Aborted => "aborted",
+        }
+    }
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "running" => Ok(Self::Running),
+            "completed" => Ok(Self::Completed),
+            "interrupted" => Ok(Self::Interrupted),
+            "aborted" => Ok(Self::Aborted),
+            s => Err(anyhow!("unknown job state: {s}")),
+        }
+    }
+
+    /// Whether the job stopped without finishing
+    pub fn is_unfinished(&self) -> bool {
+        matches!(self, Self::Interrupted | Self::Aborted)
+    }
+}
+
+/// Indexing steps of a job, in the order they run
+///
+/// Each step works from what's already in the index, so a job picking up from an unfinished one
+/// runs every step again: listing restarts from the beginning, while hashing only hashes the blobs
+/// the unfinished job didn't get to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum JobPhase {
+    /// Listing the container's blobs into the blob index
+    ListingBlobs,
+    /// Hashing blobs in the blob index
+    HashingBlobs,
+    /// Indexing iroh collections among the hashed blobs
+    IndexingCollections,
+}
+
+impl JobPhase {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::ListingBlobs => "listing_blobs",
+            Self::HashingBlobs => "hashing_blobs",
+            Self::IndexingCollections => "indexing_collections",
+        }
+    }
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "listing_blobs" => Ok(Self::ListingBlobs),
+            "hashing_blobs" => Ok(Self::HashingBlobs),
+            "indexing_collections" => Ok(Self::IndexingCollections),
+            s => Err(anyhow!("unknown job phase: {s}")),
+        }
+    }
+}
+
+impl Job {
+    fn from_tuple(
+        id: u64,
+        tuple: JobTuple,
+        phase: Option<&str>,
+        resumes: Option<u64>,
+    ) -> Result<Self> {
+        let (account, container, started_at, finished_at, state, items_indexed, errors) = tuple;
+        Ok(Self {
+            id,
+            account,
+            container,
+            started_at,
+            finished_at,
+            state: JobState::from_str(&state)?,
+            phase: phase.map(JobPhase::from_str).transpose()?,
+            resumes,
+            items_indexed,
+            errors,
+        })
+    }
+}
+
+impl From<Job> for JobTuple {
+    fn from(job: Job) -> Self {
+        let Job {
+            account,
+            container,
+            started_at,
+            finished_at,
+            state,
+            items_indexed,
+            errors,
+            ..
+        } = job;
+        (
+            account,
+            container,
+            started_at,
+            finished_at,
+            state.as_str().to_owned(),
+            items_indexed,
+            errors,
+        )
+    }
+}
+
+type ShutdownReportTuple = (i64, i64, Vec<u64>, Vec<u64>, Vec<u64>); // (started_at, stopped_at, completed, aborted, resumable)
+
+/// Indexer jobs run by a process, written when it shuts down
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ShutdownReport {
+    /// Unix timestamp the process started at
+    pub started_at: i64,
+    /// Unix timestamp the process shut down at
+    pub stopped_at: i64,
+    /// IDs of jobs completed since the process started
+    pub completed: Vec<u64>,
+    /// IDs of jobs aborted by the shutdown
+    pub aborted: Vec<u64>,
+    /// IDs of aborted jobs that got past listing blobs, whose hashing progress is picked up by the
+    /// next job for their container. Other aborted jobs are restarted from the beginning.
+    pub resumable: Vec<u64>,
+}
+
+impl From<ShutdownReportTuple> for ShutdownReport {
+    fn from(tuple: ShutdownReportTuple) -> Self {
+        let (started_at, stopped_at, completed, aborted, resumable) = tuple;
+        Self {
+            started_at,
+            stopped_at,
+            completed,
+            aborted,
+            resumable,
+        }
+    }
+}
+
+impl From<ShutdownReport> for ShutdownReportTuple {
+    fn from(report: ShutdownReport) -> Self {
+        let ShutdownReport {
+            started_at,
+            stopped_at,
+            completed,
+            aborted,
+            resumable,
+        } = report;
+        (started_at, stopped_at, completed, aborted, resumable)
+    }
+}
+
+type EventTuple = (i64, String, String, String, String, Option<String>); // (timestamp, kind, account, container, name, cid)
+
+type PruneCountsTuple = (u64, u64, u8); // (stale, entries, max_prune_percent)
+
+impl From<PruneCountsTuple> for PruneCounts {
+    fn from(tuple: PruneCountsTuple) -> Self {
+        let (stale, entries, max_prune_percent) = tuple;
+        Self {
+            stale,
+            entries,
+            max_prune_percent,
+        }
+    }
+}
+
+impl From<PruneCounts> for PruneCountsTuple {
+    fn from(counts: PruneCounts) -> Self {
+        let PruneCounts {
+            stale,
+            entries,
+            max_prune_percent,
+        } = counts;
+        (stale, entries, max_prune_percent)
+    }
+}
+
+impl RouteEvent {
+    fn from_tuple(id: u64, tuple: EventTuple) -> Result<Self> {
+        let (timestamp, kind, account, container, name, cid) = tuple;
+        Ok(Self {
+            id,
+            timestamp,
+            kind: RouteEventKind::from_str(&kind)?,
+            account,
+            container,
+            name,
+            cid,
+            prune: None,
+        })
+    }
+}
+
+impl From<RouteEvent> for EventTuple {
+    fn from(event: RouteEvent) -> Self {
+        let RouteEvent {
+            timestamp,
+            kind,
+            account,
+            container,
+            name,
+            cid,
+            ..
+        } = event;
+        (
+            timestamp,
+            kind.as_str().to_owned(),
+            account,
+            container,
+            name,
+            cid,
+        )
+    }
+}
+
 type HashBytes = [u8; 32];
 
 // Used to look up blob info by blob id
@@ -102,55 +464,191 @@ const COLLECTION_INDEX_TABLE: TableDefinition<BlobIdTuple, BlobInfoTuple> =
 const COLLECTION_HASH_INDEX_TABLE: MultimapTableDefinition<HashBytes, BlobIdTuple> =
     MultimapTableDefinition::new("collection_hash_index");
 
+// Used to detect changed blobs, kept separate from the blob index so existing databases stay
+// readable
+const BLOB_ETAG_TABLE: TableDefinition<BlobIdTuple, &str> = TableDefinition::new("blob_etag");
+
+// Content-Type of blobs as reported by blob storage, kept separate from the blob index so existing
+// databases stay readable
+const BLOB_CONTENT_TYPE_TABLE: TableDefinition<BlobIdTuple, &str> =
+    TableDefinition::new("blob_content_type");
+
+// Sample fingerprints of hashed blobs, see `BlobHashes`
+const BLOB_SAMPLE_TABLE: TableDefinition<BlobIdTuple, HashBytes> =
+    TableDefinition::new("blob_sample");
+
+// Blake3 hash of the content with a given sha256 hash, so blobs can also be found by sha256 CIDs.
+// Entries hold for any content, so they're never removed.
+const SHA256_EQUIVALENCE_TABLE: TableDefinition<HashBytes, HashBytes> =
+    TableDefinition::new("sha256_equivalence");
+
+// Children of each iroh collection as (name, hash, size), by collection hash, recorded as the
+// collection is indexed
+const COLLECTION_CHILD_TABLE: MultimapTableDefinition<HashBytes, (&str, HashBytes, u64)> =
+    MultimapTableDefinition::new("collection_child");
+
+// Blobs whose routes were deleted through the API, with when and the etag the blob had if one was
+// recorded, so reindexing skips them until the blob changes
+const BLOB_TOMBSTONE_TABLE: TableDefinition<BlobIdTuple, (i64, Option<&str>)> =
+    TableDefinition::new("blob_tombstone");
+
+// The tombstone table up to schema version 5, with an empty etag for tombstones without one
+const BLOB_TOMBSTONE_TABLE_V1: TableDefinition<BlobIdTuple, (i64, &str)> =
+    TableDefinition::new("blob_tombstone");
+
+// Indexer jobs by job id
+const JOB_TABLE: TableDefinition<u64, JobTuple> = TableDefinition::new("job");
+
+// Phase of each indexer job, journaled as the job moves through its steps. Kept separate from the
+// job table so existing databases stay readable.
+const JOB_PHASE_TABLE: TableDefinition<u64, &str> = TableDefinition::new("job_phase");
+
+// Unfinished job each indexer job picks up from, by job id
+const JOB_RESUMES_TABLE: TableDefinition<u64, u64> = TableDefinition::new("job_resumes");
+
+// Report of the last shutdown, under `LAST_SHUTDOWN_REPORT_KEY`
+const SHUTDOWN_REPORT_TABLE: TableDefinition<&str, ShutdownReportTuple> =
+    TableDefinition::new("shutdown_report");
+
+const LAST_SHUTDOWN_REPORT_KEY: &str = "last";
+
+// Route events by event id
+const EVENT_TABLE: TableDefinition<u64, EventTuple> = TableDefinition::new("event");
+
+// Counts of each `prune_refused` event, by event id, kept separate from the event table so
+// existing databases stay readable
+const EVENT_PRUNE_TABLE: TableDefinition<u64, PruneCountsTuple> =
+    TableDefinition::new("event_prune");
+
+// ID of the next event to deliver to each webhook, by webhook URL
+const WEBHOOK_CURSOR_TABLE: TableDefinition<&str, u64> = TableDefinition::new("webhook_cursor");
+
+// CIDs watched until they get a route, by CID
+const WATCH_TABLE: TableDefinition<&str, WatchTuple> = TableDefinition::new("watch");
+
+// Number of times in a row hashing each unhashed blob has failed, cleared once it's hashed
+const BLOB_HASH_FAILURE_TABLE: TableDefinition<BlobIdTuple, u32> =
+    TableDefinition::new("blob_hash_failure");
+
+// Marker of the next page to list of each container, by (account, container), kept while the
+// container is being listed so an interrupted listing resumes where it stopped
+const CONTAINER_LIST_MARKER_TABLE: TableDefinition<(&str, &str), &str> =
+    TableDefinition::new("container_list_marker");
+
+// Schema version of the database, the number of `MIGRATIONS` applied to it, under
+// `SCHEMA_VERSION_KEY`
+const SCHEMA_VERSION_TABLE: TableDefinition<&str, u64> = TableDefinition::new("schema_version");
+
+const SCHEMA_VERSION_KEY: &str = "version";
+
+/// Schema changes in order, new ones go at the end and existing ones are never changed. Each runs
+/// in the write transaction that records it, so a failed migration leaves the database as it was.
+/// Databases from before schema versions have exactly the tables of the first.
+const MIGRATIONS: &[fn(&redb::WriteTransaction) -> Result<()>] = &[
+    create_tables,
+    create_watch_table,
+    create_hash_failure_table,
+    create_event_prune_table,
+    create_collection_child_table,
+    make_tombstone_etags_optional,
+    create_container_list_marker_table,
+];
+
+/// Number of most recent route events kept in the event table
+const EVENTS_RETAINED: u64 = 100_000;
+
+/// Number of route events buffered for subscribers that fall behind
+const EVENT_CHANNEL_CAPACITY: usize = 1024;
+
+/// Number of most recent indexer jobs kept in the job table
+const JOBS_RETAINED: u64 = 1000;
+
+/// Number of bytes from each end of a blob included in its sample fingerprint
+const SAMPLE_LEN: usize = 64 * 1024;
+
+/// Number of times hashing a blob has to fail before its stub can be pruned as stale
+const MAX_HASH_FAILURES: u32 = 3;
+
 pub struct Db {
     db: redb::Database,
+    file: PathBuf,
+    events: broadcast::Sender<RouteEvent>,
+    /// Options of the blob storage clients, with the configured proxy
+    client_options: ClientOptions,
 }
 
 impl Db {
-    pub fn init(db_file: PathBuf) -> Result<Self> {
-        let db = redb::Database::create(db_file)?;
+    pub fn init(db_file: PathBuf, proxy: Option<&ProxyConfig>) -> Result<Self> {
+        let db = redb::Database::create(&db_file)?;
 
-        let tx = db.begin_write()?;
-        {
-            tx.open_table(BLOB_INDEX_TABLE)?;
-            tx.open_multimap_table(BLOB_HASH_INDEX_TABLE)?;
-            tx.open_table(COLLECTION_INDEX_TABLE)?;
-            tx.open_multimap_table(COLLECTION_HASH_INDEX_TABLE)?;
-        }
-        tx.commit()?;
+        migrate(&db)?;
+
+        let (events, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
+
+        Ok(Self {
+            db,
+            file: db_file,
+            events,
+            client_options: client_options(proxy)?,
+        })
+    }
 
-        Ok(Self { db })
+    fn blob_service_client(&self, account: &str) -> BlobServiceClient {
+        // TODO: support credentials for private blob storage
+        let storage_credentials = StorageCredentials::anonymous();
+
+        ClientBuilder::new(account.to_owned(), storage_credentials)
+            .client_options(self.client_options.clone())
+            .blob_service_client()
     }
 
-    pub async fn update_blob_index(&self, blob_storage_config: &BlobStorageConfig) -> Result<()> {
+    /// Returns the number of blob index entries added or reset because their blob changed.
+    /// Containers whose pruning is refused over `max_prune_percent` are still indexed.
+    pub async fn update_blob_index(
+        &self,
+        blob_storage_config: &BlobStorageConfig,
+        max_prune_percent: Option<u8>,
+    ) -> Result<u64> {
         log::debug!("Updating blob index...");
 
+        let mut n_added = 0;
+
         for ContainerConfig {
             account,
             container,
             filter,
+            ..
         } in &blob_storage_config.containers
         {
-            self.add_index_entries_for_missing_blobs(account, container, filter)
+            n_added += self
+                .add_index_entries_for_missing_blobs(account, container, filter)
                 .await?;
 
-            self.prune_index_entries_for_deleted_or_filtered_blobs(account, container, filter)
-                .await?;
+            self.prune_index_entries_for_deleted_or_filtered_blobs(
+                account,
+                container,
+                filter,
+                max_prune_percent,
+            )
+            .await?;
         }
 
         log::debug!("Finished updating blob index.");
 
-        Ok(())
+        Ok(n_added)
     }
 
+    /// Hashes up to `hashing_concurrency` blobs at a time.
+    /// Returns the number of blob hashes computed
     pub async fn update_blob_index_hashes(
         &self,
         blob_storage_config: &BlobStorageConfig,
-    ) -> Result<()> {
+        hashing_concurrency: usize,
+    ) -> Result<u64> {
         log::debug!("Updating blob index hashes...");
 
-        // TODO: will be needed for storage credentials
-        let _ = blob_storage_config;
+        let mut n_hashed = 0;
+        let mut n_failed = 0;
 
         // TODO: this isn't the best way to do things but for now is a nice way of leaving massive
         //       blobs until last
@@ -161,90 +659,319 @@ impl Db {
         ] {
             log::trace!("Computing hashes for blobs <= {} MB...", mb_size_cutoff);
 
-            let rtx = self.db.begin_read()?;
-            let table = rtx.open_table(BLOB_INDEX_TABLE)?;
-
-            for entry in table.iter()? {
-                let (key, value) = entry?;
-                let (blob_id, blob_info) =
-                    (BlobId::from(key.value()), BlobInfo::from(value.value()));
-
-                let BlobId {
-                    account,
-                    container,
-                    name,
-                } = blob_id.clone();
-                let BlobInfo { size, hash, .. } = blob_info;
-
-                if size > mb_size_cutoff * 1024 * 1024 {
-                    continue;
-                }
+            let unhashed_blobs =
+                {
+                    let rtx = self.db.begin_read()?;
+                    let table = rtx.open_table(BLOB_INDEX_TABLE)?;
+                    let etag_table = rtx.open_table(BLOB_ETAG_TABLE)?;
 
-                // skip if hash is already computed
-                if hash.is_some() {
-                    continue;
-                }
+                    let mut unhashed_blobs = Vec::new();
 
-                log::trace!(
-                    "Streaming blob to compute hash: size={size} account={account} container={container} name={name}"
-                );
+                    for entry in table.iter()? {
+                        let (key, value) = entry?;
+                        let (blob_id, blob_info) =
+                            (BlobId::from(key.value()), BlobInfo::from(value.value()));
 
-                let account = account.to_string();
-                let container = container.to_string();
-                let name = name.to_string();
+                        // skip entries that don't belong to the configured containers
+                        if !blob_storage_config.containers.iter().any(|c| {
+                            c.account == blob_id.account && c.container == blob_id.container
+                        }) {
+                            continue;
+                        }
 
-                let hash = {
-                    let mut hasher = blake3::Hasher::new();
+                        if blob_info.size > mb_size_cutoff * 1024 * 1024 {
+                            continue;
+                        }
 
-                    if size == 0 {
-                        hasher.update(&[]);
-                    } else {
-                        let storage_credentials = StorageCredentials::anonymous();
-                        let blob_service = BlobServiceClient::new(&account, storage_credentials);
-                        let container_client = blob_service.container_client(&container);
-                        let blob_client = container_client.blob_client(&name);
-                        let mut blob_stream = blob_client.get().into_stream();
+                        // skip if hash is already computed
+                        if blob_info.hash.is_some() {
+                            continue;
+                        }
 
-                        while let Some(chunk_response) = blob_stream.next().await {
-                            let chunk_response = chunk_response?;
-                            let chunk = chunk_response.data.collect().await?;
+                        let etag = etag_table.get(key.value())?.map(|v| v.value().to_owned());
 
-                            hasher.update(&chunk);
-                        }
+                        unhashed_blobs.push((blob_id, blob_info, etag));
                     }
 
-                    hasher.finalize().as_bytes().to_owned()
+                    unhashed_blobs
                 };
 
-                log::trace!("Computed hash={hash} for blob: account={account} container={container} name={name}", hash = hex::encode(hash));
+            let mut hashed_blobs = futures::stream::iter(unhashed_blobs)
+                .map(|(blob_id, blob_info, etag)| async move {
+                    let sha256 = blob_storage_config
+                        .containers
+                        .iter()
+                        .find(|c| c.account == blob_id.account && c.container == blob_id.container)
+                        .and_then(|c| c.sha256)
+                        .unwrap_or(false);
 
-                let now = chrono::Utc::now().timestamp();
+                    let blob_service = self.blob_service_client(&blob_id.account);
+                    let hashes =
+                        compute_blob_hashes(&blob_service, &blob_id, blob_info.size, sha256).await;
 
-                let new_blob_info = BlobInfo {
-                    hash: Some(hash),
-                    time_last_checked: now,
-                    ..blob_info.clone()
+                    (blob_id, blob_info, etag, hashes)
+                })
+                .buffer_unordered(hashing_concurrency.max(1));
+
+            while let Some((blob_id, blob_info, etag, hashes)) = hashed_blobs.next().await {
+                // a blob that fails to hash is retried on the next pass rather than holding up
+                // the rest
+                let hashes = match hashes {
+                    Ok(hashes) => hashes,
+                    Err(e) => {
+                        log::warn!(
+                            "Error hashing blob account={} container={} name={}: {e:?}",
+                            blob_id.account,
+                            blob_id.container,
+                            blob_id.name
+                        );
+                        self.record_hash_failure(&blob_id)?;
+                        n_failed += 1;
+                        continue;
+                    }
                 };
 
-                self.update_blob_index_entry(blob_id, new_blob_info, Some(blob_info))?;
+                if self
+                    .record_blob_hashes(blob_id, blob_info, etag, hashes)?
+                    .is_some()
+                {
+                    n_hashed += 1;
+                }
             }
         }
 
+        if n_failed > 0 {
+            bail!("failed to hash {n_failed} blobs, hashed {n_hashed}");
+        }
+
         log::debug!("Finished updating blob index hashes.");
 
+        Ok(n_hashed)
+    }
+
+    /// Hash a single indexed blob now rather than waiting for the indexer, returning its updated
+    /// info. `None` if its entry was removed, tombstoned or changed while it was being hashed.
+    pub async fn hash_blob(
+        &self,
+        blob_id: BlobId,
+        blob_info: BlobInfo,
+        sha256: bool,
+    ) -> Result<Option<BlobInfo>> {
+        let etag = {
+            let rtx = self.db.begin_read()?;
+            let table = rtx.open_table(BLOB_ETAG_TABLE)?;
+            let etag = table
+                .get(BlobIdTuple::from(blob_id.clone()))?
+                .map(|v| v.value().to_owned());
+            etag
+        };
+
+        let blob_service = self.blob_service_client(&blob_id.account);
+
+        let hashes =
+            match compute_blob_hashes(&blob_service, &blob_id, blob_info.size, sha256).await {
+                Ok(hashes) => hashes,
+                Err(e) => {
+                    self.record_hash_failure(&blob_id)?;
+                    return Err(e);
+                }
+            };
+
+        self.record_blob_hashes(blob_id, blob_info, etag, hashes)
+    }
+
+    /// Index a blob that hasn't been listed yet, such as one uploaded since the last indexing pass,
+    /// returning its entry. `None` if it doesn't exist or match the container's filter, or its
+    /// route was deleted through the API and it hasn't changed since.
+    pub async fn index_blob(
+        &self,
+        blob_id: &BlobId,
+        filter: &ContainerBlobFilter,
+    ) -> Result<Option<BlobInfo>> {
+        let response = self
+            .blob_service_client(&blob_id.account)
+            .container_client(blob_id.container.clone())
+            .blob_client(blob_id.name.clone())
+            .get_properties()
+            .await;
+
+        let properties = match response {
+            Ok(response) => response.blob.properties,
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    azure_core::error::ErrorKind::HttpResponse {
+                        status: azure_core::StatusCode::NotFound,
+                        ..
+                    }
+                ) =>
+            {
+                return Ok(None)
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        let timestamp = properties.last_modified.unix_timestamp();
+        let size = properties.content_length;
+        let etag = properties.etag.to_string();
+        let content_type =
+            Some(properties.content_type).filter(|content_type| !content_type.is_empty());
+
+        if !filter.blob_is_match(&blob_id.name, size) {
+            return Ok(None);
+        }
+
+        if let Some(tombstone) = self.get_tombstone(blob_id)? {
+            match tombstone.etag {
+                Some(tombstone_etag) if tombstone_etag != etag => {
+                    self.remove_tombstone(blob_id)?;
+                }
+                _ => return Ok(None),
+            }
+        }
+
+        let now = chrono::Utc::now().timestamp();
+
+        let blob_info = BlobInfo {
+            timestamp,
+            size,
+            hash: None,
+            time_first_indexed: now,
+            time_last_checked: now,
+        };
+
+        let wtx = self.db.begin_write()?;
+
+        // listed by the indexer in the meantime
+        if let Some(current_blob_info) = wtx
+            .open_table(BLOB_INDEX_TABLE)?
+            .get(BlobIdTuple::from(blob_id.clone()))?
+            .map(|v| BlobInfo::from(v.value()))
+        {
+            return Ok(Some(current_blob_info));
+        }
+
+        let event = self.write_blob_index_entry(&wtx, blob_id.clone(), blob_info.clone(), None)?;
+        self.write_blob_properties(&wtx, blob_id, &etag, content_type.as_deref())?;
+        wtx.commit()?;
+
+        if let Some(event) = event {
+            self.publish_event(event);
+        }
+
+        Ok(Some(blob_info))
+    }
+
+    /// Count a failed attempt at hashing a blob, see `MAX_HASH_FAILURES`
+    fn record_hash_failure(&self, blob_id: &BlobId) -> Result<()> {
+        let blob_id = BlobIdTuple::from(blob_id.clone());
+
+        let wtx = self.db.begin_write()?;
+        {
+            let mut table = wtx.open_table(BLOB_HASH_FAILURE_TABLE)?;
+            let failures = table.get(blob_id.clone())?.map_or(0, |v| v.value());
+            table.insert(blob_id, failures + 1)?;
+        }
+        wtx.commit()?;
+
         Ok(())
     }
 
+    /// Record a blob's hashes, given its entry and etag from before it was hashed, returning its
+    /// updated info. Nothing is recorded, and `None` returned, if in the meantime its entry was
+    /// removed or tombstoned, or the blob changed, so a hash that was in flight can't bring back a
+    /// deleted route or be recorded for content the blob no longer has.
+    fn record_blob_hashes(
+        &self,
+        blob_id: BlobId,
+        blob_info: BlobInfo,
+        etag: Option<String>,
+        hashes: BlobHashes,
+    ) -> Result<Option<BlobInfo>> {
+        let BlobHashes {
+            blake3: hash,
+            sha256,
+            sample,
+        } = hashes;
+
+        let key = BlobIdTuple::from(blob_id.clone());
+
+        let wtx = self.db.begin_write()?;
+
+        let current_blob_info = wtx
+            .open_table(BLOB_INDEX_TABLE)?
+            .get(&key)?
+            .map(|v| BlobInfo::from(v.value()));
+        let current_etag = wtx
+            .open_table(BLOB_ETAG_TABLE)?
+            .get(&key)?
+            .map(|v| v.value().to_owned());
+        let tombstoned = wtx.open_table(BLOB_TOMBSTONE_TABLE)?.get(&key)?.is_some();
+
+        let current_blob_info = match current_blob_info {
+            Some(current_blob_info)
+                if !tombstoned
+                    && current_blob_info.timestamp == blob_info.timestamp
+                    && current_blob_info.size == blob_info.size
+                    && current_etag == etag =>
+            {
+                current_blob_info
+            }
+            _ => {
+                log::debug!(
+                    "Not recording hash of blob removed or changed while hashing: account={} container={} name={}",
+                    blob_id.account,
+                    blob_id.container,
+                    blob_id.name
+                );
+                return Ok(None);
+            }
+        };
+
+        wtx.open_table(BLOB_SAMPLE_TABLE)?.insert(&key, sample)?;
+        wtx.open_table(BLOB_HASH_FAILURE_TABLE)?.remove(&key)?;
+        if let Some(sha256) = sha256 {
+            wtx.open_table(SHA256_EQUIVALENCE_TABLE)?
+                .insert(sha256, hash)?;
+        }
+
+        let now = chrono::Utc::now().timestamp();
+
+        let new_blob_info = BlobInfo {
+            hash: Some(hash),
+            time_last_checked: now,
+            ..current_blob_info.clone()
+        };
+
+        let event = self.write_blob_index_entry(
+            &wtx,
+            blob_id,
+            new_blob_info.clone(),
+            Some(current_blob_info),
+        )?;
+        wtx.commit()?;
+
+        if let Some(event) = event {
+            self.publish_event(event);
+        }
+
+        Ok(Some(new_blob_info))
+    }
+
+    /// Returns the number of iroh collections indexed
     pub fn update_iroh_collections_index(
         &self,
         blob_storage_config: &BlobStorageConfig,
-    ) -> Result<()> {
+    ) -> Result<u64> {
         log::debug!("Updating iroh collections index...");
 
+        let mut n_collections = 0;
+
         for ContainerConfig {
             account,
             container,
             filter,
+            ..
         } in &blob_storage_config.containers
         {
             // get all blobs in this container for the configured filter
@@ -341,7 +1068,9 @@ impl Db {
                     let timestamp = blobs.iter().map(|(_, _, blob_info)| blob_info.timestamp).max().expect("expected at least one blob in a collection");
                     let size = blobs.iter().map(|(_, _, blob_info)| blob_info.size).sum::<u64>();
 
-                    (path.to_owned(), collection_hash, (timestamp, size))
+                    let children = blobs.into_iter().map(|(name, hash, blob_info)| (name, *hash.as_bytes(), blob_info.size)).collect::<Vec<_>>();
+
+                    (path.to_owned(), collection_hash, (timestamp, size), children)
                 })
                 .collect::<Vec<_>>();
 
@@ -351,8 +1080,9 @@ impl Db {
                 let mut collection_index_table = wtx.open_table(COLLECTION_INDEX_TABLE)?;
                 let mut collection_hash_table =
                     wtx.open_multimap_table(COLLECTION_HASH_INDEX_TABLE)?;
+                let mut collection_child_table = wtx.open_multimap_table(COLLECTION_CHILD_TABLE)?;
 
-                for (path, collection_hash, (timestamp, size)) in &collections_blobs {
+                for (path, collection_hash, (timestamp, size), children) in &collections_blobs {
                     let account = account.clone();
                     let container = container.clone();
 
@@ -366,7 +1096,7 @@ impl Db {
                         let rtx = self.db.begin_read()?;
                         let table = rtx.open_table(COLLECTION_INDEX_TABLE)?;
 
-                        table.get(&blob_id)?
+                        table.get(&blob_id)?.map(|v| v.value()).map(BlobInfo::from)
                     };
 
                     let now = chrono::Utc::now().timestamp();
@@ -376,19 +1106,46 @@ impl Db {
                         size: *size,
                         hash: Some(*collection_hash),
                         time_first_indexed: existing_entry
-                            .map(|v| v.value())
-                            .map(BlobInfo::from)
+                            .as_ref()
                             .map(|info| info.time_first_indexed)
                             .unwrap_or(now),
                         time_last_checked: now,
                     });
 
+                    // drop the route for the collection's previous contents
+                    if let Some(BlobInfo {
+                        hash: Some(old_hash),
+                        ..
+                    }) = existing_entry
+                    {
+                        if old_hash != *collection_hash {
+                            collection_hash_table.remove(old_hash, &blob_id)?;
+                            if collection_hash_table.get(old_hash)?.next().is_none() {
+                                collection_child_table.remove_all(old_hash)?;
+                            }
+                        }
+                    }
+
                     collection_index_table.insert(&blob_id, blob_info)?;
                     collection_hash_table.insert(collection_hash, blob_id)?;
+
+                    // a collection's children follow from its hash, so are only recorded once
+                    let has_children = collection_child_table
+                        .get(collection_hash)?
+                        .next()
+                        .is_some();
+                    if !has_children {
+                        for (name, hash, size) in children {
+                            collection_child_table
+                                .insert(collection_hash, (name.as_str(), *hash, *size))?;
+                        }
+                    }
                 }
             }
             wtx.commit()?;
 
+            n_collections += collections_blobs.len() as u64;
+
             // prune any iroh collection paths no longer present in this container
             let current_collection_paths = collections_blobs
                 .iter()
@@ -399,17 +1156,22 @@ impl Db {
             let table_collection_paths = rtx
                 .open_table(COLLECTION_INDEX_TABLE)?
                 .iter()?
-                .filter_map(|entry| {
-                    let (key, value) = entry.unwrap();
+                .map(|entry| {
+                    let (key, value) = entry?;
                     let (blob_id, blob_info) =
                         (BlobId::from(key.value()), BlobInfo::from(value.value()));
 
-                    if blob_id.account == *account && blob_id.container == *container {
+                    let entry = if blob_id.account == *account && blob_id.container == *container {
                         Some((blob_id, blob_info))
                     } else {
                         None
-                    }
+                    };
+
+                    Ok(entry)
                 })
+                .collect::<Result<Vec<_>>>()?
+                .into_iter()
+                .flatten()
                 .collect::<Vec<_>>();
 
             for (blob_id, blob_info) in table_collection_paths {
@@ -425,15 +1187,20 @@ impl Db {
                         collection_index_table.remove(&blob_id)?;
                         if let Some(hash) = blob_info.hash {
                             collection_hash_table.remove(hash, blob_id)?;
+                            if collection_hash_table.get(hash)?.next().is_none() {
+                                wtx.open_multimap_table(COLLECTION_CHILD_TABLE)?
+                                    .remove_all(hash)?;
+                            }
                         }
                     }
+                    wtx.commit()?;
                 }
             }
         }
 
         log::debug!("Finished updating iroh collections index.");
 
-        Ok(())
+        Ok(n_collections)
     }
 
     async fn add_index_entries_for_missing_blobs(
@@ -441,126 +1208,382 @@ impl Db {
         account: impl Into<String>,
         container: impl Into<String>,
         filter: &ContainerBlobFilter,
-    ) -> Result<()> {
+    ) -> Result<u64> {
         let account = account.into();
         let container = container.into();
 
-        // TODO: support credentials for private blob storage
-        let storage_credentials = StorageCredentials::anonymous();
-
-        let blob_service = BlobServiceClient::new(account.clone(), storage_credentials);
+        let blob_service = self.blob_service_client(&account);
         let container_client = blob_service.container_client(container.clone());
 
-        let response = container_client
+        let mut list_blobs = container_client
             .list_blobs()
-            .max_results(NonZeroU32::new(10 * 1000).unwrap())
-            .into_stream()
-            .next()
-            .await
-            .expect("stream failed")?;
+            .max_results(NonZeroU32::new(10 * 1000).unwrap());
 
-        for blob in response.blobs.blobs() {
-            let account = account.clone();
-            let container = container.clone();
-            let name = blob.name.clone();
-            let timestamp = blob.properties.last_modified.unix_timestamp();
-            let size = blob.properties.content_length;
-
-            if !filter.blob_is_match(&name, size) {
-                continue;
-            }
+        let marker = self.get_container_list_marker(&account, &container)?;
+        if let Some(marker) = &marker {
+            log::debug!("Resuming listing of account={account} container={container} where the last pass stopped");
+            list_blobs = list_blobs.marker(NextMarker::new(marker.clone()));
+        }
 
-            let blob_id = BlobId {
-                account,
-                container,
-                name: name.clone(),
+        let mut pages = list_blobs.into_stream();
+
+        let mut n_added = 0;
+        let mut is_first_page = true;
+
+        // entries are written as each page is listed, and the marker of the next page saved after
+        // them, so progress on large containers is kept even if a later page fails or the service
+        // restarts
+        while let Some(response) = pages.next().await {
+            let response = match response {
+                Ok(response) => response,
+                Err(e) => {
+                    // a marker blob storage no longer accepts would otherwise fail every pass,
+                    // starting over only costs listing the pages before it again
+                    if is_first_page && marker.is_some() {
+                        self.set_container_list_marker(&account, &container, None)?;
+                    }
+                    return Err(e.into());
+                }
             };
+            is_first_page = false;
+
+            for blob in response.blobs.blobs() {
+                let account = account.clone();
+                let container = container.clone();
+                let name = blob.name.clone();
+                let timestamp = blob.properties.last_modified.unix_timestamp();
+                let size = blob.properties.content_length;
+                let etag = blob.properties.etag.to_string();
+                let content_type = Some(blob.properties.content_type.clone())
+                    .filter(|content_type| !content_type.is_empty());
+
+                if !filter.blob_is_match(&name, size) {
+                    continue;
+                }
 
-            let current_blob_info = {
-                let rtx = self.db.begin_read()?;
-                let table = rtx.open_table(BLOB_INDEX_TABLE)?;
+                let blob_id = BlobId {
+                    account,
+                    container,
+                    name: name.clone(),
+                };
 
-                table
-                    .get(BlobIdTuple::from(blob_id.clone()))?
-                    .map(|v| v.value())
-                    .map(BlobInfo::from)
-            };
+                if let Some(tombstone) = self.get_tombstone(&blob_id)? {
+                    match tombstone.etag {
+                        Some(tombstone_etag) if tombstone_etag != etag => {
+                            log::debug!("Tombstoned blob changed, indexing it again: account={account} container={container} name={name}", account = blob_id.account, container = blob_id.container);
+
+                            self.remove_tombstone(&blob_id)?;
+                        }
+                        _ => continue,
+                    }
+                }
+
+                let (current_blob_info, current_etag, current_content_type) = {
+                    let rtx = self.db.begin_read()?;
+                    let table = rtx.open_table(BLOB_INDEX_TABLE)?;
+                    let etag_table = rtx.open_table(BLOB_ETAG_TABLE)?;
+                    let content_type_table = rtx.open_table(BLOB_CONTENT_TYPE_TABLE)?;
+
+                    let key = BlobIdTuple::from(blob_id.clone());
+
+                    (
+                        table.get(&key)?.map(|v| v.value()).map(BlobInfo::from),
+                        etag_table.get(&key)?.map(|v| v.value().to_owned()),
+                        content_type_table.get(&key)?.map(|v| v.value().to_owned()),
+                    )
+                };
 
-            if current_blob_info.is_none() {
                 let now = chrono::Utc::now().timestamp();
 
-                let new_blob_info = BlobInfo {
-                    timestamp,
-                    size,
-                    hash: None,
-                    time_first_indexed: now,
-                    time_last_checked: now,
+                let entry = match current_blob_info {
+                    None => {
+                        let new_blob_info = BlobInfo {
+                            timestamp,
+                            size,
+                            hash: None,
+                            time_first_indexed: now,
+                            time_last_checked: now,
+                        };
+
+                        Some((new_blob_info, None))
+                    }
+                    Some(current_blob_info) => {
+                        // entries indexed before etags were recorded fall back to comparing
+                        // last-modified and size
+                        let is_changed = match &current_etag {
+                            Some(current_etag) => *current_etag != etag,
+                            None => {
+                                current_blob_info.timestamp != timestamp
+                                    || current_blob_info.size != size
+                            }
+                        };
+
+                        if is_changed {
+                            log::debug!("Blob changed, resetting its hash: account={account} container={container} name={name}", account = blob_id.account, container = blob_id.container);
+
+                            // clears the hash, which drops the old route until it's rehashed
+                            let new_blob_info = BlobInfo {
+                                timestamp,
+                                size,
+                                hash: None,
+                                time_last_checked: now,
+                                ..current_blob_info.clone()
+                            };
+
+                            Some((new_blob_info, Some(current_blob_info)))
+                        } else {
+                            None
+                        }
+                    }
                 };
 
-                self.update_blob_index_entry(blob_id, new_blob_info, None)?;
+                let properties_changed = current_etag.as_deref() != Some(etag.as_str())
+                    || current_content_type != content_type;
+
+                if entry.is_none() && !properties_changed {
+                    continue;
+                }
+
+                // the entry, etag and content type are written together so they always agree
+                let wtx = self.db.begin_write()?;
+                let event = match entry {
+                    Some((new_blob_info, current_blob_info)) => {
+                        n_added += 1;
+                        self.write_blob_index_entry(
+                            &wtx,
+                            blob_id.clone(),
+                            new_blob_info,
+                            current_blob_info,
+                        )?
+                    }
+                    None => None,
+                };
+                self.write_blob_properties(&wtx, &blob_id, &etag, content_type.as_deref())?;
+                wtx.commit()?;
+
+                if let Some(event) = event {
+                    self.publish_event(event);
+                }
             }
+
+            // the last page has no next marker, which clears it for the next pass
+            self.set_container_list_marker(
+                &account,
+                &container,
+                response.next_marker.as_ref().map(NextMarker::as_str),
+            )?;
         }
 
+        Ok(n_added)
+    }
+
+    fn get_container_list_marker(&self, account: &str, container: &str) -> Result<Option<String>> {
+        let rtx = self.db.begin_read()?;
+        let table = rtx.open_table(CONTAINER_LIST_MARKER_TABLE)?;
+        let marker = table
+            .get((account, container))?
+            .map(|v| v.value().to_owned());
+
+        Ok(marker)
+    }
+
+    fn set_container_list_marker(
+        &self,
+        account: &str,
+        container: &str,
+        marker: Option<&str>,
+    ) -> Result<()> {
+        let wtx = self.db.begin_write()?;
+        {
+            let mut table = wtx.open_table(CONTAINER_LIST_MARKER_TABLE)?;
+            match marker {
+                Some(marker) => table.insert((account, container), marker)?,
+                None => table.remove((account, container))?,
+            };
+        }
+        wtx.commit()?;
+
         Ok(())
     }
 
-    async fn prune_index_entries_for_deleted_or_filtered_blobs(
+    /// Remove index entries for blobs that were deleted or no longer match the filter. If that would
+    /// remove more than `max_prune_percent` of the container's entries none are removed and a
+    /// `prune_refused` event is recorded instead.
+    /// Returns the number of entries removed.
+    pub async fn prune_index_entries_for_deleted_or_filtered_blobs(
         &self,
         account: impl Into<String>,
         container: impl Into<String>,
         filter: &ContainerBlobFilter,
-    ) -> Result<()> {
+        max_prune_percent: Option<u8>,
+    ) -> Result<u64> {
         let account = account.into();
         let container = container.into();
 
-        // TODO: support credentials for private blob storage
-        let storage_credentials = StorageCredentials::anonymous();
+        let blob_names = list_blob_names(&self.blob_service_client(&account), &container).await?;
 
-        let blob_service = BlobServiceClient::new(account.clone(), storage_credentials);
-        let container_client = blob_service.container_client(container.clone());
+        let mut n_entries = 0;
+        let mut stale_blob_ids = Vec::new();
 
-        let response = container_client
-            .list_blobs()
-            .max_results(NonZeroU32::new(10 * 1000).unwrap())
-            .into_stream()
-            .next()
-            .await
-            .expect("stream failed")?;
+        {
+            let rtx = self.db.begin_read()?;
+            let table = rtx.open_table(BLOB_INDEX_TABLE)?;
 
-        let blobs = response.blobs.blobs().collect::<Vec<_>>();
+            for entry in table.iter()? {
+                let (key, value) = entry?;
+                let (blob_id, blob_info) =
+                    (BlobId::from(key.value()), BlobInfo::from(value.value()));
 
-        let rtx = self.db.begin_read()?;
-        let table = rtx.open_table(BLOB_INDEX_TABLE)?;
+                // skip entries that don't belong to this account/container
+                if blob_id.account != account || blob_id.container != container {
+                    continue;
+                }
 
-        for entry in table.iter()? {
-            let (key, value) = entry?;
-            let (blob_id, blob_info) = (BlobId::from(key.value()), BlobInfo::from(value.value()));
+                n_entries += 1;
+
+                // remove entry if it no longer is included by the filter
+                if !filter.blob_is_match(&blob_id.name, blob_info.size) {
+                    stale_blob_ids.push(blob_id);
+                    continue;
+                }
+
+                // remove the entry if it no longer exists in the blob storage
+                if !blob_names.contains(&blob_id.name) {
+                    stale_blob_ids.push(blob_id);
+                }
+            }
+        }
+
+        // guard against upstream misconfigurations (e.g. an emptied container) wiping the index
+        if let Some(max_prune_percent) = max_prune_percent {
+            if stale_blob_ids.len() * 100 > n_entries * max_prune_percent as usize {
+                log::warn!(
+                    "Refusing to prune {} of {n_entries} index entries for account={account} container={container}, over the {max_prune_percent}% limit; use POST /v1/admin/force-prune to apply",
+                    stale_blob_ids.len(),
+                );
+                self.record_prune_refused(
+                    &account,
+                    &container,
+                    PruneCounts {
+                        stale: stale_blob_ids.len() as u64,
+                        entries: n_entries as u64,
+                        max_prune_percent,
+                    },
+                )?;
+                return Ok(0);
+            }
+        }
+
+        for blob_id in &stale_blob_ids {
+            self.delete_blob_index_entry(blob_id)?;
+        }
+
+        Ok(stale_blob_ids.len() as u64)
+    }
 
-            // skip entries that don't belong to this account/container
-            if blob_id.account != account || blob_id.container != container {
+    /// Remove blob index entries of the configured containers that are still unhashed, were first
+    /// indexed more than `older_than` seconds ago, and whose blob is gone or has failed to hash
+    /// `MAX_HASH_FAILURES` times. A container is skipped, recording a `prune_refused` event, if that
+    /// would remove more than `max_prune_percent` of its entries. Returns the number of entries
+    /// removed.
+    pub async fn prune_stale_stubs(
+        &self,
+        blob_storage_config: &BlobStorageConfig,
+        older_than: u64,
+        max_prune_percent: Option<u8>,
+    ) -> Result<u64> {
+        log::debug!("Pruning stale stubs...");
+
+        let cutoff = chrono::Utc::now().timestamp() - older_than as i64;
+
+        let mut n_pruned = 0;
+
+        for ContainerConfig {
+            account, container, ..
+        } in &blob_storage_config.containers
+        {
+            let mut n_entries = 0;
+            let mut old_stubs = Vec::new();
+
+            {
+                let rtx = self.db.begin_read()?;
+                let table = rtx.open_table(BLOB_INDEX_TABLE)?;
+                let failure_table = rtx.open_table(BLOB_HASH_FAILURE_TABLE)?;
+
+                for entry in table.iter()? {
+                    let (key, value) = entry?;
+                    let (blob_id, blob_info) =
+                        (BlobId::from(key.value()), BlobInfo::from(value.value()));
+
+                    // skip entries that don't belong to this account/container
+                    if blob_id.account != *account || blob_id.container != *container {
+                        continue;
+                    }
+
+                    n_entries += 1;
+
+                    if blob_info.hash.is_none() && blob_info.time_first_indexed < cutoff {
+                        let failures = failure_table.get(key.value())?.map_or(0, |v| v.value());
+                        old_stubs.push((blob_id, failures));
+                    }
+                }
+            }
+
+            if old_stubs.is_empty() {
                 continue;
             }
 
-            // remove entry if it no longer is included by the filter
-            if !filter.blob_is_match(&blob_id.name, blob_info.size) {
-                self.delete_blob_index_entry(&blob_id)?;
+            // stubs of blobs that still exist are usually just waiting their turn to be hashed
+            let blob_names = list_blob_names(&self.blob_service_client(account), container).await?;
+
+            let stale_blob_ids = old_stubs
+                .into_iter()
+                .filter(|(blob_id, failures)| {
+                    !blob_names.contains(&blob_id.name) || *failures >= MAX_HASH_FAILURES
+                })
+                .map(|(blob_id, _)| blob_id)
+                .collect::<Vec<_>>();
+
+            if let Some(max_prune_percent) = max_prune_percent {
+                if stale_blob_ids.len() * 100 > n_entries * max_prune_percent as usize {
+                    log::warn!(
+                        "Refusing to prune {} of {n_entries} stubs for account={account} container={container}, over the {max_prune_percent}% limit",
+                        stale_blob_ids.len(),
+                    );
+                    self.record_prune_refused(
+                        account,
+                        container,
+                        PruneCounts {
+                            stale: stale_blob_ids.len() as u64,
+                            entries: n_entries as u64,
+                            max_prune_percent,
+                        },
+                    )?;
+                    continue;
+                }
             }
 
-            // remove the entry if it no longer exists in the blob storage
-            if !blobs.iter().any(|blob| blob.name != blob_id.name) {
-                self.delete_blob_index_entry(&blob_id)?;
+            for blob_id in &stale_blob_ids {
+                self.delete_blob_index_entry(blob_id)?;
             }
+
+            n_pruned += stale_blob_ids.len() as u64;
         }
 
-        Ok(())
+        log::debug!("Finished pruning {n_pruned} stale stubs.");
+
+        Ok(n_pruned)
     }
 
-    fn update_blob_index_entry(
+    /// Create or update a blob's entry as part of the write transaction, returning the event to
+    /// publish once the transaction is committed, if any
+    fn write_blob_index_entry(
         &self,
+        wtx: &redb::WriteTransaction,
         blob_id: BlobId,
         new_blob_info: BlobInfo,
         current_blob_info: Option<BlobInfo>,
-    ) -> Result<()> {
+    ) -> Result<Option<RouteEvent>> {
         log::trace!(
             "{action} blob entry: account={account} container={container} name={name} t={timestamp} size={size}",
             action = if current_blob_info.is_some() { "Updating" } else { "Creating" },
@@ -573,10 +1596,23 @@ impl Db {
 
         let BlobInfo { hash: new_hash, .. } = new_blob_info;
 
+        let old_hash = current_blob_info
+            .as_ref()
+            .and_then(|blob_info| blob_info.hash);
+
+        let event = match (&current_blob_info, new_hash, old_hash) {
+            (None, _, _) => Some((RouteEventKind::Inserted, None)),
+            (Some(_), Some(new_hash), _) => Some((RouteEventKind::Completed, Some(new_hash))),
+            (Some(_), None, Some(old_hash)) => Some((RouteEventKind::Reset, Some(old_hash))),
+            (Some(_), None, None) => None,
+        };
+
         let blob_id = BlobIdTuple::from(blob_id);
         let new_blob_info = BlobInfoTuple::from(new_blob_info);
 
-        let wtx = self.db.begin_write()?;
+        let event = event
+            .map(|(kind, hash)| self.insert_event(wtx, kind, &blob_id, hash))
+            .transpose()?;
         {
             let mut table = wtx.open_table(BLOB_INDEX_TABLE)?;
             table.insert(&blob_id, new_blob_info)?;
@@ -591,54 +1627,875 @@ impl Db {
                     .remove(old_hash, &blob_id)?;
             }
 
-            // if present, insert the new hash into the hash index
-            if let Some(new_hash) = new_hash {
-                wtx.open_multimap_table(BLOB_HASH_INDEX_TABLE)?
-                    .insert(new_hash, blob_id)?;
+            // if present, insert the new hash into the hash index
+            if let Some(new_hash) = new_hash {
+                wtx.open_multimap_table(BLOB_HASH_INDEX_TABLE)?
+                    .insert(new_hash, blob_id)?;
+            }
+        }
+
+        Ok(event)
+    }
+
+    /// Record a blob's etag and content type as part of the write transaction changing its entry
+    fn write_blob_properties(
+        &self,
+        wtx: &redb::WriteTransaction,
+        blob_id: &BlobId,
+        etag: &str,
+        content_type: Option<&str>,
+    ) -> Result<()> {
+        let key = BlobIdTuple::from(blob_id.clone());
+
+        wtx.open_table(BLOB_ETAG_TABLE)?.insert(&key, etag)?;
+
+        let mut content_type_table = wtx.open_table(BLOB_CONTENT_TYPE_TABLE)?;
+        match content_type {
+            Some(content_type) => content_type_table.insert(key, content_type)?,
+            None => content_type_table.remove(key)?,
+        };
+
+        Ok(())
+    }
+
+    fn delete_blob_index_entry(&self, blob_id: &BlobId) -> Result<()> {
+        let wtx = self.db.begin_write()?;
+        let event = self.remove_blob_index_entry(&wtx, blob_id)?;
+        wtx.commit()?;
+
+        self.publish_event(event);
+
+        Ok(())
+    }
+
+    /// Remove a blob's entry as part of the write transaction, returning the `deleted` event to
+    /// publish once the transaction is committed
+    fn remove_blob_index_entry(
+        &self,
+        wtx: &redb::WriteTransaction,
+        blob_id: &BlobId,
+    ) -> Result<RouteEvent> {
+        log::trace!(
+            "Deleting blob entry: account={account} container={container} name={name}",
+            account = blob_id.account,
+            container = blob_id.container,
+            name = blob_id.name,
+        );
+
+        let blob_id = BlobIdTuple::from(blob_id.clone());
+
+        let blob_info = {
+            let mut table = wtx.open_table(BLOB_INDEX_TABLE)?;
+            let (account, container, name) = &blob_id;
+            let blob_info = table
+                .get(blob_id.clone())?
+                .map(|v| v.value())
+                .map(BlobInfo::from)
+                .ok_or_else(|| {
+                    anyhow!("blob info not found for account={account} container={container} name={name}")
+                })?;
+
+            table.remove(blob_id.clone())?;
+
+            wtx.open_table(BLOB_ETAG_TABLE)?.remove(blob_id.clone())?;
+            wtx.open_table(BLOB_CONTENT_TYPE_TABLE)?
+                .remove(blob_id.clone())?;
+            wtx.open_table(BLOB_SAMPLE_TABLE)?.remove(blob_id.clone())?;
+            wtx.open_table(BLOB_HASH_FAILURE_TABLE)?
+                .remove(blob_id.clone())?;
+
+            if let BlobInfo {
+                hash: Some(hash), ..
+            } = blob_info
+            {
+                wtx.open_multimap_table(BLOB_HASH_INDEX_TABLE)?
+                    .remove(hash, &blob_id)?;
+            }
+
+            blob_info
+        };
+
+        self.insert_event(wtx, RouteEventKind::Deleted, &blob_id, blob_info.hash)
+    }
+}
+
+impl Db {
+    /// Delete a blob's index entries, and so its route, and tombstone it so reindexing doesn't
+    /// add it back until it changes. Returns false if the blob isn't indexed.
+    pub fn tombstone_blob(&self, blob_id: &BlobId) -> Result<bool> {
+        let key = BlobIdTuple::from(blob_id.clone());
+
+        // the entry is removed and the tombstone added together, so a blob is never left without
+        // either for the indexer to add back
+        let wtx = self.db.begin_write()?;
+
+        if wtx.open_table(BLOB_INDEX_TABLE)?.get(&key)?.is_none() {
+            return Ok(false);
+        }
+
+        let etag = wtx
+            .open_table(BLOB_ETAG_TABLE)?
+            .get(&key)?
+            .map(|v| v.value().to_owned());
+
+        log::debug!(
+            "Tombstoning blob: account={} container={} name={}",
+            blob_id.account,
+            blob_id.container,
+            blob_id.name
+        );
+
+        let event = self.remove_blob_index_entry(&wtx, blob_id)?;
+        wtx.open_table(BLOB_TOMBSTONE_TABLE)?
+            .insert(key, (chrono::Utc::now().timestamp(), etag.as_deref()))?;
+        wtx.commit()?;
+
+        self.publish_event(event);
+
+        Ok(true)
+    }
+
+    /// Tombstone every indexed blob in a container, returning how many were tombstoned
+    pub fn tombstone_container(&self, account: &str, container: &str) -> Result<u64> {
+        let blob_ids = {
+            let rtx = self.db.begin_read()?;
+            let table = rtx.open_table(BLOB_INDEX_TABLE)?;
+
+            let mut blob_ids = Vec::new();
+
+            for entry in table.iter()? {
+                let blob_id = BlobId::from(entry?.0.value());
+
+                if blob_id.account == account && blob_id.container == container {
+                    blob_ids.push(blob_id);
+                }
+            }
+
+            blob_ids
+        };
+
+        for blob_id in &blob_ids {
+            self.tombstone_blob(blob_id)?;
+        }
+
+        Ok(blob_ids.len() as u64)
+    }
+
+    pub fn get_tombstone(&self, blob_id: &BlobId) -> Result<Option<Tombstone>> {
+        let rtx = self.db.begin_read()?;
+        let table = rtx.open_table(BLOB_TOMBSTONE_TABLE)?;
+
+        let tombstone = table.get(BlobIdTuple::from(blob_id.clone()))?.map(|v| {
+            let (tombstoned_at, etag) = v.value();
+
+            Tombstone {
+                account: blob_id.account.clone(),
+                container: blob_id.container.clone(),
+                name: blob_id.name.clone(),
+                tombstoned_at,
+                etag: etag.map(str::to_owned),
+            }
+        });
+
+        Ok(tombstone)
+    }
+
+    pub fn get_tombstones(&self) -> Result<Vec<Tombstone>> {
+        let rtx = self.db.begin_read()?;
+        let table = rtx.open_table(BLOB_TOMBSTONE_TABLE)?;
+
+        table
+            .iter()?
+            .map(|entry| {
+                let (key, value) = entry?;
+                let ((account, container, name), (tombstoned_at, etag)) =
+                    (key.value(), value.value());
+
+                Ok(Tombstone {
+                    account,
+                    container,
+                    name,
+                    tombstoned_at,
+                    etag: etag.map(str::to_owned),
+                })
+            })
+            .collect()
+    }
+
+    /// Remove a blob's tombstone so the next indexer pass indexes it again, returning false if
+    /// it has none
+    pub fn remove_tombstone(&self, blob_id: &BlobId) -> Result<bool> {
+        let wtx = self.db.begin_write()?;
+        let removed = {
+            let mut table = wtx.open_table(BLOB_TOMBSTONE_TABLE)?;
+            let removed = table.remove(BlobIdTuple::from(blob_id.clone()))?.is_some();
+            removed
+        };
+        wtx.commit()?;
+
+        Ok(removed)
+    }
+}
+
+impl Db {
+    /// Record a route event as part of the write transaction changing the blob's entry. The event
+    /// is published to subscribers with `publish_event` once the transaction is committed.
+    fn insert_event(
+        &self,
+        wtx: &redb::WriteTransaction,
+        kind: RouteEventKind,
+        blob_id: &BlobIdTuple,
+        hash: Option<HashBytes>,
+    ) -> Result<RouteEvent> {
+        let cid = hash.map(|hash| hash_to_cid(multihash::BLAKE3, &hash, multicodec::RAW));
+
+        self.append_event(wtx, kind, blob_id.clone(), cid)
+    }
+
+    /// Record an event for a blob, or for no blob with an empty ID, as part of the write
+    /// transaction
+    fn append_event(
+        &self,
+        wtx: &redb::WriteTransaction,
+        kind: RouteEventKind,
+        blob_id: BlobIdTuple,
+        cid: Option<String>,
+    ) -> Result<RouteEvent> {
+        let mut table = wtx.open_table(EVENT_TABLE)?;
+
+        let id = table.last()?.map(|(k, _)| k.value() + 1).unwrap_or(0);
+
+        let (account, container, name) = blob_id;
+
+        let event = RouteEvent {
+            id,
+            timestamp: chrono::Utc::now().timestamp(),
+            kind,
+            account,
+            container,
+            name,
+            cid,
+            prune: None,
+        };
+
+        table.insert(id, EventTuple::from(event.clone()))?;
+
+        if id >= EVENTS_RETAINED {
+            table.retain_in(..=(id - EVENTS_RETAINED), |_, _| false)?;
+            wtx.open_table(EVENT_PRUNE_TABLE)?
+                .retain_in(..=(id - EVENTS_RETAINED), |_, _| false)?;
+        }
+
+        Ok(event)
+    }
+
+    /// Record and publish a `prune_refused` event for a container
+    fn record_prune_refused(
+        &self,
+        account: &str,
+        container: &str,
+        counts: PruneCounts,
+    ) -> Result<()> {
+        let wtx = self.db.begin_write()?;
+        let mut event = self.append_event(
+            &wtx,
+            RouteEventKind::PruneRefused,
+            (account.to_owned(), container.to_owned(), String::new()),
+            None,
+        )?;
+        wtx.open_table(EVENT_PRUNE_TABLE)?
+            .insert(event.id, PruneCountsTuple::from(counts))?;
+        wtx.commit()?;
+
+        event.prune = Some(counts);
+        self.publish_event(event);
+
+        Ok(())
+    }
+
+    fn publish_event(&self, event: RouteEvent) {
+        // fails only when nothing is subscribed
+        let _ = self.events.send(event);
+    }
+
+    /// Receive route events as they're recorded
+    pub fn subscribe_events(&self) -> broadcast::Receiver<RouteEvent> {
+        self.events.subscribe()
+    }
+
+    pub fn get_next_event_id(&self) -> Result<u64> {
+        let rtx = self.db.begin_read()?;
+        let table = rtx.open_table(EVENT_TABLE)?;
+
+        let next_id = table.last()?.map(|(k, _)| k.value() + 1).unwrap_or(0);
+
+        Ok(next_id)
+    }
+
+    /// Get up to `limit` retained events, starting from event ID `from`
+    pub fn get_events(&self, from: u64, limit: usize) -> Result<Vec<RouteEvent>> {
+        let rtx = self.db.begin_read()?;
+        let table = rtx.open_table(EVENT_TABLE)?;
+        let prune_table = rtx.open_table(EVENT_PRUNE_TABLE)?;
+
+        let events = table
+            .range(from..)?
+            .take(limit)
+            .map(|entry| {
+                let (key, value) = entry?;
+                let mut event = RouteEvent::from_tuple(key.value(), value.value())?;
+                event.prune = prune_table
+                    .get(key.value())?
+                    .map(|v| PruneCounts::from(v.value()));
+                Ok(event)
+            })
+            .collect();
+
+        events
+    }
+
+    pub fn get_webhook_cursor(&self, url: &str) -> Result<Option<u64>> {
+        let rtx = self.db.begin_read()?;
+        let table = rtx.open_table(WEBHOOK_CURSOR_TABLE)?;
+
+        Ok(table.get(url)?.map(|v| v.value()))
+    }
+
+    pub fn set_webhook_cursor(&self, url: &str, cursor: u64) -> Result<()> {
+        let wtx = self.db.begin_write()?;
+        {
+            let mut table = wtx.open_table(WEBHOOK_CURSOR_TABLE)?;
+            table.insert(url, cursor)?;
+        }
+        wtx.commit()?;
+
+        Ok(())
+    }
+}
+
+impl Db {
+    /// Watch a CID until it gets a route, replacing any existing watch for it
+    pub fn insert_watch(&self, cid: &Cid, deadline: Option<i64>) -> Result<Watch> {
+        let watch = Watch {
+            cid: cid.to_string(),
+            watched_at: chrono::Utc::now().timestamp(),
+            deadline,
+            expired: false,
+        };
+
+        let wtx = self.db.begin_write()?;
+        {
+            let mut table = wtx.open_table(WATCH_TABLE)?;
+            table.insert(
+                watch.cid.as_str(),
+                (watch.watched_at, watch.deadline, watch.expired),
+            )?;
+        }
+        wtx.commit()?;
+
+        Ok(watch)
+    }
+
+    pub fn get_watches(&self) -> Result<Vec<Watch>> {
+        let rtx = self.db.begin_read()?;
+        let table = rtx.open_table(WATCH_TABLE)?;
+
+        table
+            .iter()?
+            .map(|entry| {
+                let (key, value) = entry?;
+                let (watched_at, deadline, expired) = value.value();
+
+                Ok(Watch {
+                    cid: key.value().to_owned(),
+                    watched_at,
+                    deadline,
+                    expired,
+                })
+            })
+            .collect()
+    }
+
+    /// Stop watching a CID, returning false if it isn't watched
+    pub fn remove_watch(&self, cid: &Cid) -> Result<bool> {
+        let wtx = self.db.begin_write()?;
+        let removed = {
+            let mut table = wtx.open_table(WATCH_TABLE)?;
+            let removed = table.remove(cid.to_string().as_str())?.is_some();
+            removed
+        };
+        wtx.commit()?;
+
+        Ok(removed)
+    }
+
+    /// Record a `watch_found` event for each watched CID that has a route, which stops watching
+    /// it, and a `watch_expired` event for each whose deadline has passed without one, once.
+    /// Returns the number of events recorded.
+    pub fn check_watches(&self) -> Result<u64> {
+        let now = chrono::Utc::now().timestamp();
+
+        let mut recorded = 0;
+
+        for watch in self.get_watches()? {
+            let blob_id = self
+                .get_blob_ids_and_infos_for_cid(watch.cid.as_str())?
+                .into_iter()
+                .next()
+                .map(|(blob_id, _)| BlobIdTuple::from(blob_id));
+
+            let kind = match (&blob_id, watch.deadline) {
+                (Some(_), _) => RouteEventKind::WatchFound,
+                (None, Some(deadline)) if deadline <= now && !watch.expired => {
+                    RouteEventKind::WatchExpired
+                }
+                _ => continue,
+            };
+
+            let wtx = self.db.begin_write()?;
+            {
+                let mut table = wtx.open_table(WATCH_TABLE)?;
+
+                // removed or replaced through the API since it was read
+                let current = table.get(watch.cid.as_str())?.map(|v| v.value());
+                if current != Some((watch.watched_at, watch.deadline, watch.expired)) {
+                    continue;
+                }
+
+                match kind {
+                    RouteEventKind::WatchFound => {
+                        table.remove(watch.cid.as_str())?;
+                    }
+                    _ => {
+                        table
+                            .insert(watch.cid.as_str(), (watch.watched_at, watch.deadline, true))?;
+                    }
+                }
+            }
+            // a CID that's still missing has no blob to name
+            let event =
+                self.append_event(&wtx, kind, blob_id.unwrap_or_default(), Some(watch.cid))?;
+            wtx.commit()?;
+
+            self.publish_event(event);
+
+            recorded += 1;
+        }
+
+        Ok(recorded)
+    }
+}
+
+impl Db {
+    /// Record a new running indexer job, picking up from an unfinished job if any, and drop the
+    /// oldest jobs beyond the retention limit
+    pub fn create_job(&self, account: &str, container: &str, resumes: Option<u64>) -> Result<Job> {
+        let wtx = self.db.begin_write()?;
+        let job = {
+            let mut table = wtx.open_table(JOB_TABLE)?;
+            let mut phase_table = wtx.open_table(JOB_PHASE_TABLE)?;
+            let mut resumes_table = wtx.open_table(JOB_RESUMES_TABLE)?;
+
+            let id = table.last()?.map(|(k, _)| k.value() + 1).unwrap_or(0);
+
+            let job = Job {
+                id,
+                account: account.to_owned(),
+                container: container.to_owned(),
+                started_at: chrono::Utc::now().timestamp(),
+                finished_at: None,
+                state: JobState::Running,
+                phase: None,
+                resumes,
+                items_indexed: 0,
+                errors: vec![],
+            };
+
+            table.insert(id, JobTuple::from(job.clone()))?;
+
+            if let Some(resumes) = resumes {
+                resumes_table.insert(id, resumes)?;
+            }
+
+            if id >= JOBS_RETAINED {
+                let dropped = ..=(id - JOBS_RETAINED);
+                table.retain_in(dropped, |_, _| false)?;
+                phase_table.retain_in(dropped, |_, _| false)?;
+                resumes_table.retain_in(dropped, |_, _| false)?;
+            }
+
+            job
+        };
+        wtx.commit()?;
+
+        Ok(job)
+    }
+
+    /// Journal a job's progress. Jobs that have already been stopped, e.g. by a shutdown while
+    /// the job's step was still running, are left as they are.
+    pub fn update_job(&self, job: &Job) -> Result<()> {
+        let wtx = self.db.begin_write()?;
+        {
+            let mut table = wtx.open_table(JOB_TABLE)?;
+
+            let stopped = match table.get(job.id)? {
+                Some(v) => JobState::from_str(&v.value().4)?.is_unfinished(),
+                None => false,
+            };
+
+            if !stopped {
+                table.insert(job.id, JobTuple::from(job.clone()))?;
+
+                let mut phase_table = wtx.open_table(JOB_PHASE_TABLE)?;
+                match job.phase {
+                    Some(phase) => phase_table.insert(job.id, phase.as_str())?,
+                    None => phase_table.remove(job.id)?,
+                };
+            }
+        }
+        wtx.commit()?;
+
+        Ok(())
+    }
+
+    /// Mark jobs left running by a previous process as interrupted
+    pub fn interrupt_running_jobs(&self) -> Result<()> {
+        for job in self.stop_running_jobs(JobState::Interrupted)? {
+            log::warn!(
+                "Indexer job={} was interrupted in phase={:?}, the previous process didn't shut down cleanly",
+                job.id,
+                job.phase
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Mark running jobs as aborted by a shutdown and record a report of the jobs run since
+    /// `started_at`
+    pub fn record_shutdown(&self, started_at: i64) -> Result<ShutdownReport> {
+        let aborted = self.stop_running_jobs(JobState::Aborted)?;
+
+        let completed = self
+            .get_jobs()?
+            .into_iter()
+            .filter(|job| job.started_at >= started_at && job.state == JobState::Completed)
+            .map(|job| job.id)
+            .collect();
+
+        let report = ShutdownReport {
+            started_at,
+            stopped_at: chrono::Utc::now().timestamp(),
+            completed,
+            aborted: aborted.iter().map(|job| job.id).collect(),
+            resumable: aborted
+                .iter()
+                .filter(|job| job.phase > Some(JobPhase::ListingBlobs))
+                .map(|job| job.id)
+                .collect(),
+        };
+
+        let wtx = self.db.begin_write()?;
+        {
+            let mut table = wtx.open_table(SHUTDOWN_REPORT_TABLE)?;
+            table.insert(
+                LAST_SHUTDOWN_REPORT_KEY,
+                ShutdownReportTuple::from(report.clone()),
+            )?;
+        }
+        wtx.commit()?;
+
+        Ok(report)
+    }
+
+    pub fn get_last_shutdown_report(&self) -> Result<Option<ShutdownReport>> {
+        let rtx = self.db.begin_read()?;
+        let table = rtx.open_table(SHUTDOWN_REPORT_TABLE)?;
+
+        Ok(table
+            .get(LAST_SHUTDOWN_REPORT_KEY)?
+            .map(|v| ShutdownReport::from(v.value())))
+    }
+
+    /// Most recent job for a container if it stopped without finishing
+    pub fn get_unfinished_job(&self, account: &str, container: &str) -> Result<Option<Job>> {
+        let last_job = self
+            .get_jobs()?
+            .into_iter()
+            .find(|job| job.account == account && job.container == container);
+
+        Ok(last_job.filter(|job| job.state.is_unfinished()))
+    }
+
+    /// Set running jobs to the state, returning them
+    fn stop_running_jobs(&self, state: JobState) -> Result<Vec<Job>> {
+        let running_jobs = self
+            .get_jobs()?
+            .into_iter()
+            .filter(|job| job.state == JobState::Running)
+            .map(|job| Job { state, ..job })
+            .collect::<Vec<_>>();
+
+        let wtx = self.db.begin_write()?;
+        {
+            let mut table = wtx.open_table(JOB_TABLE)?;
+            for job in &running_jobs {
+                table.insert(job.id, JobTuple::from(job.clone()))?;
+            }
+        }
+        wtx.commit()?;
+
+        Ok(running_jobs)
+    }
+
+    /// Get all retained jobs, most recent first
+    pub fn get_jobs(&self) -> Result<Vec<Job>> {
+        let rtx = self.db.begin_read()?;
+        let table = rtx.open_table(JOB_TABLE)?;
+        let phase_table = rtx.open_table(JOB_PHASE_TABLE)?;
+        let resumes_table = rtx.open_table(JOB_RESUMES_TABLE)?;
+
+        table
+            .iter()?
+            .rev()
+            .map(|entry| {
+                let (key, value) = entry?;
+                let id = key.value();
+                Job::from_tuple(
+                    id,
+                    value.value(),
+                    phase_table.get(id)?.as_ref().map(|v| v.value()),
+                    resumes_table.get(id)?.map(|v| v.value()),
+                )
+            })
+            .collect()
+    }
+
+    pub fn get_job(&self, id: u64) -> Result<Option<Job>> {
+        let rtx = self.db.begin_read()?;
+        let table = rtx.open_table(JOB_TABLE)?;
+        let phase_table = rtx.open_table(JOB_PHASE_TABLE)?;
+        let resumes_table = rtx.open_table(JOB_RESUMES_TABLE)?;
+
+        table
+            .get(id)?
+            .map(|v| {
+                Job::from_tuple(
+                    id,
+                    v.value(),
+                    phase_table.get(id)?.as_ref().map(|v| v.value()),
+                    resumes_table.get(id)?.map(|v| v.value()),
+                )
+            })
+            .transpose()
+    }
+}
+
+/// CIDv1 string for a digest of the given multihash code and codec
+fn hash_to_cid(code: u64, digest: &[u8], codec: u64) -> String {
+    let multihash = Multihash::wrap(code, digest).expect("unexpectedly failed to wrap a multihash");
+
+    Cid::new_v1(codec, multihash).to_string()
+}
+
+/// Hashes of a blob's content
+struct BlobHashes {
+    blake3: HashBytes,
+    /// Only computed for containers configured with `sha256 = true`
+    sha256: Option<HashBytes>,
+    /// Blake3 hash of the blob's first and last `SAMPLE_LEN` bytes and its size, which is cheap to
+    /// recompute with two range requests and so can be used as a "probably the same blob" check
+    /// before committing to a full rehash
+    sample: HashBytes,
+}
+
+/// Blob storage client options sending requests through the proxy if one is configured, or
+/// through the environment's proxy otherwise
+fn client_options(proxy: Option<&ProxyConfig>) -> Result<ClientOptions> {
+    let Some(ProxyConfig { url, no_proxy }) = proxy else {
+        return Ok(ClientOptions::default());
+    };
+
+    let proxy = azure_reqwest::Proxy::all(url)?.no_proxy(
+        no_proxy
+            .as_deref()
+            .and_then(azure_reqwest::NoProxy::from_string),
+    );
+
+    let http_client = azure_reqwest::Client::builder().proxy(proxy).build()?;
+
+    Ok(ClientOptions::default().transport(TransportOptions::new(Arc::new(http_client))))
+}
+
+/// Names of all blobs in a container
+async fn list_blob_names(
+    blob_service: &BlobServiceClient,
+    container: &str,
+) -> Result<HashSet<String>> {
+    let container_client = blob_service.container_client(container.to_owned());
+
+    let mut pages = container_client
+        .list_blobs()
+        .max_results(NonZeroU32::new(10 * 1000).unwrap())
+        .into_stream();
+
+    let mut blob_names = HashSet::new();
+
+    while let Some(response) = pages.next().await {
+        blob_names.extend(response?.blobs.blobs().map(|blob| blob.name.clone()));
+    }
+
+    Ok(blob_names)
+}
+
+/// Stream a blob to compute its hashes
+async fn compute_blob_hashes(
+    blob_service: &BlobServiceClient,
+    blob_id: &BlobId,
+    size: u64,
+    sha256: bool,
+) -> Result<BlobHashes> {
+    let BlobId {
+        account,
+        container,
+        name,
+    } = blob_id;
+
+    log::trace!(
+        "Streaming blob to compute hash: size={size} account={account} container={container} name={name}"
+    );
+
+    let mut hasher = blake3::Hasher::new();
+    let mut sha256_hasher = sha256.then(Sha256::new);
+    let mut head = Vec::with_capacity(SAMPLE_LEN);
+    let mut tail = Vec::with_capacity(2 * SAMPLE_LEN);
+
+    if size == 0 {
+        hasher.update(&[]);
+    } else {
+        let container_client = blob_service.container_client(container);
+        let blob_client = container_client.blob_client(name);
+        let mut blob_stream = blob_client.get().into_stream();
+
+        while let Some(chunk_response) = blob_stream.next().await {
+            let chunk_response = chunk_response?;
+            let chunk = chunk_response.data.collect().await?;
+
+            hasher.update(&chunk);
+            if let Some(sha256_hasher) = &mut sha256_hasher {
+                sha256_hasher.update(&chunk);
+            }
+
+            if head.len() < SAMPLE_LEN {
+                let n = (SAMPLE_LEN - head.len()).min(chunk.len());
+                head.extend_from_slice(&chunk[..n]);
             }
-        }
-        wtx.commit()?;
 
-        Ok(())
+            tail.extend_from_slice(&chunk[chunk.len().saturating_sub(SAMPLE_LEN)..]);
+            if tail.len() > SAMPLE_LEN {
+                tail.drain(..tail.len() - SAMPLE_LEN);
+            }
+        }
     }
 
-    fn delete_blob_index_entry(&self, blob_id: &BlobId) -> Result<()> {
-        log::trace!(
-            "Deleting blob entry: account={account} container={container} name={name}",
-            account = blob_id.account,
-            container = blob_id.container,
-            name = blob_id.name,
-        );
+    let hash = hasher.finalize().as_bytes().to_owned();
+    let sha256 = sha256_hasher.map(|sha256_hasher| sha256_hasher.finalize().into());
+
+    let sample = blake3::Hasher::new()
+        .update(&head)
+        .update(&tail)
+        .update(&size.to_le_bytes())
+        .finalize()
+        .as_bytes()
+        .to_owned();
+
+    log::trace!(
+        "Computed hash={hash} sha256={sha256:?} sample={sample} for blob: account={account} container={container} name={name}",
+        hash = hex::encode(hash),
+        sha256 = sha256.map(hex::encode),
+        sample = hex::encode(sample),
+    );
+
+    Ok(BlobHashes {
+        blake3: hash,
+        sha256,
+        sample,
+    })
+}
 
-        let blob_id = BlobIdTuple::from(blob_id.clone());
+// TODO: re-org this a bit, split the view (hashes becoming cids for the table view) from the logic
+//       probably have separate "db" entry type and "ascii table row" type
+/// Counts and sizes of what's indexed
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct DbStats {
+    pub containers: Vec<ContainerStats>,
+    /// Hashed blobs, each of which has a route
+    pub routes: u64,
+    /// Blobs listed but not hashed yet
+    pub stubs: u64,
+    /// Total size of the hashed blobs, in bytes
+    pub indexed_bytes: u64,
+    /// Iroh collections indexed
+    pub collections: u64,
+    /// CIDs of content indexed for more than one blob
+    pub duplicate_cids: u64,
+    /// Size of the database file, in bytes
+    pub db_file_size: u64,
+}
 
-        let wtx = self.db.begin_write()?;
-        {
-            let mut table = wtx.open_table(BLOB_INDEX_TABLE)?;
-            let blob_info = table
-                .get(blob_id.clone())?
-                .map(|v| v.value())
-                .map(BlobInfo::from)
-                .expect("blob info not found");
+#[derive(Debug, Clone, Serialize, ToSchema, Tabled)]
+pub struct ContainerStats {
+    pub account: String,
+    pub container: String,
+    pub routes: u64,
+    pub stubs: u64,
+    pub indexed_bytes: u64,
+}
 
-            table.remove(blob_id.clone())?;
+/// Blobs with the same content
+#[derive(Debug, Clone)]
+pub struct DuplicateBlobs {
+    pub cid: String,
+    /// Size of the content, in bytes
+    pub size: u64,
+    pub blobs: Vec<(BlobId, BlobInfo)>,
+}
 
-            if let BlobInfo {
-                hash: Some(hash), ..
-            } = blob_info
-            {
-                wtx.open_multimap_table(BLOB_HASH_INDEX_TABLE)?
-                    .remove(hash, blob_id)?;
-            }
-        }
-        wtx.commit()?;
+impl DuplicateBlobs {
+    /// Bytes taken by all but one copy of the content
+    pub fn redundant_bytes(&self) -> u64 {
+        self.size * (self.blobs.len() as u64).saturating_sub(1)
+    }
+}
 
-        Ok(())
+/// Filter on blob index entries, unset fields match every entry
+#[derive(Debug, Clone, Default, Deserialize, IntoParams)]
+pub struct BlobEntryFilter {
+    pub account: Option<String>,
+    pub container: Option<String>,
+    /// Minimum size in bytes
+    pub min_size: Option<u64>,
+    /// Maximum size in bytes
+    pub max_size: Option<u64>,
+    /// Unix timestamp the blob was first indexed at or after
+    pub indexed_after: Option<i64>,
+    /// Unix timestamp the blob was first indexed before
+    pub indexed_before: Option<i64>,
+}
+
+impl BlobEntryFilter {
+    fn matches(&self, account: &str, container: &str, info: &BlobInfoTuple) -> bool {
+        let (_, size, _, time_first_indexed, _) = *info;
+
+        self.account.as_deref().map_or(true, |a| a == account)
+            && self.container.as_deref().map_or(true, |c| c == container)
+            && self.min_size.map_or(true, |min| size >= min)
+            && self.max_size.map_or(true, |max| size <= max)
+            && self.indexed_after.map_or(true, |t| time_first_indexed >= t)
+            && self.indexed_before.map_or(true, |t| time_first_indexed < t)
     }
 }
 
-// TODO: re-org this a bit, split the view (hashes becoming cids for the table view) from the logic
-//       probably have separate "db" entry type and "ascii table row" type
 #[derive(Tabled)]
 pub struct BlobEntryTableRow {
     pub size: u64,
@@ -684,7 +2541,80 @@ pub struct CollectionEntryTableRow {
 }
 
 impl Db {
+    pub fn get_stats(&self) -> Result<DbStats> {
+        let mut containers = BTreeMap::<(String, String), ContainerStats>::new();
+
+        let collections = {
+            let rtx = self.db.begin_read()?;
+            let table = rtx.open_table(BLOB_INDEX_TABLE)?;
+
+            for entry in table.iter()? {
+                let (key, value) = entry?;
+                let ((account, container, _), blob_info) =
+                    (key.value(), BlobInfo::from(value.value()));
+
+                let stats = containers
+                    .entry((account.clone(), container.clone()))
+                    .or_insert_with(|| ContainerStats {
+                        account,
+                        container,
+                        routes: 0,
+                        stubs: 0,
+                        indexed_bytes: 0,
+                    });
+
+                match blob_info.hash {
+                    Some(_) => {
+                        stats.routes += 1;
+                        stats.indexed_bytes += blob_info.size;
+                    }
+                    None => stats.stubs += 1,
+                }
+            }
+
+            rtx.open_table(COLLECTION_INDEX_TABLE)?.len()?
+        };
+
+        // counted the same way as the duplicate report, so dangling hash index entries are skipped
+        let duplicate_cids = self.get_duplicate_blobs()?.len() as u64;
+
+        let containers = containers.into_values().collect::<Vec<_>>();
+
+        Ok(DbStats {
+            routes: containers.iter().map(|c| c.routes).sum(),
+            stubs: containers.iter().map(|c| c.stubs).sum(),
+            indexed_bytes: containers.iter().map(|c| c.indexed_bytes).sum(),
+            containers,
+            collections,
+            duplicate_cids,
+            db_file_size: std::fs::metadata(&self.file)?.len(),
+        })
+    }
+
+    pub fn get_stats_ascii_table(&self) -> Result<String> {
+        let stats = self.get_stats()?;
+
+        let table = Table::new(&stats.containers)
+            .with(Style::sharp())
+            .with(Alignment::left())
+            .to_string();
+
+        Ok(format!(
+            "{table}\nroutes={} stubs={} indexed_bytes={} collections={} duplicate_cids={} db_file_size={}",
+            stats.routes,
+            stats.stubs,
+            stats.indexed_bytes,
+            stats.collections,
+            stats.duplicate_cids,
+            stats.db_file_size
+        ))
+    }
+
     pub fn get_all_blob_entries(&self) -> Result<Vec<BlobEntryTableRow>> {
+        self.get_blob_entries(&BlobEntryFilter::default())
+    }
+
+    pub fn get_blob_entries(&self, filter: &BlobEntryFilter) -> Result<Vec<BlobEntryTableRow>> {
         let rtx = self.db.begin_read()?;
         let table = rtx.open_table(BLOB_INDEX_TABLE)?;
 
@@ -695,14 +2625,15 @@ impl Db {
             let (key, value) = (key.value(), value.value());
 
             let (account, container, name) = key;
+
+            if !filter.matches(&account, &container, &value) {
+                continue;
+            }
+
             let (timestamp, size, hash, time_first_indexed, time_last_checked) = value;
 
             let cid = hash
-                .map(|hash| {
-                    let multihash = Multihash::wrap(0x1e, &hash)
-                        .expect("unexpectedly failed to wrap a multihash");
-                    Cid::new_v1(0x55, multihash).to_string()
-                })
+                .map(|hash| hash_to_cid(multihash::BLAKE3, &hash, multicodec::RAW))
                 .unwrap_or_default();
 
             let account = account.to_string();
@@ -725,7 +2656,11 @@ impl Db {
     }
 
     pub fn get_all_blob_entries_ascii_table(&self) -> Result<String> {
-        let entries = self.get_all_blob_entries()?;
+        self.get_blob_entries_ascii_table(&BlobEntryFilter::default())
+    }
+
+    pub fn get_blob_entries_ascii_table(&self, filter: &BlobEntryFilter) -> Result<String> {
+        let entries = self.get_blob_entries(filter)?;
 
         let table = Table::new(entries)
             .with(Style::sharp())
@@ -735,13 +2670,33 @@ impl Db {
         Ok(table)
     }
 
+    /// Blake3 hash of the content a CID refers to.
+    /// sha256 CIDs are resolved through the sha256 equivalence table, returns `None` for sha256
+    /// CIDs of content that hasn't been indexed and for CIDs of other hash functions.
+    fn get_blake3_hash_for_cid(&self, cid: &Cid) -> Result<Option<HashBytes>> {
+        let digest: HashBytes = cid.hash().digest().try_into()?;
+
+        match cid.hash().code() {
+            multihash::BLAKE3 => Ok(Some(digest)),
+            multihash::SHA256 => {
+                let rtx = self.db.begin_read()?;
+                let table = rtx.open_table(SHA256_EQUIVALENCE_TABLE)?;
+
+                Ok(table.get(digest)?.map(|v| v.value()))
+            }
+            _ => Ok(None),
+        }
+    }
+
     pub fn get_blob_ids_for_cid<T>(&self, cid: T) -> Result<Vec<BlobId>>
     where
         Cid: TryFrom<T, Error = cid::Error>,
     {
         let cid = Cid::try_from(cid)?;
 
-        let hash: [u8; 32] = cid.hash().digest().try_into()?;
+        let Some(hash) = self.get_blake3_hash_for_cid(&cid)? else {
+            return Ok(Vec::new());
+        };
 
         let rtx = self.db.begin_read()?;
         let table = rtx.open_multimap_table(BLOB_HASH_INDEX_TABLE)?;
@@ -757,13 +2712,66 @@ impl Db {
         Ok(entries)
     }
 
+    /// Content-Type recorded for a blob when it was indexed, if blob storage reported one
+    pub fn get_blob_content_type(&self, blob_id: &BlobId) -> Result<Option<String>> {
+        let rtx = self.db.begin_read()?;
+        let table = rtx.open_table(BLOB_CONTENT_TYPE_TABLE)?;
+
+        Ok(table
+            .get(BlobIdTuple::from(blob_id.clone()))?
+            .map(|v| v.value().to_owned()))
+    }
+
+    /// Sample fingerprint recorded for a blob when it was last hashed
+    pub fn get_blob_sample(&self, blob_id: &BlobId) -> Result<Option<HashBytes>> {
+        let rtx = self.db.begin_read()?;
+        let table = rtx.open_table(BLOB_SAMPLE_TABLE)?;
+
+        Ok(table
+            .get(BlobIdTuple::from(blob_id.clone()))?
+            .map(|v| v.value()))
+    }
+
+    /// Info of a blob in the blob index, or failing that in the iroh collection index
+    pub fn get_blob_info(&self, blob_id: &BlobId) -> Result<Option<BlobInfo>> {
+        let rtx = self.db.begin_read()?;
+        let blob_table = rtx.open_table(BLOB_INDEX_TABLE)?;
+        let collection_table = rtx.open_table(COLLECTION_INDEX_TABLE)?;
+
+        let key = BlobIdTuple::from(blob_id.clone());
+
+        let blob_info = match blob_table.get(key.clone())? {
+            Some(v) => Some(v.value()),
+            None => collection_table.get(key)?.map(|v| v.value()),
+        };
+
+        Ok(blob_info.map(BlobInfo::from))
+    }
+
+    /// Blob at a storage URL along with its index entry, which its route is built from, if it's
+    /// indexed
+    pub fn routes_for_url(&self, url: &str) -> Result<(BlobId, Option<BlobInfo>)> {
+        let blob_id = BlobId::from_url(url)?;
+
+        let rtx = self.db.begin_read()?;
+        let table = rtx.open_table(BLOB_INDEX_TABLE)?;
+
+        let blob_info = table
+            .get(BlobIdTuple::from(blob_id.clone()))?
+            .map(|v| BlobInfo::from(v.value()));
+
+        Ok((blob_id, blob_info))
+    }
+
     pub fn get_blob_ids_and_infos_for_cid<T>(&self, cid: T) -> Result<Vec<(BlobId, BlobInfo)>>
     where
         Cid: TryFrom<T, Error = cid::Error>,
     {
         let cid = Cid::try_from(cid)?;
 
-        let hash: [u8; 32] = cid.hash().digest().try_into()?;
+        let Some(hash) = self.get_blake3_hash_for_cid(&cid)? else {
+            return Ok(Vec::new());
+        };
 
         let rtx = self.db.begin_read()?;
         let blob_hash_table = rtx.open_multimap_table(BLOB_HASH_INDEX_TABLE)?;
@@ -777,11 +2785,15 @@ impl Db {
             let rtx = self.db.begin_read()?;
             let table = rtx.open_table(BLOB_INDEX_TABLE)?;
 
-            let blob_info = table
+            let Some(blob_info) = table
                 .get(BlobIdTuple::from(blob_id.clone()))?
                 .map(|v| v.value())
                 .map(BlobInfo::from)
-                .expect("blob info not found");
+            else {
+                let (account, container, name) = &blob_id;
+                log::warn!("Skipping hash index entry without blob info: account={account} container={container} name={name}");
+                continue;
+            };
 
             entries.push((BlobId::from(blob_id), blob_info));
         }
@@ -792,11 +2804,15 @@ impl Db {
             let rtx = self.db.begin_read()?;
             let table = rtx.open_table(COLLECTION_INDEX_TABLE)?;
 
-            let blob_info = table
+            let Some(blob_info) = table
                 .get(blob_id.clone())?
                 .map(|v| v.value())
                 .map(BlobInfo::from)
-                .expect("blob info not found");
+            else {
+                let (account, container, name) = &blob_id;
+                log::warn!("Skipping collection hash index entry without collection info: account={account} container={container} name={name}");
+                continue;
+            };
 
             entries.push((BlobId::from(blob_id), blob_info));
         }
@@ -804,6 +2820,51 @@ impl Db {
         Ok(entries)
     }
 
+    /// Directory of the iroh collection with the CID's content, along with its children in
+    /// collection order, as recorded when the collection was indexed. `None` if the CID isn't an
+    /// indexed collection.
+    pub fn get_collection_children(
+        &self,
+        cid: &Cid,
+    ) -> Result<Option<(BlobId, Vec<CollectionChild>)>> {
+        let Some(hash) = self.get_blake3_hash_for_cid(cid)? else {
+            return Ok(None);
+        };
+
+        let rtx = self.db.begin_read()?;
+
+        // a collection with the same content in more than one directory has the same children
+        let collection_id = rtx
+            .open_multimap_table(COLLECTION_HASH_INDEX_TABLE)?
+            .get(hash)?
+            .next()
+            .transpose()?
+            .map(|v| BlobId::from(v.value()));
+
+        let Some(collection_id) = collection_id else {
+            return Ok(None);
+        };
+
+        let mut children = rtx
+            .open_multimap_table(COLLECTION_CHILD_TABLE)?
+            .get(hash)?
+            .map(|entry| {
+                let entry = entry?;
+                let (name, hash, size) = entry.value();
+                Ok(CollectionChild {
+                    name: name.to_owned(),
+                    cid: hash_to_cid(multihash::BLAKE3, &hash, multicodec::RAW),
+                    size,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        // alphabetical order of names, as in the collection
+        children.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(Some((collection_id, children)))
+    }
+
     fn get_all_hash_entry_groups(&self) -> Result<HashMap<HashBytes, Vec<BlobId>>> {
         let rtx = self.db.begin_read()?;
         let table = rtx.open_multimap_table(BLOB_HASH_INDEX_TABLE)?;
@@ -830,15 +2891,57 @@ impl Db {
         Ok(groups)
     }
 
+    /// Hashed blobs sharing their content with another blob, most redundant bytes first
+    pub fn get_duplicate_blobs(&self) -> Result<Vec<DuplicateBlobs>> {
+        let mut duplicates = Vec::new();
+
+        for (hash, blob_ids) in self.get_all_hash_entry_groups()? {
+            if blob_ids.len() < 2 {
+                continue;
+            }
+
+            let mut blobs = Vec::new();
+
+            for blob_id in blob_ids {
+                let Some(blob_info) = self.get_blob_info(&blob_id)? else {
+                    let BlobId {
+                        account,
+                        container,
+                        name,
+                    } = &blob_id;
+                    log::warn!("Skipping hash index entry without blob info: account={account} container={container} name={name}");
+                    continue;
+                };
+
+                blobs.push((blob_id, blob_info));
+            }
+
+            // dangling entries can leave a single copy of the content
+            if blobs.len() < 2 {
+                continue;
+            }
+
+            duplicates.push(DuplicateBlobs {
+                cid: hash_to_cid(multihash::BLAKE3, &hash, multicodec::RAW),
+                size: blobs.first().map_or(0, |(_, blob_info)| blob_info.size),
+                blobs,
+            });
+        }
+
+        duplicates.sort_by(|a, b| {
+            b.redundant_bytes()
+                .cmp(&a.redundant_bytes())
+                .then_with(|| a.cid.cmp(&b.cid))
+        });
+
+        Ok(duplicates)
+    }
+
     pub fn get_all_hash_entries(&self) -> Result<Vec<HashEntryTableRow>> {
         let mut entries = Vec::new();
 
         for (hash, blob_ids) in self.get_all_hash_entry_groups()?.into_iter().sorted() {
-            let cid = {
-                let multihash =
-                    Multihash::wrap(0x1e, &hash).expect("unexpectedly failed to wrap a multihash");
-                Cid::new_v1(0x55, multihash).to_string()
-            };
+            let cid = hash_to_cid(multihash::BLAKE3, &hash, multicodec::RAW);
 
             let n = blob_ids.len();
             for (i, blob_id) in blob_ids.into_iter().enumerate() {
@@ -885,11 +2988,7 @@ impl Db {
         let mut entries = Vec::new();
 
         for (hash, blob_ids) in self.get_all_hash_entry_groups()?.into_iter().sorted() {
-            let cid = {
-                let multihash =
-                    Multihash::wrap(0x1e, &hash).expect("unexpectedly failed to wrap a multihash");
-                Cid::new_v1(0x55, multihash).to_string()
-            };
+            let cid = hash_to_cid(multihash::BLAKE3, &hash, multicodec::RAW);
 
             let n = blob_ids.len();
             for (i, blob_id) in blob_ids.into_iter().enumerate() {
@@ -904,11 +3003,19 @@ impl Db {
                 let rtx = self.db.begin_read()?;
                 let table = rtx.open_table(BLOB_INDEX_TABLE)?;
 
-                let blob_info = table
+                let Some(blob_info) = table
                     .get(BlobIdTuple::from(blob_id.clone()))?
                     .map(|v| v.value())
                     .map(BlobInfo::from)
-                    .expect("blob info not found");
+                else {
+                    let BlobId {
+                        account,
+                        container,
+                        name,
+                    } = &blob_id;
+                    log::warn!("Skipping hash index entry without blob info: account={account} container={container} name={name}");
+                    continue;
+                };
 
                 let BlobId {
                     account,
@@ -965,11 +3072,7 @@ impl Db {
             let (timestamp, size, hash, time_first_indexed, time_last_checked) = value;
 
             let cid = hash
-                .map(|hash| {
-                    let multihash = Multihash::wrap(0x1e, &hash)
-                        .expect("unexpectedly failed to wrap a multihash");
-                    Cid::new_v1(0x80, multihash).to_string()
-                })
+                .map(|hash| hash_to_cid(multihash::BLAKE3, &hash, multicodec::BLAKE3_HASHSEQ))
                 .unwrap_or_default();
 
             let account = account.to_string();
@@ -1002,3 +3105,318 @@ impl Db {
         Ok(table)
     }
 }
+
+/// Apply the migrations the database doesn't have yet, one transaction each
+fn migrate(db: &redb::Database) -> Result<()> {
+    loop {
+        let tx = db.begin_write()?;
+
+        let version = {
+            let table = tx.open_table(SCHEMA_VERSION_TABLE)?;
+            let version = table.get(SCHEMA_VERSION_KEY)?.map_or(0, |v| v.value());
+            version
+        };
+
+        let Some(migration) = MIGRATIONS.get(version as usize) else {
+            if version as usize > MIGRATIONS.len() {
+                bail!(
+                    "db schema version={version} is newer than the latest this service knows={}",
+                    MIGRATIONS.len()
+                );
+            }
+
+            return Ok(());
+        };
+
+        migration(&tx)?;
+
+        tx.open_table(SCHEMA_VERSION_TABLE)?
+            .insert(SCHEMA_VERSION_KEY, version + 1)?;
+        tx.commit()?;
+
+        log::info!("Migrated db to schema version={}", version + 1);
+    }
+}
+
+/// Schema version 1, the tables from before schema versions
+fn create_tables(tx: &redb::WriteTransaction) -> Result<()> {
+    tx.open_table(BLOB_INDEX_TABLE)?;
+    tx.open_multimap_table(BLOB_HASH_INDEX_TABLE)?;
+    tx.open_table(BLOB_ETAG_TABLE)?;
+    tx.open_table(BLOB_CONTENT_TYPE_TABLE)?;
+    tx.open_table(BLOB_SAMPLE_TABLE)?;
+    tx.open_table(SHA256_EQUIVALENCE_TABLE)?;
+    tx.open_table(COLLECTION_INDEX_TABLE)?;
+    tx.open_multimap_table(COLLECTION_HASH_INDEX_TABLE)?;
+    tx.open_table(BLOB_TOMBSTONE_TABLE_V1)?;
+    tx.open_table(JOB_TABLE)?;
+    tx.open_table(JOB_PHASE_TABLE)?;
+    tx.open_table(JOB_RESUMES_TABLE)?;
+    tx.open_table(SHUTDOWN_REPORT_TABLE)?;
+    tx.open_table(EVENT_TABLE)?;
+    tx.open_table(WEBHOOK_CURSOR_TABLE)?;
+
+    Ok(())
+}
+
+/// Schema version 2, adds the watch table
+fn create_watch_table(tx: &redb::WriteTransaction) -> Result<()> {
+    tx.open_table(WATCH_TABLE)?;
+
+    Ok(())
+}
+
+/// Schema version 3, adds the blob hash failure table
+fn create_hash_failure_table(tx: &redb::WriteTransaction) -> Result<()> {
+    tx.open_table(BLOB_HASH_FAILURE_TABLE)?;
+
+    Ok(())
+}
+
+/// Schema version 4, adds the event prune counts table
+fn create_event_prune_table(tx: &redb::WriteTransaction) -> Result<()> {
+    tx.open_table(EVENT_PRUNE_TABLE)?;
+
+    Ok(())
+}
+
+/// Schema version 5, adds the collection child table. Collections indexed before it get their
+/// children on the next indexing pass.
+fn create_collection_child_table(tx: &redb::WriteTransaction) -> Result<()> {
+    tx.open_multimap_table(COLLECTION_CHILD_TABLE)?;
+
+    Ok(())
+}
+
+/// Schema version 6, stores tombstones without an etag as such rather than with an empty one
+fn make_tombstone_etags_optional(tx: &redb::WriteTransaction) -> Result<()> {
+    let tombstones = tx
+        .open_table(BLOB_TOMBSTONE_TABLE_V1)?
+        .iter()?
+        .map(|entry| {
+            let (key, value) = entry?;
+            let (tombstoned_at, etag) = value.value();
+
+            Ok((
+                key.value(),
+                tombstoned_at,
+                Some(etag.to_owned()).filter(|etag| !etag.is_empty()),
+            ))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    tx.delete_table(BLOB_TOMBSTONE_TABLE_V1)?;
+
+    let mut table = tx.open_table(BLOB_TOMBSTONE_TABLE)?;
+    for (blob_id, tombstoned_at, etag) in tombstones {
+        table.insert(blob_id, (tombstoned_at, etag.as_deref()))?;
+    }
+
+    Ok(())
+}
+
+/// Schema version 7, adds the container list marker table
+fn create_container_list_marker_table(tx: &redb::WriteTransaction) -> Result<()> {
+    tx.open_table(CONTAINER_LIST_MARKER_TABLE)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Path for a test's database file, removing any left over from a previous run
+    fn temp_db_file(name: &str) -> PathBuf {
+        let db_file = std::env::temp_dir().join(format!(
+            "azure-blob-storage-crp-{}-{name}.redb",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&db_file);
+
+        db_file
+    }
+
+    fn blob_id() -> BlobId {
+        BlobId {
+            account: "account".to_owned(),
+            container: "container".to_owned(),
+            name: "dir/blob.txt".to_owned(),
+        }
+    }
+
+    fn blob_info(timestamp: i64) -> BlobInfo {
+        BlobInfo {
+            timestamp,
+            size: 5,
+            hash: None,
+            time_first_indexed: 0,
+            time_last_checked: 0,
+        }
+    }
+
+    fn hashes() -> BlobHashes {
+        BlobHashes {
+            blake3: [1; 32],
+            sha256: Some([2; 32]),
+            sample: [3; 32],
+        }
+    }
+
+    /// Index an unhashed blob, returning its entry
+    fn index(db: &Db, timestamp: i64, etag: &str) -> BlobInfo {
+        let wtx = db.db.begin_write().unwrap();
+        db.write_blob_index_entry(&wtx, blob_id(), blob_info(timestamp), None)
+            .unwrap();
+        db.write_blob_properties(&wtx, &blob_id(), etag, None)
+            .unwrap();
+        wtx.commit().unwrap();
+
+        blob_info(timestamp)
+    }
+
+    fn blob_index_entry(db: &Db) -> Option<BlobInfo> {
+        let rtx = db.db.begin_read().unwrap();
+        let table = rtx.open_table(BLOB_INDEX_TABLE).unwrap();
+        let blob_info = table
+            .get(BlobIdTuple::from(blob_id()))
+            .unwrap()
+            .map(|v| BlobInfo::from(v.value()));
+        blob_info
+    }
+
+    #[test]
+    fn record_blob_hashes_skips_removed_or_changed_blobs() {
+        let db = Db::init(temp_db_file("record-blob-hashes"), None).unwrap();
+        let etag = Some("0x1".to_owned());
+
+        // tombstoned while hashing
+        let hashed_blob_info = index(&db, 1, "0x1");
+        assert!(db.tombstone_blob(&blob_id()).unwrap());
+        let recorded = db
+            .record_blob_hashes(blob_id(), hashed_blob_info, etag.clone(), hashes())
+            .unwrap();
+        assert!(recorded.is_none());
+        assert!(blob_index_entry(&db).is_none());
+        db.remove_tombstone(&blob_id()).unwrap();
+
+        // modified while hashing
+        let hashed_blob_info = index(&db, 1, "0x1");
+        index(&db, 2, "0x2");
+        let recorded = db
+            .record_blob_hashes(blob_id(), hashed_blob_info, etag.clone(), hashes())
+            .unwrap();
+        assert!(recorded.is_none());
+        assert_eq!(blob_index_entry(&db).unwrap().hash, None);
+
+        // unchanged
+        let hashed_blob_info = blob_index_entry(&db).unwrap();
+        let recorded = db
+            .record_blob_hashes(
+                blob_id(),
+                hashed_blob_info,
+                Some("0x2".to_owned()),
+                hashes(),
+            )
+            .unwrap();
+        assert_eq!(recorded.unwrap().hash, Some([1; 32]));
+        assert_eq!(blob_index_entry(&db).unwrap().hash, Some([1; 32]));
+
+        std::fs::remove_file(&db.file).unwrap();
+    }
+
+    fn schema_version(db: &redb::Database) -> u64 {
+        let rtx = db.begin_read().unwrap();
+        let table = rtx.open_table(SCHEMA_VERSION_TABLE).unwrap();
+        let version = table.get(SCHEMA_VERSION_KEY).unwrap().unwrap().value();
+        version
+    }
+
+    #[test]
+    fn migrate_db_from_before_schema_versions() {
+        let db_file = temp_db_file("migrate-unversioned");
+
+        {
+            let db = redb::Database::create(&db_file).unwrap();
+            let tx = db.begin_write().unwrap();
+            create_tables(&tx).unwrap();
+            tx.commit().unwrap();
+        }
+
+        let db = Db::init(db_file.clone(), None).unwrap();
+
+        assert_eq!(schema_version(&db.db), MIGRATIONS.len() as u64);
+        assert!(db.get_watches().unwrap().is_empty());
+        assert!(db.get_tombstones().unwrap().is_empty());
+
+        drop(db);
+        std::fs::remove_file(&db_file).unwrap();
+    }
+
+    #[test]
+    fn migrate_empty_tombstone_etags_to_none() {
+        let db_file = temp_db_file("migrate-tombstones");
+
+        let without_etag = blob_id();
+        let with_etag = BlobId {
+            name: "dir/other.txt".to_owned(),
+            ..blob_id()
+        };
+
+        // schema version 5, before tombstone etags were optional
+        {
+            let db = redb::Database::create(&db_file).unwrap();
+            let tx = db.begin_write().unwrap();
+            for migration in &MIGRATIONS[..5] {
+                migration(&tx).unwrap();
+            }
+            tx.open_table(SCHEMA_VERSION_TABLE)
+                .unwrap()
+                .insert(SCHEMA_VERSION_KEY, 5)
+                .unwrap();
+            {
+                let mut table = tx.open_table(BLOB_TOMBSTONE_TABLE_V1).unwrap();
+                table
+                    .insert(BlobIdTuple::from(without_etag.clone()), (1, ""))
+                    .unwrap();
+                table
+                    .insert(BlobIdTuple::from(with_etag.clone()), (2, "0x1"))
+                    .unwrap();
+            }
+            tx.commit().unwrap();
+        }
+
+        let db = Db::init(db_file.clone(), None).unwrap();
+
+        let tombstone = db.get_tombstone(&without_etag).unwrap().unwrap();
+        assert_eq!(tombstone.tombstoned_at, 1);
+        assert_eq!(tombstone.etag, None);
+
+        let tombstone = db.get_tombstone(&with_etag).unwrap().unwrap();
+        assert_eq!(tombstone.tombstoned_at, 2);
+        assert_eq!(tombstone.etag.as_deref(), Some("0x1"));
+
+        drop(db);
+        std::fs::remove_file(&db_file).unwrap();
+    }
+
+    #[test]
+    fn refuse_newer_schema_version() {
+        let db_file = temp_db_file("migrate-newer");
+
+        {
+            let db = redb::Database::create(&db_file).unwrap();
+            let tx = db.begin_write().unwrap();
+            tx.open_table(SCHEMA_VERSION_TABLE)
+                .unwrap()
+                .insert(SCHEMA_VERSION_KEY, MIGRATIONS.len() as u64 + 1)
+                .unwrap();
+            tx.commit().unwrap();
+        }
+
+        let error = Db::init(db_file.clone(), None).err().unwrap();
+        assert!(error.to_string().contains("newer than the latest"));
+
+        std::fs::remove_file(&db_file).unwrap();
+    }
+}
@@ -0,0 +1,233 @@
+use std::{sync::Arc, time::Duration};
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio::sync::broadcast::error::RecvError;
+use utoipa::ToSchema;
+
+use crate::{
+    config::{ProxyConfig, WebhookConfig},
+    context::Context,
+};
+
+/// Most events POSTed to a webhook at once
+const WEBHOOK_BATCH_SIZE: usize = 100;
+
+/// Timeout of each webhook request
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Delay before the first retry of a failed delivery, doubling on each retry after it
+const WEBHOOK_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// Longest delay between retries of a failed delivery
+const WEBHOOK_MAX_RETRY_DELAY: Duration = Duration::from_secs(300);
+
+/// A change to a blob's index entry, and so to its route, to a watched CID, or a refused prune
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct RouteEvent {
+    pub id: u64,
+    /// Unix timestamp of the change
+    pub timestamp: i64,
+    pub kind: RouteEventKind,
+    /// Storage account of the blob, empty for `watch_expired` events, which are for no blob
+    pub account: String,
+    pub container: String,
+    /// Name of the blob, empty for `prune_refused` events, which are for a whole container
+    pub name: String,
+    /// CID of the blob's content, once it's hashed, or the watched CID for watch events
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cid: Option<String>,
+    /// What a `prune_refused` event's prune would have removed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prune: Option<PruneCounts>,
+}
+
+/// Index entries a prune would have removed from a container, over its churn limit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+pub struct PruneCounts {
+    /// Entries the prune would have removed
+    pub stale: u64,
+    /// Entries the container has
+    pub entries: u64,
+    /// The configured `max_prune_percent`
+    pub max_prune_percent: u8,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RouteEventKind {
+    /// A new blob was listed, its route is added once it's hashed
+    Inserted,
+    /// A blob was hashed and its route is now returned
+    Completed,
+    /// A blob changed, its route is dropped until it's hashed again
+    Reset,
+    /// A blob's entry and route were removed, as it was deleted, no longer matches the filter,
+    /// or was tombstoned
+    Deleted,
+    /// A watched CID got a route, to this blob, and is no longer watched
+    WatchFound,
+    /// A watched CID's deadline passed without a route, it stays watched in case it turns up late
+    WatchExpired,
+    /// Pruning a container was skipped as it would have removed more than `max_prune_percent` of
+    /// its entries, which usually means blob storage is misconfigured
+    PruneRefused,
+}
+
+impl RouteEventKind {
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            Self::Inserted => "inserted",
+            Self::Completed => "completed",
+            Self::Reset => "reset",
+            Self::Deleted => "deleted",
+            Self::WatchFound => "watch_found",
+            Self::WatchExpired => "watch_expired",
+            Self::PruneRefused => "prune_refused",
+        }
+    }
+
+    pub(crate) fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "inserted" => Ok(Self::Inserted),
+            "completed" => Ok(Self::Completed),
+            "reset" => Ok(Self::Reset),
+            "deleted" => Ok(Self::Deleted),
+            "watch_found" => Ok(Self::WatchFound),
+            "watch_expired" => Ok(Self::WatchExpired),
+            "prune_refused" => Ok(Self::PruneRefused),
+            s => Err(anyhow!("unknown route event kind: {s}")),
+        }
+    }
+}
+
+/// Deliver route events to every configured webhook
+pub async fn start(ctx: Arc<Context>) -> Result<()> {
+    let mut client = reqwest::Client::builder().timeout(WEBHOOK_TIMEOUT);
+
+    if let Some(ProxyConfig { url, no_proxy }) = &ctx.proxy {
+        let proxy = reqwest::Proxy::all(url)?
+            .no_proxy(no_proxy.as_deref().and_then(reqwest::NoProxy::from_string));
+        client = client.proxy(proxy);
+    }
+
+    let client = client.build()?;
+
+    let webhook_loops = ctx
+        .webhooks
+        .iter()
+        .map(|webhook| webhook_loop(&ctx, &client, webhook));
+
+    futures::future::join_all(webhook_loops).await;
+
+    Ok(())
+}
+
+async fn webhook_loop(ctx: &Context, client: &reqwest::Client, webhook: &WebhookConfig) {
+    if let Err(e) = deliver_events(ctx, client, webhook).await {
+        log::error!(
+            "Stopped delivering events to webhook={}: {:?}",
+            webhook.url,
+            e
+        );
+    }
+}
+
+/// Deliver events to a webhook in order, from where delivery last left off. A new webhook gets
+/// events from when it was added.
+async fn deliver_events(
+    ctx: &Context,
+    client: &reqwest::Client,
+    webhook: &WebhookConfig,
+) -> Result<()> {
+    let Context { db, .. } = ctx;
+
+    // subscribed before reading the cursor so no event is missed between the two
+    let mut new_events = db.subscribe_events();
+
+    // ID of the next event to deliver
+    let mut cursor = match db.get_webhook_cursor(&webhook.url)? {
+        Some(cursor) => cursor,
+        None => {
+            let cursor = db.get_next_event_id()?;
+            db.set_webhook_cursor(&webhook.url, cursor)?;
+            cursor
+        }
+    };
+
+    loop {
+        let batch = db.get_events(cursor, WEBHOOK_BATCH_SIZE)?;
+
+        let Some(last) = batch.last().map(|event| event.id) else {
+            match new_events.recv().await {
+                Ok(_) | Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return Ok(()),
+            }
+        };
+
+        let events = batch
+            .into_iter()
+            .filter(|event| {
+                webhook
+                    .events
+                    .as_ref()
+                    .map_or(true, |kinds| kinds.contains(&event.kind))
+            })
+            .collect::<Vec<_>>();
+
+        if !events.is_empty() {
+            post_with_retries(client, webhook, &events).await;
+        }
+
+        cursor = last + 1;
+        db.set_webhook_cursor(&webhook.url, cursor)?;
+    }
+}
+
+/// POST events to a webhook, retrying with backoff until it succeeds or `max_retries` is reached,
+/// in which case the events are skipped
+async fn post_with_retries(
+    client: &reqwest::Client,
+    webhook: &WebhookConfig,
+    events: &[RouteEvent],
+) {
+    let mut delay = WEBHOOK_RETRY_DELAY;
+    let mut retries = 0;
+
+    loop {
+        let result = client
+            .post(&webhook.url)
+            .json(&json!({ "events": events }))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+
+        let e = match result {
+            Ok(_) => return,
+            Err(e) => e,
+        };
+
+        if webhook
+            .max_retries
+            .is_some_and(|max_retries| retries >= max_retries)
+        {
+            log::error!(
+                "Skipping {} events after {retries} retries delivering them to webhook={}: {e}",
+                events.len(),
+                webhook.url
+            );
+            return;
+        }
+
+        log::warn!(
+            "Error delivering events to webhook={}, retrying in {delay:?}: {e}",
+            webhook.url
+        );
+
+        tokio::time::sleep(delay).await;
+
+        delay = (delay * 2).min(WEBHOOK_MAX_RETRY_DELAY);
+        retries += 1;
+    }
+}
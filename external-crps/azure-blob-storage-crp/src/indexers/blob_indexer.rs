@@ -3,7 +3,14 @@ use std::sync::Arc;
 use anyhow::Result;
 use tokio::time::{Duration, Instant};
 
-use crate::{config::IndexingStrategy, context::Context};
+use crate::{
+    config::{BlobStorageConfig, ContainerConfig, IndexingStrategy},
+    context::Context,
+    db::{Job, JobPhase, JobState},
+};
+
+/// Number of blobs hashed in parallel per container when not configured
+pub const DEFAULT_HASHING_CONCURRENCY: usize = 4;
 
 pub async fn start(ctx: Arc<Context>) -> Result<()> {
     let ctx = ctx.clone();
@@ -21,21 +28,128 @@ pub async fn start(ctx: Arc<Context>) -> Result<()> {
 async fn blob_indexer_task(ctx: Arc<Context>) -> Result<()> {
     let Context { db, .. } = &*ctx;
 
-    match ctx.indexing_strategy {
+    db.interrupt_running_jobs()?;
+
+    // each container is indexed on its own schedule
+    let container_tasks = ctx
+        .blob_storage_config
+        .containers
+        .iter()
+        .map(|container_config| container_indexer_task(ctx.clone(), container_config.clone()))
+        .collect::<Vec<_>>();
+
+    if container_tasks.is_empty() {
+        log::warn!("No containers configured, nothing to index");
+
+        return futures::future::pending().await;
+    }
+
+    futures::future::try_join_all(container_tasks).await?;
+
+    Ok(())
+}
+
+async fn container_indexer_task(
+    ctx: Arc<Context>,
+    container_config: ContainerConfig,
+) -> Result<()> {
+    let Context { db, .. } = &*ctx;
+
+    let indexing_strategy = container_config
+        .indexing_strategy
+        .clone()
+        .unwrap_or(ctx.indexing_strategy.clone());
+
+    let hashing_concurrency = container_config
+        .hashing_concurrency
+        .unwrap_or(DEFAULT_HASHING_CONCURRENCY);
+
+    let ContainerConfig {
+        account, container, ..
+    } = container_config.clone();
+
+    let blob_storage_config = BlobStorageConfig {
+        containers: vec![container_config],
+    };
+
+    match indexing_strategy {
         IndexingStrategy::PollInterval(interval) => {
             let interval = Duration::from_secs(interval);
 
+            // the first job after a restart picks up from the container's unfinished job, if any
+            let mut unfinished_job = db.get_unfinished_job(&account, &container)?;
+
             loop {
                 let next_update_time = Instant::now() + interval;
 
-                if let Err(e) = db.update_blob_index(&ctx.blob_storage_config).await {
-                    log::error!("Error updating blob index: {:?}", e);
+                let resumes = unfinished_job.take();
+
+                let mut job =
+                    db.create_job(&account, &container, resumes.as_ref().map(|j| j.id))?;
+
+                match resumes {
+                    Some(resumes) => log::info!(
+                        "Starting indexer job={} account={account} container={container}, picking up from job={} ({:?} in phase={:?})",
+                        job.id,
+                        resumes.id,
+                        resumes.state,
+                        resumes.phase
+                    ),
+                    None => log::debug!(
+                        "Starting indexer job={} account={account} container={container}",
+                        job.id
+                    ),
+                }
+
+                job.phase = Some(JobPhase::ListingBlobs);
+                db.update_job(&job)?;
+
+                match db
+                    .update_blob_index(&blob_storage_config, ctx.max_prune_percent)
+                    .await
+                {
+                    Ok(n) => job.items_indexed += n,
+                    Err(e) => {
+                        log::error!("Error updating blob index: {:?}", e);
+                        job.errors.push(format!("Error updating blob index: {e}"));
+                    }
+                }
+                job.phase = Some(JobPhase::HashingBlobs);
+                db.update_job(&job)?;
+
+                match db
+                    .update_blob_index_hashes(&blob_storage_config, hashing_concurrency)
+                    .await
+                {
+                    Ok(n) => job.items_indexed += n,
+                    Err(e) => {
+                        log::error!("Error updating blob index hashes: {:?}", e);
+                        job.errors
+                            .push(format!("Error updating blob index hashes: {e}"));
+                    }
                 }
-                if let Err(e) = db.update_blob_index_hashes(&ctx.blob_storage_config).await {
-                    log::error!("Error updating blob index hashes: {:?}", e);
+                job.phase = Some(JobPhase::IndexingCollections);
+                db.update_job(&job)?;
+
+                match db.update_iroh_collections_index(&blob_storage_config) {
+                    Ok(n) => job.items_indexed += n,
+                    Err(e) => {
+                        log::error!("Error updating iroh collections index: {:?}", e);
+                        job.errors
+                            .push(format!("Error updating iroh collections index: {e}"));
+                    }
                 }
-                if let Err(e) = db.update_iroh_collections_index(&ctx.blob_storage_config) {
-                    log::error!("Error updating iroh collections index: {:?}", e);
+                db.update_job(&Job {
+                    finished_at: Some(chrono::Utc::now().timestamp()),
+                    state: JobState::Completed,
+                    phase: None,
+                    ..job
+                })?;
+
+                match db.check_watches() {
+                    Ok(n) if n > 0 => log::info!("Recorded {n} watch events"),
+                    Ok(_) => {}
+                    Err(e) => log::error!("Error checking watches: {:?}", e),
                 }
 
                 if Instant::now() < next_update_time {
@@ -1,7 +1,11 @@
 pub mod api;
+pub mod auth;
 pub mod cli;
 pub mod config;
 pub mod context;
 pub mod db;
+pub mod events;
 pub mod indexers;
 pub mod log;
+pub mod scheduler;
+pub mod simulate;
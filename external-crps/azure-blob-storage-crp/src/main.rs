@@ -1,9 +1,25 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
-use anyhow::Result;
-use azure_blob_storage_crp::{api, cli, config::Config, context::Context, indexers::blob_indexer};
+use anyhow::{anyhow, Context as _, Result};
+use api_utils::shutdown::DEFAULT_SHUTDOWN_TIMEOUT_MS;
+use azure_blob_storage_crp::{
+    api::{
+        self,
+        v1::crp::routes::{get_blob_route, get_routes_for_cid},
+    },
+    cli,
+    config::Config,
+    context::Context,
+    db::{BlobId, Db},
+    events,
+    indexers::blob_indexer,
+    scheduler,
+    simulate::{read_listing, simulate_indexing},
+};
+use cid::Cid;
 use clap::Parser;
 use log::info;
+use serde_json::json;
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -11,6 +27,9 @@ async fn main() -> Result<()> {
 
     match args.cmd {
         cli::Subcommand::Start(args) => start(args).await?,
+        cli::Subcommand::Routes(args) => routes(args)?,
+        cli::Subcommand::Simulate(args) => simulate(args)?,
+        cli::Subcommand::Stats(args) => stats(args)?,
     }
 
     Ok(())
@@ -21,15 +40,169 @@ async fn start(args: cli::Start) -> Result<()> {
 
     azure_blob_storage_crp::log::init(&config)?;
 
+    if let Some(expose_callstacks) = config.expose_callstacks {
+        api_utils::error::set_expose_callstacks(expose_callstacks);
+    }
+
     info!("Starting: {config:#?}");
 
+    let shutdown_timeout = Duration::from_millis(
+        config
+            .shutdown_timeout_ms
+            .unwrap_or(DEFAULT_SHUTDOWN_TIMEOUT_MS),
+    );
+
     let ctx = Arc::new(Context::init(config)?);
 
-    tokio::spawn(blob_indexer::start(ctx.clone()));
+    let tasks = [
+        tokio::spawn(blob_indexer::start(ctx.clone())),
+        tokio::spawn(scheduler::start(ctx.clone())),
+        tokio::spawn(events::start(ctx.clone())),
+    ];
+
+    api::start(ctx.clone(), shutdown_timeout).await?;
+
+    // stopped before the report so no job is journaled after it's marked aborted
+    for task in tasks {
+        task.abort();
+        let _ = task.await;
+    }
 
-    tokio::spawn(api::start(ctx));
+    let report = ctx.db.record_shutdown(ctx.start_time)?;
 
-    tokio::signal::ctrl_c().await?;
+    info!(
+        "Shutting down: completed jobs={:?} aborted jobs={:?} resumable jobs={:?}",
+        report.completed, report.aborted, report.resumable
+    );
 
     Ok(())
 }
+
+fn routes(args: cli::Routes) -> Result<()> {
+    let db = open_db(&args.common_args)?;
+
+    match args.cmd {
+        cli::RoutesSubcommand::List(_) => {
+            println!("Blob Index");
+            println!("{}", db.get_all_blob_entries_ascii_table()?);
+            println!("Iroh Collection Index");
+            println!("{}", db.get_all_collection_entries_ascii_table()?);
+        }
+        cli::RoutesSubcommand::Get(cli::RoutesGet {
+            account,
+            container,
+            name,
+        }) => {
+            let blob_id = BlobId {
+                account,
+                container,
+                name,
+            };
+
+            let blob_info = db.get_blob_info(&blob_id)?.ok_or_else(|| {
+                anyhow!(
+                    "blob account={} container={} name={} not found",
+                    blob_id.account,
+                    blob_id.container,
+                    blob_id.name
+                )
+            })?;
+
+            let route = get_blob_route(&db, blob_id, blob_info)?;
+
+            println!("{}", serde_json::to_string_pretty(&route)?);
+        }
+        cli::RoutesSubcommand::ForCid(cli::RoutesForCid { cid }) => {
+            let cid = cid
+                .parse::<Cid>()
+                .map_err(|e| anyhow!("invalid cid={cid}: {e}"))?;
+
+            let routes = get_routes_for_cid(&db, &cid)?;
+
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&json!({ "routes": routes }))?
+            );
+        }
+        cli::RoutesSubcommand::Export(_) => {
+            let entries = db
+                .get_all_blob_entries()?
+                .into_iter()
+                .chain(db.get_all_collection_entries()?)
+                .filter(|entry| !entry.cid.is_empty());
+
+            for entry in entries {
+                let blob_id = BlobId {
+                    account: entry.account,
+                    container: entry.container,
+                    name: entry.name,
+                };
+
+                let Some(blob_info) = db.get_blob_info(&blob_id)? else {
+                    continue;
+                };
+
+                let route = get_blob_route(&db, blob_id, blob_info)?;
+
+                println!("{}", json!({ "cid": entry.cid, "route": route }));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn simulate(args: cli::Simulate) -> Result<()> {
+    let config = Config::from_file(args.common_args.config)?;
+
+    let containers = &config.blob_storage.containers;
+
+    let container_config = match &args.container {
+        Some(name) => containers
+            .iter()
+            .find(|c| format!("{}/{}", c.account, c.container) == *name)
+            .ok_or_else(|| anyhow!("container={name} isn't configured"))?,
+        None => match &containers[..] {
+            [container_config] => container_config,
+            _ => {
+                return Err(anyhow!(
+                    "{} containers are configured, pick one with --container",
+                    containers.len()
+                ))
+            }
+        },
+    };
+
+    let listing = read_listing(&args.listing)?;
+
+    let download_throughput = args.download_mbps.map(|mbps| (mbps * 1e6) as u64);
+
+    let simulation = simulate_indexing(container_config, &listing, download_throughput)?;
+
+    println!(
+        "Container {}/{}",
+        container_config.account, container_config.container
+    );
+    println!("{simulation}");
+
+    Ok(())
+}
+
+fn stats(args: cli::Stats) -> Result<()> {
+    let db = open_db(&args.common_args)?;
+
+    println!("{}", db.get_stats_ascii_table()?);
+
+    Ok(())
+}
+
+fn open_db(common_args: &cli::CommonArgs) -> Result<Db> {
+    let config = Config::from_file(common_args.config.clone())?;
+
+    Db::init(config.db_file.clone(), config.proxy.as_ref()).with_context(|| {
+        format!(
+            "failed to open db_file={}, it can't be opened while the service is running",
+            config.db_file.display()
+        )
+    })
+}
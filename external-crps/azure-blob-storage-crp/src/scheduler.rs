@@ -0,0 +1,365 @@
+use std::{
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use anyhow::{anyhow, bail, Result};
+use chrono::{DateTime, Datelike, Days, NaiveDate, TimeDelta, Timelike, Utc};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::{
+    config::{Config, MaintenanceTaskConfig},
+    context::Context,
+};
+
+/// Periodic maintenance work run by the scheduler
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MaintenanceTask {
+    /// Prune unhashed blob index entries older than `stub_retention` whose blob is gone or keeps
+    /// failing to hash
+    PruneStaleStubs,
+}
+
+impl MaintenanceTask {
+    const ALL: [Self; 1] = [Self::PruneStaleStubs];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::PruneStaleStubs => "prune_stale_stubs",
+        }
+    }
+
+    fn default_schedule(&self) -> &'static str {
+        match self {
+            Self::PruneStaleStubs => "0 * * * *",
+        }
+    }
+
+    fn default_enabled(&self, config: &Config) -> bool {
+        match self {
+            Self::PruneStaleStubs => config.stub_retention.is_some(),
+        }
+    }
+
+    async fn run(&self, ctx: &Context) -> Result<()> {
+        match self {
+            Self::PruneStaleStubs => {
+                let stub_retention = ctx
+                    .stub_retention
+                    .ok_or_else(|| anyhow!("no stub_retention is configured"))?;
+
+                let pruned = ctx
+                    .db
+                    .prune_stale_stubs(
+                        &ctx.blob_storage_config,
+                        stub_retention,
+                        ctx.max_prune_percent,
+                    )
+                    .await?;
+
+                log::debug!("Pruned {pruned} stale stubs");
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Schedule and status of a maintenance task
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct MaintenanceTaskStatus {
+    pub task: MaintenanceTask,
+    pub schedule: String,
+    pub enabled: bool,
+    /// Unix timestamp of the next scheduled run, runs are skipped while the task is disabled
+    pub next_run: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_run: Option<MaintenanceTaskRun>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct MaintenanceTaskRun {
+    pub started_at: i64,
+    pub finished_at: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+struct ScheduledTask {
+    task: MaintenanceTask,
+    schedule: CronSchedule,
+    enabled: AtomicBool,
+    last_run: Mutex<Option<MaintenanceTaskRun>>,
+}
+
+/// Runs maintenance tasks on cron schedules
+pub struct Scheduler {
+    tasks: Vec<ScheduledTask>,
+}
+
+impl Scheduler {
+    /// Build the schedule of every maintenance task from its config, if any
+    pub fn init(config: &Config) -> Result<Self> {
+        let maintenance = config.maintenance.clone().unwrap_or_default();
+
+        if let Some(name) = maintenance
+            .keys()
+            .find(|name| !MaintenanceTask::ALL.iter().any(|task| task.name() == *name))
+        {
+            bail!("unknown maintenance task={name}");
+        }
+
+        let tasks = MaintenanceTask::ALL
+            .into_iter()
+            .map(|task| {
+                let MaintenanceTaskConfig { schedule, enabled } =
+                    maintenance.get(task.name()).cloned().unwrap_or_default();
+
+                let schedule = schedule
+                    .as_deref()
+                    .unwrap_or(task.default_schedule())
+                    .parse::<CronSchedule>()
+                    .map_err(|e| anyhow!("invalid schedule for task={}: {e}", task.name()))?;
+
+                Ok(ScheduledTask {
+                    task,
+                    schedule,
+                    enabled: AtomicBool::new(
+                        enabled.unwrap_or_else(|| task.default_enabled(config)),
+                    ),
+                    last_run: Mutex::new(None),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self { tasks })
+    }
+
+    pub fn get_statuses(&self) -> Vec<MaintenanceTaskStatus> {
+        let now = Utc::now();
+
+        self.tasks
+            .iter()
+            .map(|scheduled_task| MaintenanceTaskStatus {
+                task: scheduled_task.task,
+                schedule: scheduled_task.schedule.to_string(),
+                enabled: scheduled_task.enabled.load(Ordering::Relaxed),
+                next_run: scheduled_task
+                    .schedule
+                    .next_after(now)
+                    .map(|t| t.timestamp()),
+                last_run: scheduled_task
+                    .last_run
+                    .lock()
+                    .expect("maintenance task lock poisoned")
+                    .clone(),
+            })
+            .collect()
+    }
+
+    /// Enable or disable a task by name, returning its status or `None` if there's no such task
+    pub fn set_enabled(&self, name: &str, enabled: bool) -> Option<MaintenanceTaskStatus> {
+        let scheduled_task = self.tasks.iter().find(|t| t.task.name() == name)?;
+
+        scheduled_task.enabled.store(enabled, Ordering::Relaxed);
+
+        self.get_statuses()
+            .into_iter()
+            .find(|status| status.task == scheduled_task.task)
+    }
+}
+
+pub async fn start(ctx: Arc<Context>) -> Result<()> {
+    let task_loops = ctx
+        .scheduler
+        .tasks
+        .iter()
+        .map(|scheduled_task| task_loop(&ctx, scheduled_task));
+
+    futures::future::join_all(task_loops).await;
+
+    Ok(())
+}
+
+async fn task_loop(ctx: &Context, scheduled_task: &ScheduledTask) {
+    let ScheduledTask {
+        task,
+        schedule,
+        enabled,
+        last_run,
+    } = scheduled_task;
+
+    let mut last_scheduled = None;
+
+    loop {
+        // the clock can read slightly before the scheduled time on waking, so don't schedule the
+        // same time twice
+        let after = last_scheduled.map_or(Utc::now(), |t| Utc::now().max(t));
+
+        let Some(next_run) = schedule.next_after(after) else {
+            log::warn!("Schedule for task={} never runs", task.name());
+            return;
+        };
+
+        tokio::time::sleep((next_run - Utc::now()).to_std().unwrap_or_default()).await;
+
+        last_scheduled = Some(next_run);
+
+        if !enabled.load(Ordering::Relaxed) {
+            continue;
+        }
+
+        log::debug!("Running maintenance task={}", task.name());
+
+        let started_at = Utc::now().timestamp();
+
+        let error = task.run(ctx).await.err().map(|e| {
+            log::error!("Error running maintenance task={}: {:?}", task.name(), e);
+            e.to_string()
+        });
+
+        *last_run.lock().expect("maintenance task lock poisoned") = Some(MaintenanceTaskRun {
+            started_at,
+            finished_at: Utc::now().timestamp(),
+            error,
+        });
+    }
+}
+
+/// Cron schedule with the five fields "minute hour day-of-month month day-of-week", in UTC.
+///
+/// Fields take `*`, values, ranges `a-b`, steps `*/n` or `a-b/n`, and comma separated lists of
+/// these. Days of the week are 0-7 with both 0 and 7 meaning Sunday. As in cron, when both the day
+/// of the month and day of the week are restricted a day matching either of them matches.
+#[derive(Debug, Clone)]
+pub struct CronSchedule {
+    expr: String,
+    minutes: u64,
+    hours: u64,
+    days_of_month: u64,
+    months: u64,
+    days_of_week: u64,
+    days_of_month_restricted: bool,
+    days_of_week_restricted: bool,
+}
+
+impl FromStr for CronSchedule {
+    type Err = anyhow::Error;
+
+    fn from_str(expr: &str) -> Result<Self> {
+        let fields = expr.split_whitespace().collect::<Vec<_>>();
+
+        let [minutes, hours, days_of_month, months, days_of_week] = fields[..] else {
+            bail!("expected 5 fields in cron expression={expr}");
+        };
+
+        let mut days_of_week_mask = parse_cron_field(days_of_week, 0, 7)?;
+        if days_of_week_mask & (1 << 7) != 0 {
+            days_of_week_mask |= 1;
+        }
+
+        Ok(Self {
+            expr: expr.to_owned(),
+            minutes: parse_cron_field(minutes, 0, 59)?,
+            hours: parse_cron_field(hours, 0, 23)?,
+            days_of_month: parse_cron_field(days_of_month, 1, 31)?,
+            months: parse_cron_field(months, 1, 12)?,
+            days_of_week: days_of_week_mask,
+            days_of_month_restricted: !days_of_month.starts_with('*'),
+            days_of_week_restricted: !days_of_week.starts_with('*'),
+        })
+    }
+}
+
+impl std::fmt::Display for CronSchedule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.expr)
+    }
+}
+
+impl CronSchedule {
+    /// First time matching the schedule after `t`, or `None` if none is within five years
+    pub fn next_after(&self, t: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let limit = t + TimeDelta::days(5 * 366);
+
+        let mut t = t.with_second(0)?.with_nanosecond(0)? + TimeDelta::minutes(1);
+
+        while t < limit {
+            if !is_set(self.months, t.month()) {
+                let (year, month) = match t.month() {
+                    12 => (t.year() + 1, 1),
+                    month => (t.year(), month + 1),
+                };
+                t = NaiveDate::from_ymd_opt(year, month, 1)?
+                    .and_hms_opt(0, 0, 0)?
+                    .and_utc();
+            } else if !self.is_day_match(&t) {
+                t = (t.date_naive() + Days::new(1))
+                    .and_hms_opt(0, 0, 0)?
+                    .and_utc();
+            } else if !is_set(self.hours, t.hour()) {
+                t = t.with_minute(0)? + TimeDelta::hours(1);
+            } else if !is_set(self.minutes, t.minute()) {
+                t += TimeDelta::minutes(1);
+            } else {
+                return Some(t);
+            }
+        }
+
+        None
+    }
+
+    fn is_day_match(&self, t: &DateTime<Utc>) -> bool {
+        let day_of_month = is_set(self.days_of_month, t.day());
+        let day_of_week = is_set(self.days_of_week, t.weekday().num_days_from_sunday());
+
+        match (self.days_of_month_restricted, self.days_of_week_restricted) {
+            (true, true) => day_of_month || day_of_week,
+            _ => day_of_month && day_of_week,
+        }
+    }
+}
+
+fn is_set(mask: u64, value: u32) -> bool {
+    mask & (1 << value) != 0
+}
+
+fn parse_cron_field(field: &str, min: u32, max: u32) -> Result<u64> {
+    let mut mask = 0;
+
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, Some(step.parse::<u32>()?)),
+            None => (part, None),
+        };
+
+        let (start, end) = match range.split_once('-') {
+            _ if range == "*" => (min, max),
+            Some((start, end)) => (start.parse()?, end.parse()?),
+            // "a/n" runs from a to the end of the range
+            None if step.is_some() => (range.parse()?, max),
+            None => (range.parse()?, range.parse()?),
+        };
+
+        if start < min || end > max || start > end {
+            bail!("{part} is out of range {min}-{max} in cron field={field}");
+        }
+
+        let step = match step {
+            Some(0) => bail!("step of 0 in cron field={field}"),
+            Some(step) => step as usize,
+            None => 1,
+        };
+
+        for value in (start..=end).step_by(step) {
+            mask |= 1 << value;
+        }
+    }
+
+    Ok(mask)
+}
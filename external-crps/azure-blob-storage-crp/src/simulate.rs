@@ -0,0 +1,266 @@
+use std::{
+    fs,
+    path::Path,
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, bail, Result};
+use sha2::{Digest, Sha256};
+
+use crate::{config::ContainerConfig, indexers::blob_indexer::DEFAULT_HASHING_CONCURRENCY};
+
+/// Size of the buffer hashed to measure hashing throughput
+const THROUGHPUT_SAMPLE_LEN: usize = 16 * 1024 * 1024;
+
+/// How long hashing throughput is measured for
+const THROUGHPUT_MEASURE_TIME: Duration = Duration::from_millis(500);
+
+/// Number of tables a hashed blob's id is stored in: the blob index, etag, content type and sample
+/// tables, and the hash index
+const BLOB_ID_COPIES: u64 = 5;
+
+/// Bytes a hashed blob stores besides its id: blob info, a typical etag and content type, sample
+/// fingerprint and hash
+const BLOB_ENTRIES_LEN: u64 = 65 + 40 + 24 + 32 + 32;
+
+/// Bytes of a sha256 equivalence entry
+const SHA256_EQUIVALENCE_ENTRY_LEN: u64 = 64;
+
+/// redb's B-tree pages aren't full, so the file takes more space than the entries themselves
+const DB_SPACE_OVERHEAD: f64 = 2.0;
+
+/// An object in a listing, as `name,size` in the CSV
+#[derive(Debug, Clone)]
+pub struct ListedBlob {
+    pub name: String,
+    pub size: u64,
+}
+
+/// Projection of what indexing a container with a given listing would take
+#[derive(Debug, Clone)]
+pub struct Simulation {
+    pub listed_blobs: u64,
+    pub listed_bytes: u64,
+    /// Blobs matching the container's filter, each of which gets an index entry and a route once
+    /// it's hashed
+    pub routes: u64,
+    /// Bytes of the blobs matching the filter, all of which are downloaded and hashed
+    pub bytes_to_hash: u64,
+    /// Hashing throughput measured on this machine, in bytes per second across
+    /// `hashing_concurrency` blobs at a time
+    pub hashing_throughput: u64,
+    /// Download throughput from blob storage, if given, in bytes per second
+    pub download_throughput: Option<u64>,
+    /// Time to hash every matching blob at the slower of the two throughputs
+    pub index_duration: Duration,
+    /// Rough size of the database once every matching blob is hashed
+    pub db_size: u64,
+}
+
+/// Read a listing CSV of `name,size` lines, skipping a header line if there is one. Names may
+/// contain commas, since the size is taken after the last one.
+pub fn read_listing(path: &Path) -> Result<Vec<ListedBlob>> {
+    let listing = fs::read_to_string(path)?;
+
+    let mut lines = listing
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .peekable();
+
+    if let Some((_, header)) = lines.peek() {
+        let is_header = header
+            .rsplit_once(',')
+            .is_some_and(|(_, size)| size.trim().parse::<u64>().is_err());
+
+        if is_header {
+            lines.next();
+        }
+    }
+
+    lines
+        .map(|(i, line)| {
+            let (name, size) = line
+                .rsplit_once(',')
+                .ok_or_else(|| anyhow!("expected name,size on line {} of listing", i + 1))?;
+
+            let size = size
+                .trim()
+                .parse()
+                .map_err(|e| anyhow!("invalid size on line {} of listing: {e}", i + 1))?;
+
+            Ok(ListedBlob {
+                name: name.to_owned(),
+                size,
+            })
+        })
+        .collect()
+}
+
+/// Project indexing the container's listing, with the download throughput in bytes per second if
+/// known
+pub fn simulate_indexing(
+    container_config: &ContainerConfig,
+    listing: &[ListedBlob],
+    download_throughput: Option<u64>,
+) -> Result<Simulation> {
+    let ContainerConfig {
+        account,
+        container,
+        filter,
+        hashing_concurrency,
+        sha256,
+        ..
+    } = container_config;
+
+    let sha256 = sha256.unwrap_or(false);
+
+    let matching = listing
+        .iter()
+        .filter(|blob| filter.blob_is_match(&blob.name, blob.size))
+        .collect::<Vec<_>>();
+
+    let bytes_to_hash = matching.iter().map(|blob| blob.size).sum::<u64>();
+
+    // blobs are hashed in parallel, but never faster than the machine has cores for
+    let concurrency = hashing_concurrency
+        .unwrap_or(DEFAULT_HASHING_CONCURRENCY)
+        .min(thread::available_parallelism().map_or(1, |n| n.get()))
+        .max(1);
+
+    let hashing_throughput = measure_hashing_throughput(sha256)? * concurrency as u64;
+
+    let throughput = download_throughput
+        .map_or(hashing_throughput, |download| {
+            download.min(hashing_throughput)
+        })
+        .max(1);
+
+    let index_duration = Duration::from_secs_f64(bytes_to_hash as f64 / throughput as f64);
+
+    let sha256_len = if sha256 {
+        SHA256_EQUIVALENCE_ENTRY_LEN
+    } else {
+        0
+    };
+
+    let entries_len = matching
+        .iter()
+        .map(|blob| {
+            let blob_id_len = (account.len() + container.len() + blob.name.len()) as u64;
+
+            blob_id_len * BLOB_ID_COPIES + BLOB_ENTRIES_LEN + sha256_len
+        })
+        .sum::<u64>();
+
+    Ok(Simulation {
+        listed_blobs: listing.len() as u64,
+        listed_bytes: listing.iter().map(|blob| blob.size).sum(),
+        routes: matching.len() as u64,
+        bytes_to_hash,
+        hashing_throughput,
+        download_throughput,
+        index_duration,
+        db_size: (entries_len as f64 * DB_SPACE_OVERHEAD) as u64,
+    })
+}
+
+/// Bytes per second a single blob is hashed at, with sha256 as well if enabled, as when indexing
+fn measure_hashing_throughput(sha256: bool) -> Result<u64> {
+    let buf = vec![0x5a; THROUGHPUT_SAMPLE_LEN];
+
+    let start = Instant::now();
+    let mut hashed = 0;
+
+    while start.elapsed() < THROUGHPUT_MEASURE_TIME {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(&buf);
+        hasher.finalize();
+
+        if sha256 {
+            Sha256::digest(&buf);
+        }
+
+        hashed += buf.len() as u64;
+    }
+
+    let elapsed = start.elapsed().as_secs_f64();
+
+    if elapsed == 0.0 {
+        bail!("failed to measure hashing throughput");
+    }
+
+    Ok((hashed as f64 / elapsed) as u64)
+}
+
+impl std::fmt::Display for Simulation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Self {
+            listed_blobs,
+            listed_bytes,
+            routes,
+            bytes_to_hash,
+            hashing_throughput,
+            download_throughput,
+            index_duration,
+            db_size,
+        } = self;
+
+        writeln!(
+            f,
+            "Listed blobs:        {listed_blobs} ({})",
+            format_bytes(*listed_bytes)
+        )?;
+        writeln!(f, "Routes:              {routes}")?;
+        writeln!(f, "Bytes to hash:       {}", format_bytes(*bytes_to_hash))?;
+        writeln!(
+            f,
+            "Hashing throughput:  {}/s",
+            format_bytes(*hashing_throughput)
+        )?;
+        if let Some(download_throughput) = download_throughput {
+            writeln!(
+                f,
+                "Download throughput: {}/s",
+                format_bytes(*download_throughput)
+            )?;
+        }
+        writeln!(
+            f,
+            "Index duration:      {}",
+            format_duration(*index_duration)
+        )?;
+        write!(f, "Projected DB size:   {}", format_bytes(*db_size))
+    }
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 6] = ["B", "KiB", "MiB", "GiB", "TiB", "PiB"];
+
+    let mut value = bytes as f64;
+    let mut unit = 0;
+
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    format!("{value:.1} {}", UNITS[unit])
+}
+
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+
+    let (days, hours, minutes, secs) = (
+        secs / 86400,
+        secs % 86400 / 3600,
+        secs % 3600 / 60,
+        secs % 60,
+    );
+
+    match days {
+        0 => format!("{hours}h {minutes}m {secs}s"),
+        days => format!("{days}d {hours}h {minutes}m {secs}s"),
+    }
+}
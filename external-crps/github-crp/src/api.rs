@@ -1,8 +1,9 @@
 pub mod v1;
 
-use std::{net::SocketAddr, sync::Arc};
+use std::{net::SocketAddr, sync::Arc, time::Duration};
 
 use anyhow::Result;
+use api_utils::shutdown;
 use axum::{response::Redirect, routing::get, Router};
 use log::info;
 use utoipa::OpenApi;
@@ -21,9 +22,11 @@ use crate::context::Context;
     ),
     components(
         schemas(
+            api_utils::ApiErrorBody,
+            api_utils::ErrorCode,
             v1::crp::filter::CrpGetFilterResponse,
             v1::crp::routes::CrpGetRoutesResponse,
-            v1::crp::routes::Route,
+            routes::Route,
             v1::status::StatusResponse,
         )
     ),
@@ -33,7 +36,9 @@ use crate::context::Context;
 )]
 struct ApiDoc;
 
-pub async fn start(ctx: Arc<Context>) -> Result<()> {
+/// Serve the API until SIGINT or SIGTERM, then give in-flight requests up to `shutdown_timeout`
+/// to finish
+pub async fn start(ctx: Arc<Context>, shutdown_timeout: Duration) -> Result<()> {
     let addr = SocketAddr::from(([0, 0, 0, 0], ctx.port));
 
     info!("🚀 Starting Github CRP");
@@ -62,9 +67,5 @@ pub async fn start(ctx: Arc<Context>) -> Result<()> {
         .route("/v1/status", get(v1::status::get_status))
         .with_state(ctx);
 
-    axum::Server::bind(&addr)
-        .serve(router.into_make_service())
-        .await?;
-
-    Ok(())
+    shutdown::serve(router, addr, shutdown::signal(), shutdown_timeout).await
 }
@@ -1,7 +1,7 @@
 use std::{str::FromStr, sync::Arc};
 
 use anyhow::Result;
-use api_utils::ApiResult;
+use api_utils::{ApiError, ApiResult, ErrorCode};
 use axum::{
     extract::{Path, State},
     Json,
@@ -9,24 +9,15 @@ use axum::{
 use cid::Cid;
 use routes::{GithubRef, GithubRouteMethod, IntoRoute};
 use serde::Serialize;
-use serde_json::Value;
 use utoipa::ToSchema;
 
-use crate::{context::Context, db::RepoId};
+use crate::{
+    context::Context,
+    db::{Db, RepoId},
+};
 #[derive(Serialize, ToSchema)]
 pub struct CrpGetRoutesResponse {
-    routes: Vec<Route>,
-}
-
-#[derive(Serialize, ToSchema)]
-pub struct Route {
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub crp_id: Option<String>,
-    #[serde(rename = "type")]
-    pub type_: String,
-    pub method: Value,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub metadata: Option<Value>,
+    routes: Vec<routes::Route>,
 }
 
 /// Get CID Routes
@@ -35,7 +26,8 @@ pub struct Route {
     path = "/v1/crp/routes/{cid}",
     tag = "/v1/crp/routes/{cid}",
     responses(
-        (status = 200, description = "Get CID Routes", body = CrpGetRoutesResponse)
+        (status = 200, description = "Get CID Routes", body = CrpGetRoutesResponse),
+        (status = 400, description = "Invalid CID", body = ApiErrorBody)
     )
 )]
 pub async fn get_routes(
@@ -44,42 +36,33 @@ pub async fn get_routes(
 ) -> ApiResult<Json<CrpGetRoutesResponse>> {
     let Context { db, .. } = &*ctx;
 
-    let cid = Cid::from_str(&cid)?;
+    let cid = Cid::from_str(&cid)
+        .map_err(|e| ApiError::new(ErrorCode::CidInvalid, format!("invalid cid={cid}: {e}")))?;
+
+    let routes = get_routes_for_cid(db, &cid)?;
 
+    Ok(Json(CrpGetRoutesResponse { routes }))
+}
+
+/// Routes to the commit with the CID in every repo it's indexed for
+pub fn get_routes_for_cid(db: &Db, cid: &Cid) -> Result<Vec<routes::Route>> {
     let commit = hex::encode(cid.hash().digest());
 
-    let routes = db
-        .get_repos_with_commits_for_cid(&cid)?
+    db.get_repos_with_commits_for_cid(cid)?
         .into_iter()
-        .map(|RepoId { owner, repo }| {
-            Ok(GithubRouteMethod {
-                owner,
-                repo,
-                ref_: GithubRef::Commit(commit.clone()),
-                path: None,
-            }
-            .into_route(None, None)?)
-        })
-        .collect::<Result<Vec<_>>>()?;
-    let routes = routes.into_iter().map(Into::into).collect();
-
-    Ok(Json(CrpGetRoutesResponse { routes }))
+        .map(|repo_id| get_commit_route(repo_id, commit.clone()))
+        .collect()
 }
 
-impl From<routes::Route> for Route {
-    fn from(route: routes::Route) -> Self {
-        let routes::Route {
-            crp_id,
-            type_,
-            method,
-            metadata,
-        } = route;
+/// Route to a commit of a repo, from its hex sha
+pub fn get_commit_route(repo_id: RepoId, commit: String) -> Result<routes::Route> {
+    let RepoId { owner, repo } = repo_id;
 
-        Self {
-            crp_id,
-            type_,
-            method,
-            metadata,
-        }
+    Ok(GithubRouteMethod {
+        owner,
+        repo,
+        ref_: GithubRef::Commit(commit),
+        path: None,
     }
+    .into_route(None, None)?)
 }
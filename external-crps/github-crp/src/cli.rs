@@ -15,6 +15,7 @@ pub struct Args {
 #[derive(Debug, Clone, Parser)]
 pub enum Subcommand {
     Start(Start),
+    Routes(Routes),
 }
 
 /// Start service
@@ -24,6 +25,48 @@ pub struct Start {
     pub common_args: CommonArgs,
 }
 
+/// Query the routes in the DB directly, while the service isn't running
+#[derive(Debug, Clone, Parser)]
+pub struct Routes {
+    #[clap(flatten)]
+    pub common_args: CommonArgs,
+
+    #[clap(subcommand)]
+    pub cmd: RoutesSubcommand,
+}
+
+/// Route queries
+#[derive(Debug, Clone, Parser)]
+pub enum RoutesSubcommand {
+    List(RoutesList),
+    Get(RoutesGet),
+    ForCid(RoutesForCid),
+    Export(RoutesExport),
+}
+
+/// Print tables of the indexed commits and their CIDs
+#[derive(Debug, Clone, Parser)]
+pub struct RoutesList {}
+
+/// Print the route to a commit of a repo as JSON
+#[derive(Debug, Clone, Parser)]
+pub struct RoutesGet {
+    pub owner: String,
+    pub repo: String,
+    /// Hex commit sha
+    pub commit: String,
+}
+
+/// Print the routes for a CID as JSON, as returned by the routes endpoint
+#[derive(Debug, Clone, Parser)]
+pub struct RoutesForCid {
+    pub cid: String,
+}
+
+/// Print the CID and route of every indexed commit, as JSON lines
+#[derive(Debug, Clone, Parser)]
+pub struct RoutesExport {}
+
 /// Common Args
 #[derive(Debug, Clone, Parser)]
 pub struct CommonArgs {
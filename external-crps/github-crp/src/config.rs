@@ -11,6 +11,11 @@ pub struct Config {
     pub db_file: PathBuf,
     pub log_level_default: Option<String>,
     pub log_level_app: Option<String>,
+    /// Include internal callstacks in API error responses (defaults to true in debug builds only)
+    pub expose_callstacks: Option<bool>,
+    /// How long in-flight requests are given to finish on SIGINT or SIGTERM, in milliseconds
+    /// (defaults to 30000)
+    pub shutdown_timeout_ms: Option<u64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -166,6 +166,19 @@ impl Db {
         Ok(table)
     }
 
+    pub fn repo_has_commit(&self, repo_id: RepoId, sha1: Sha1Bytes) -> Result<bool> {
+        let tx = self.db.begin_read()?;
+        let commit_table = tx.open_multimap_table(REPO_COMMIT_TABLE)?;
+
+        for entry in commit_table.get(RepoIdTuple::from(repo_id))? {
+            if entry?.value() == sha1 {
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
+    }
+
     pub fn get_repos_with_commits_for_cid(&self, cid: &Cid) -> Result<Vec<RepoId>> {
         let mut repos = vec![];
 
@@ -1,9 +1,22 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
-use anyhow::Result;
+use anyhow::{anyhow, Context as _, Result};
+use api_utils::shutdown::DEFAULT_SHUTDOWN_TIMEOUT_MS;
+use cid::Cid;
 use clap::Parser;
-use github_crp::{api, cli, config::Config, context::Context, indexers::commit_indexer};
+use github_crp::{
+    api::{
+        self,
+        v1::crp::routes::{get_commit_route, get_routes_for_cid},
+    },
+    cli,
+    config::Config,
+    context::Context,
+    db::{Db, RepoId},
+    indexers::commit_indexer,
+};
 use log::info;
+use serde_json::json;
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -11,6 +24,7 @@ async fn main() -> Result<()> {
 
     match args.cmd {
         cli::Subcommand::Start(args) => start(args).await?,
+        cli::Subcommand::Routes(args) => routes(args)?,
     }
 
     Ok(())
@@ -21,13 +35,99 @@ async fn start(args: cli::Start) -> Result<()> {
 
     github_crp::log::init(&config)?;
 
+    if let Some(expose_callstacks) = config.expose_callstacks {
+        api_utils::error::set_expose_callstacks(expose_callstacks);
+    }
+
     info!("Starting: {config:#?}");
 
+    let shutdown_timeout = Duration::from_millis(
+        config
+            .shutdown_timeout_ms
+            .unwrap_or(DEFAULT_SHUTDOWN_TIMEOUT_MS),
+    );
+
     let ctx = Arc::new(Context::init(config)?);
 
-    tokio::spawn(commit_indexer::update_commit_index(ctx.clone()));
+    let indexer = tokio::spawn(commit_indexer::update_commit_index(ctx.clone()));
+
+    api::start(ctx, shutdown_timeout).await?;
+
+    // stopped between updates or mid-update, whose writes are in a transaction that's dropped
+    indexer.abort();
+    let _ = indexer.await;
+
+    info!("Shut down");
+
+    Ok(())
+}
+
+fn routes(args: cli::Routes) -> Result<()> {
+    let config = Config::from_file(args.common_args.config)?;
+
+    let db = Db::init(config.db_file.clone()).with_context(|| {
+        format!(
+            "failed to open db_file={}, it can't be opened while the service is running",
+            config.db_file.display()
+        )
+    })?;
+
+    match args.cmd {
+        cli::RoutesSubcommand::List(_) => {
+            println!("Repo Commits");
+            println!("{}", db.get_all_repo_commits_ascii_table()?);
+            println!("CID Lookups");
+            println!("{}", db.get_all_cid_lookups_ascii_table()?);
+        }
+        cli::RoutesSubcommand::Get(cli::RoutesGet {
+            owner,
+            repo,
+            commit,
+        }) => {
+            let sha1: [u8; 20] = hex::decode(&commit)
+                .ok()
+                .and_then(|sha1| sha1.try_into().ok())
+                .ok_or_else(|| anyhow!("invalid commit={commit}, expected a hex sha1"))?;
+
+            let repo_id = RepoId { owner, repo };
+
+            if !db.repo_has_commit(repo_id.clone(), sha1)? {
+                return Err(anyhow!(
+                    "commit={commit} not found for repo={}/{}",
+                    repo_id.owner,
+                    repo_id.repo
+                ));
+            }
+
+            let route = get_commit_route(repo_id, hex::encode(sha1))?;
 
-    api::start(ctx).await?;
+            println!("{}", serde_json::to_string_pretty(&route)?);
+        }
+        cli::RoutesSubcommand::ForCid(cli::RoutesForCid { cid }) => {
+            let cid = cid
+                .parse::<Cid>()
+                .map_err(|e| anyhow!("invalid cid={cid}: {e}"))?;
+
+            let routes = get_routes_for_cid(&db, &cid)?;
+
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&json!({ "routes": routes }))?
+            );
+        }
+        cli::RoutesSubcommand::Export(_) => {
+            for lookup in db.get_all_cid_lookups()? {
+                let repo_id = RepoId {
+                    owner: lookup.owner,
+                    repo: lookup.repo,
+                };
+
+                let route = get_commit_route(repo_id, lookup.commit)?;
+
+                println!("{}", json!({ "cid": lookup.cid, "route": route }));
+            }
+        }
+    }
 
     Ok(())
 }